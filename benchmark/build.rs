@@ -0,0 +1,16 @@
+use std::env;
+use std::process::Command;
+
+// records the version of the compiler that builds the benchmarks for the results files
+fn main() {
+    let rustc = env::var("RUSTC").unwrap_or("rustc".to_string());
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|version| version.trim().to_string())
+        .unwrap_or("unknown".to_string());
+    println!("cargo:rustc-env=BENCHMARK_RUSTC_VERSION={}", version);
+    println!("cargo:rerun-if-env-changed=RUSTC");
+}
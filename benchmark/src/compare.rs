@@ -0,0 +1,355 @@
+use crate::plotting::{font_family, render_file, Drawing};
+use crate::results::{read_results, Results};
+use crate::runner::{Measurement, Workload};
+use crate::statistics::{mann_whitney_p_value, summarize, Summary};
+use crate::tuning::thread_count;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::error::Error;
+
+// a change is only reported if it is this unlikely to be noise
+pub const SIGNIFICANCE_LEVEL: f64 = 0.05;
+// relative change of the median runtime that counts as a regression or improvement by default
+pub const DEFAULT_THRESHOLD: f64 = 0.05;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Verdict {
+    Regression,
+    Improvement,
+    Unchanged,
+}
+
+// the same configuration measured in the baseline and the candidate
+#[derive(Debug, PartialEq, Clone)]
+pub struct Comparison {
+    pub experiment: String,
+    pub algorithm: String,
+    pub generator: String,
+    pub size: usize,
+    pub threads: usize,
+    pub baseline: Summary,
+    pub candidate: Summary,
+    // relative change of the median runtime, positive if the candidate is slower
+    pub change: f64,
+    pub p_value: f64,
+    pub verdict: Verdict,
+}
+
+type Key<'a> = (&'a str, &'a str, &'a str, usize, usize);
+
+fn key(m: &Measurement) -> Key<'_> {
+    (&m.experiment, &m.algorithm, &m.generator, m.size, m.threads)
+}
+
+fn runtimes(results: &Results, configuration: Key) -> Vec<f64> {
+    results
+        .measurements
+        .iter()
+        .filter(|m| !m.skipped && key(m) == configuration)
+        .map(|m| m.seconds)
+        .collect()
+}
+
+/**
+    Matches the runs of both result sets by experiment, algorithm, generator, input size and thread
+    count. A configuration is a regression (improvement) if its median runtime got slower (faster)
+    by more than the threshold and the Mann-Whitney U test rejects that both runtimes come from the
+    same distribution. Configurations that were only measured in one of the sets (or skipped because
+    of the time budget) are ignored.
+*/
+pub fn compare(baseline: &Results, candidate: &Results, threshold: f64) -> Vec<Comparison> {
+    let mut configurations: Vec<Key> = vec![];
+    for m in baseline.measurements.iter().filter(|m| !m.skipped) {
+        if !configurations.contains(&key(m)) {
+            configurations.push(key(m));
+        }
+    }
+
+    configurations
+        .into_iter()
+        .filter_map(|configuration| {
+            let before = runtimes(baseline, configuration);
+            let after = runtimes(candidate, configuration);
+            if after.is_empty() {
+                return None;
+            }
+
+            let (experiment, algorithm, generator, size, threads) = configuration;
+            let baseline = summarize(&before);
+            let candidate = summarize(&after);
+            let change = candidate.median / baseline.median - 1.0;
+            let p_value = mann_whitney_p_value(&before, &after);
+            let verdict = if p_value >= SIGNIFICANCE_LEVEL || change.abs() <= threshold {
+                Verdict::Unchanged
+            } else if change > 0.0 {
+                Verdict::Regression
+            } else {
+                Verdict::Improvement
+            };
+
+            Some(Comparison {
+                experiment: experiment.to_string(),
+                algorithm: algorithm.to_string(),
+                generator: generator.to_string(),
+                size,
+                threads,
+                baseline,
+                candidate,
+                change,
+                p_value,
+                verdict,
+            })
+        })
+        .collect()
+}
+
+fn name(workload: &dyn Workload, c: &Comparison) -> String {
+    format!(
+        "{}: {}, {}, n={:.0e}, {} threads",
+        c.experiment,
+        workload.label(&c.algorithm),
+        workload.label(&c.generator),
+        c.size as f64,
+        thread_count(c.threads)
+    )
+}
+
+pub fn print_comparison(workload: &dyn Workload, comparisons: &[Comparison]) {
+    println!(
+        "{:<80}{:>14}{:>14}{:>10}{:>10}",
+        "configuration", "baseline ms", "candidate ms", "change", "p"
+    );
+    for c in comparisons.iter() {
+        let marker = match c.verdict {
+            Verdict::Regression => "  REGRESSION",
+            Verdict::Improvement => "  improvement",
+            Verdict::Unchanged => "",
+        };
+        println!(
+            "{:<80}{:>14.3}{:>14.3}{:>9.1}%{:>10.3}{}",
+            name(workload, c),
+            c.baseline.median * 1000.0,
+            c.candidate.median * 1000.0,
+            c.change * 100.0,
+            c.p_value,
+            marker
+        );
+    }
+}
+
+// the relative change of every configuration as a horizontal bar
+struct ComparisonPlot<'a> {
+    workload: &'a dyn Workload,
+    comparisons: &'a [Comparison],
+    threshold: f64,
+}
+
+impl Drawing for ComparisonPlot<'_> {
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let (comparisons, threshold) = (self.comparisons, self.threshold);
+        let rows = comparisons.len().max(1);
+        let limit = comparisons
+            .iter()
+            .map(|c| c.change.abs() * 100.0)
+            .fold(threshold * 200.0, f64::max)
+            * 1.1;
+        let font = font_family("sans-serif");
+
+        root.fill(&WHITE)?;
+        let root = root.margin(10, 10, 10, 10);
+
+        let mut chart = ChartBuilder::on(&root)
+            .caption("Change of the Median Runtime", (font, 26).into_font())
+            .x_label_area_size(30)
+            .y_label_area_size(420)
+            .build_cartesian_2d(-limit..limit, 0.0..rows as f64)?;
+
+        chart
+            .configure_mesh()
+            .disable_y_mesh()
+            .y_labels(rows * 2 + 1)
+            .y_label_formatter(&|y| {
+                // labels only in the middle of the bars
+                let row = *y - 0.5;
+                if row.fract().abs() > 1e-9 || row < 0.0 || row as usize >= comparisons.len() {
+                    return String::new();
+                }
+                name(
+                    self.workload,
+                    &comparisons[comparisons.len() - 1 - row as usize],
+                )
+            })
+            .x_label_formatter(&|x| format!("{:.0}%", x))
+            .x_desc("Change of the candidate relative to the baseline")
+            .draw()?;
+
+        chart.draw_series(comparisons.iter().enumerate().map(|(i, c)| {
+            let row = (comparisons.len() - 1 - i) as f64;
+            let color = match c.verdict {
+                Verdict::Regression => RED.filled(),
+                Verdict::Improvement => GREEN.filled(),
+                Verdict::Unchanged => RGBColor(160, 160, 160).filled(),
+            };
+            Rectangle::new([(0.0, row + 0.15), (c.change * 100.0, row + 0.85)], color)
+        }))?;
+
+        for x in [-threshold * 100.0, threshold * 100.0] {
+            chart.draw_series(DashedLineSeries::new(
+                vec![(x, 0.0), (x, rows as f64)],
+                5,
+                5,
+                BLACK.into(),
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+/**
+    Draws the relative change of every configuration as a horizontal bar, regressions in red and
+    improvements in green. The dashed lines mark the threshold. The path may be a PNG or SVG file.
+*/
+pub fn plot_comparison(
+    workload: &dyn Workload,
+    comparisons: &[Comparison],
+    threshold: f64,
+    path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let rows = comparisons.len().max(1) as u32;
+    let drawing = ComparisonPlot {
+        workload,
+        comparisons,
+        threshold,
+    };
+    render_file(path, (1000, 100 + 22 * rows), &drawing)
+}
+
+/**
+    Compares two results files, prints the table and draws the diff plot if a path is given.
+    Returns the comparisons so that the caller can fail on regressions.
+*/
+pub fn compare_files(
+    workload: &dyn Workload,
+    baseline: &str,
+    candidate: &str,
+    threshold: f64,
+    plot_path: Option<&str>,
+) -> Result<Vec<Comparison>, Box<dyn Error>> {
+    let baseline = read_results(baseline)?;
+    let candidate = read_results(candidate)?;
+    println!(
+        "baseline: commit {}, candidate: commit {}",
+        baseline.environment.commit, candidate.environment.commit
+    );
+
+    let comparisons = compare(&baseline, &candidate, threshold);
+    print_comparison(workload, &comparisons);
+    if let Some(path) = plot_path {
+        plot_comparison(workload, &comparisons, threshold, path)?;
+    }
+    Ok(comparisons)
+}
+
+/**
+    The compare command of the projects: `compare <baseline.csv> <candidate.csv> [threshold]
+    [plot.png|plot.svg]`. Returns whether there was any regression.
+*/
+pub fn compare_command(workload: &dyn Workload, args: &[String]) -> Result<bool, Box<dyn Error>> {
+    if args.len() < 2 {
+        return Err("usage: compare <baseline.csv> <candidate.csv> [threshold] [plot.png]".into());
+    }
+    let threshold = match args.get(2) {
+        Some(threshold) => threshold
+            .parse()
+            .map_err(|_| format!("invalid threshold {}", threshold))?,
+        None => DEFAULT_THRESHOLD,
+    };
+
+    let comparisons = compare_files(
+        workload,
+        &args[0],
+        &args[1],
+        threshold,
+        args.get(3).map(String::as_str),
+    )?;
+    Ok(comparisons.iter().any(|c| c.verdict == Verdict::Regression))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compare::{compare, Verdict};
+    use crate::results::{Environment, Results};
+    use crate::runner::Measurement;
+
+    fn results(runtimes: &[(&str, f64)]) -> Results {
+        let measurements = runtimes
+            .iter()
+            .flat_map(|(algorithm, seconds)| {
+                (0..10).map(move |repetition| Measurement {
+                    experiment: "test".to_string(),
+                    algorithm: algorithm.to_string(),
+                    generator: "random".to_string(),
+                    seed: repetition as u64,
+                    size: 1000,
+                    threads: 4,
+                    repetition,
+                    // a little noise so that the runs are not all equal
+                    seconds: seconds * (1.0 + repetition as f64 * 0.001),
+                    output_size: None,
+                    outlier: false,
+                    skipped: false,
+                    operations: None,
+                })
+            })
+            .collect();
+        Results {
+            environment: Environment {
+                cpus: 4,
+                rustc: "rustc".to_string(),
+                commit: "abc".to_string(),
+            },
+            measurements,
+        }
+    }
+
+    #[test]
+    fn verdicts() {
+        let baseline = results(&[
+            ("slower", 1.0),
+            ("faster", 1.0),
+            ("same", 1.0),
+            ("gone", 1.0),
+        ]);
+        let candidate = results(&[("slower", 1.2), ("faster", 0.5), ("same", 1.01)]);
+        let comparisons = compare(&baseline, &candidate, 0.05);
+
+        let verdicts: Vec<(&str, Verdict)> = comparisons
+            .iter()
+            .map(|c| (c.algorithm.as_str(), c.verdict))
+            .collect();
+        assert_eq!(
+            verdicts,
+            vec![
+                ("slower", Verdict::Regression),
+                ("faster", Verdict::Improvement),
+                ("same", Verdict::Unchanged),
+            ]
+        );
+        assert!((comparisons[0].change - 0.2).abs() < 1e-9);
+        assert!(comparisons[0].p_value < 0.001);
+    }
+
+    #[test]
+    fn small_changes_need_significance() {
+        // 1% slower is above a 0.5% threshold, but one run per side is no evidence
+        let mut baseline = results(&[("a", 1.0)]);
+        let mut candidate = results(&[("a", 1.01)]);
+        baseline.measurements.truncate(1);
+        candidate.measurements.truncate(1);
+        let comparisons = compare(&baseline, &candidate, 0.005);
+        assert_eq!(comparisons[0].verdict, Verdict::Unchanged);
+    }
+}
@@ -0,0 +1,185 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+// the operations whose number is compared to the theoretical bounds of the algorithms
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Counter {
+    // comparisons of two elements
+    Comparisons,
+    // elements written to the output or the scratch space
+    Writes,
+    // calls of the orientation test of three points
+    OrientationTests,
+    // steps of the searches for the tangents between two hulls
+    TangentSteps,
+}
+
+// the number of every counted operation of one run
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Operations {
+    pub comparisons: u64,
+    pub writes: u64,
+    pub orientation_tests: u64,
+    pub tangent_steps: u64,
+}
+
+impl Operations {
+    pub fn get(&self, counter: Counter) -> u64 {
+        match counter {
+            Counter::Comparisons => self.comparisons,
+            Counter::Writes => self.writes,
+            Counter::OrientationTests => self.orientation_tests,
+            Counter::TangentSteps => self.tangent_steps,
+        }
+    }
+
+    fn add(&mut self, counter: Counter, n: u64) {
+        match counter {
+            Counter::Comparisons => self.comparisons += n,
+            Counter::Writes => self.writes += n,
+            Counter::OrientationTests => self.orientation_tests += n,
+            Counter::TangentSteps => self.tangent_steps += n,
+        }
+    }
+
+    // all operations, the unit of work of a simulated run
+    pub fn total(&self) -> u64 {
+        self.comparisons + self.writes + self.orientation_tests + self.tangent_steps
+    }
+}
+
+// number of runs of counted and counted_locally in progress, the only thing count looks at while
+// nothing is counted
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+// whether counted is running
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static COUNTS: [AtomicU64; 4] = [const { AtomicU64::new(0) }; 4];
+// only one counted run at a time, e.g. when tests run in parallel
+static COUNTING: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    static LOCAL: Cell<Option<Operations>> = const { Cell::new(None) };
+}
+
+/**
+    Adds n operations of the counter while `counted` or `counted_locally` runs, does nothing
+    otherwise. Hot loops should count locally and call this once at the end. The check whether
+    anything is counted is inlined into the caller, the counting itself is not.
+*/
+#[inline]
+pub fn count(counter: Counter, n: u64) {
+    if ACTIVE.load(Ordering::Relaxed) > 0 {
+        add(counter, n);
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn add(counter: Counter, n: u64) {
+    if ENABLED.load(Ordering::Relaxed) {
+        COUNTS[counter as usize].fetch_add(n, Ordering::Relaxed);
+    }
+    LOCAL.with(|local| {
+        if let Some(mut operations) = local.get() {
+            operations.add(counter, n);
+            local.set(Some(operations));
+        }
+    });
+}
+
+/**
+    Runs f and returns the operations counted by all threads while it ran. The counters are
+    global, so operations of other threads running at the same time are included.
+*/
+pub fn counted<R>(f: impl FnOnce() -> R) -> (R, Operations) {
+    let _counting = COUNTING
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    for counter in COUNTS.iter() {
+        counter.store(0, Ordering::Relaxed);
+    }
+    ENABLED.store(true, Ordering::Relaxed);
+    ACTIVE.fetch_add(1, Ordering::Relaxed);
+
+    let result = f();
+
+    ACTIVE.fetch_sub(1, Ordering::Relaxed);
+    ENABLED.store(false, Ordering::Relaxed);
+    let get = |counter: Counter| COUNTS[counter as usize].load(Ordering::Relaxed);
+    let operations = Operations {
+        comparisons: get(Counter::Comparisons),
+        writes: get(Counter::Writes),
+        orientation_tests: get(Counter::OrientationTests),
+        tangent_steps: get(Counter::TangentSteps),
+    };
+    (result, operations)
+}
+
+/**
+    Runs f and returns only the operations of the calling thread, so unlike `counted` the result
+    does not depend on other threads. The operations of a nested call are not added to the outer
+    one.
+*/
+pub fn counted_locally<R>(f: impl FnOnce() -> R) -> (R, Operations) {
+    ACTIVE.fetch_add(1, Ordering::Relaxed);
+    let outer = LOCAL.with(|local| local.replace(Some(Operations::default())));
+
+    let result = f();
+
+    let operations = LOCAL.with(|local| local.replace(outer)).unwrap_or_default();
+    ACTIVE.fetch_sub(1, Ordering::Relaxed);
+    (result, operations)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::counters::{count, counted, counted_locally, Counter, Operations};
+
+    #[test]
+    fn counts_all_threads_while_counting() {
+        count(Counter::Comparisons, 100);
+        let (result, operations) = counted(|| {
+            std::thread::scope(|scope| {
+                scope.spawn(|| count(Counter::Comparisons, 3));
+                count(Counter::Comparisons, 2);
+                count(Counter::TangentSteps, 1);
+            });
+            "done"
+        });
+        count(Counter::Writes, 100);
+
+        assert_eq!(result, "done");
+        assert_eq!(
+            operations,
+            Operations {
+                comparisons: 5,
+                tangent_steps: 1,
+                ..Operations::default()
+            }
+        );
+        assert_eq!(operations.get(Counter::Comparisons), 5);
+        // every counted run starts from zero
+        assert_eq!(counted(|| ()).1, Operations::default());
+    }
+
+    #[test]
+    fn counts_only_the_own_thread_locally() {
+        let (_, operations) = counted_locally(|| {
+            count(Counter::Writes, 4);
+            std::thread::scope(|scope| {
+                scope.spawn(|| count(Counter::Writes, 100));
+            });
+            let (_, inner) = counted_locally(|| count(Counter::OrientationTests, 2));
+            assert_eq!(inner.total(), 2);
+            count(Counter::Writes, 1);
+        });
+        assert_eq!(
+            operations,
+            Operations {
+                writes: 5,
+                ..Operations::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,12 @@
+pub mod compare;
+pub mod counters;
+pub mod plotting;
+pub mod report;
+pub mod results;
+pub mod runner;
+pub mod scaling;
+pub mod spec;
+pub mod statistics;
+pub mod trace;
+pub mod tuning;
+pub mod workspan;
@@ -0,0 +1,710 @@
+use crate::runner::Measurement;
+use crate::scaling::scaling_series;
+use crate::spec::{
+    ErrorDisplay, ExperimentSpec, Normalization, PlotSpec, SeriesKey, StyleSpec, XValue, YValue,
+};
+use crate::statistics::summarize;
+use crate::tuning::{thread_count, AUTO_THREADS};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::ORANGE;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// file format of a plot, chosen by the extension of its path
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    Png,
+    Svg,
+}
+
+impl Format {
+    pub fn of(path: &str) -> Result<Format, String> {
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("png") => Ok(Format::Png),
+            Some(e) if e.eq_ignore_ascii_case("svg") => Ok(Format::Svg),
+            _ => Err(format!("{} is neither a .png nor a .svg file", path)),
+        }
+    }
+}
+
+/**
+    A figure that can be drawn onto any plotters backend, so that the same drawing code writes PNG
+    files, SVG files and the SVG images embedded in the HTML report.
+*/
+pub trait Drawing {
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static;
+}
+
+// draws into a PNG or SVG file depending on the extension of the path
+pub fn render_file(
+    path: &str,
+    size: (u32, u32),
+    drawing: &impl Drawing,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match Format::of(path)? {
+        Format::Png => {
+            let root = BitMapBackend::new(path, size).into_drawing_area();
+            drawing.draw(&root)?;
+            root.present()?;
+        }
+        Format::Svg => {
+            let root = SVGBackend::new(path, size).into_drawing_area();
+            drawing.draw(&root)?;
+            root.present()?;
+        }
+    }
+    Ok(())
+}
+
+pub fn render_svg(size: (u32, u32), drawing: &impl Drawing) -> Result<String, Box<dyn Error>> {
+    let mut svg = String::new();
+    {
+        let root = SVGBackend::with_string(&mut svg, size).into_drawing_area();
+        drawing.draw(&root)?;
+        root.present()?;
+    }
+    Ok(svg)
+}
+
+/**
+    Draws the frames of an animation. A path ending in .gif gives an animated GIF with the given
+    delay between frames, any other path is a directory that gets the numbered frames
+    frame_0001.png, frame_0002.png, ...
+*/
+pub fn render_animation(
+    path: &str,
+    size: (u32, u32),
+    frames: &[impl Drawing],
+    delay_ms: u32,
+) -> Result<(), Box<dyn Error>> {
+    if path.to_lowercase().ends_with(".gif") {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let root = BitMapBackend::gif(path, size, delay_ms)?.into_drawing_area();
+        for frame in frames {
+            frame.draw(&root)?;
+            root.present()?;
+        }
+    } else {
+        fs::create_dir_all(path)?;
+        for (i, frame) in frames.iter().enumerate() {
+            render_file(&format!("{}/frame_{:04}.png", path, i + 1), size, frame)?;
+        }
+    }
+    Ok(())
+}
+
+/**
+    The font family of the given name if it is installed, otherwise the generic sans-serif family,
+    so that a missing font (e.g. on a CI machine) does not fail the drawing.
+*/
+pub fn font_family(name: &str) -> FontFamily<'_> {
+    let family = FontFamily::from(name);
+    if FontDesc::new(family, 12.0, FontStyle::Normal)
+        .box_size("0")
+        .is_ok()
+    {
+        family
+    } else {
+        FontFamily::SansSerif
+    }
+}
+
+// the median of the runs of one input size (or thread count) and its 95% confidence interval
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SeriesPoint {
+    pub x: usize,
+    pub value: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+// one line of a plot
+#[derive(Debug, PartialEq, Clone)]
+pub struct Series {
+    pub name: String,
+    pub points: Vec<SeriesPoint>,
+    // fitted model drawn as a dashed line, empty if there is none
+    pub fit: Vec<(usize, f64)>,
+}
+
+pub fn color(i: usize) -> RGBColor {
+    let colors = [RED, GREEN, BLUE, ORANGE, BLACK];
+    if i < colors.len() {
+        colors[i]
+    } else {
+        let (r, g, b) = Palette99::pick(i).rgb();
+        RGBColor(r, g, b)
+    }
+}
+
+type SeriesValues = (String, Vec<(usize, Vec<f64>)>);
+
+// the measured runs selected by the plot, runs skipped by the time budget have no runtime
+pub(crate) fn matches(plot: &PlotSpec, m: &Measurement) -> bool {
+    !m.skipped && selects(plot, m)
+}
+
+fn selects(plot: &PlotSpec, m: &Measurement) -> bool {
+    m.experiment == plot.experiment
+        && plot.algorithm.as_ref().is_none_or(|a| *a == m.algorithm)
+        && plot.generator.as_ref().is_none_or(|g| *g == m.generator)
+        && plot.threads.is_none_or(|t| t == m.threads)
+        // "auto" has no place on a thread count axis
+        && !(plot.x == XValue::Threads && m.threads == AUTO_THREADS)
+}
+
+pub(crate) fn series_name(
+    plot: &PlotSpec,
+    experiment: &ExperimentSpec,
+    m: &Measurement,
+    label: &dyn Fn(&str) -> String,
+) -> String {
+    plot.series
+        .iter()
+        .map(|key| match key {
+            SeriesKey::Algorithm => label(&m.algorithm),
+            SeriesKey::Generator => label(&m.generator),
+            SeriesKey::Threads => format!("{} threads", thread_count(m.threads)),
+            SeriesKey::Size if experiment.weak_scaling => {
+                format!("n/p={:.0e}", (m.size / m.threads) as f64)
+            }
+            SeriesKey::Size => format!("n={:.0e}", m.size as f64),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/**
+    Groups the measurements selected by the plot into series and takes the median of the
+    repetitions of each input size (or thread count). Series and points keep the order in which
+    they were measured.
+*/
+pub fn collect_series(
+    plot: &PlotSpec,
+    experiment: &ExperimentSpec,
+    results: &[Measurement],
+    label: &dyn Fn(&str) -> String,
+) -> Vec<Series> {
+    if plot.y.is_scaling() {
+        return scaling_series(plot, experiment, results, label);
+    }
+
+    // (name, [(x, values)]) in order of appearance
+    let mut groups: Vec<SeriesValues> = vec![];
+
+    for m in results.iter().filter(|m| matches(plot, m)) {
+        let value = match (plot.y, plot.y.counter()) {
+            (_, Some(counter)) => match m.operations {
+                Some(operations) => {
+                    operations.get(counter) as f64 / plot.normalize.divisor(m.size, m.output_size)
+                }
+                // e.g. results written before the experiment counted operations
+                None => continue,
+            },
+            (YValue::TimePerElement, _) => m.seconds * 1000.0 / m.size as f64,
+            (YValue::Time, _) => m.seconds * 1000.0,
+            (YValue::OutputSize, _) => m.output_size.unwrap_or(0) as f64,
+            _ => unreachable!("scaling values are collected by scaling_series"),
+        };
+        let x = match plot.x {
+            XValue::Size => m.size,
+            XValue::Threads => m.threads,
+        };
+
+        let name = series_name(plot, experiment, m, label);
+        let group = match groups.iter().position(|(n, _)| *n == name) {
+            Some(i) => &mut groups[i].1,
+            None => {
+                groups.push((name, vec![]));
+                &mut groups.last_mut().unwrap().1
+            }
+        };
+        match group.iter_mut().find(|(point, _)| *point == x) {
+            Some((_, values)) => values.push(value),
+            None => group.push((x, vec![value])),
+        }
+    }
+
+    // the first input size (or thread count) of each series that exceeded the time budget
+    let mut skipped: Vec<(String, usize)> = vec![];
+    for m in results.iter().filter(|m| m.skipped && selects(plot, m)) {
+        let name = series_name(plot, experiment, m, label);
+        let x = match plot.x {
+            XValue::Size => m.size,
+            XValue::Threads => m.threads,
+        };
+        match skipped.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, first)) => *first = (*first).min(x),
+            None => skipped.push((name, x)),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(name, points)| Series {
+            name: match skipped.iter().find(|(n, _)| *n == name) {
+                Some((_, x)) if plot.x == XValue::Size => {
+                    format!("{} (skipped from n={:.0e})", name, *x as f64)
+                }
+                Some((_, x)) => format!("{} (skipped from {} threads)", name, x),
+                None => name,
+            },
+            points: points
+                .into_iter()
+                .map(|(x, values)| {
+                    let summary = summarize(&values);
+                    SeriesPoint {
+                        x,
+                        value: summary.median,
+                        low: summary.ci_low,
+                        high: summary.ci_high,
+                    }
+                })
+                .collect(),
+            fit: vec![],
+        })
+        .collect()
+}
+
+fn y_description(plot: &PlotSpec) -> String {
+    let description = match plot.y {
+        YValue::TimePerElement => "Median Runtime in milliseconds / input size",
+        YValue::Time => "Median Runtime in milliseconds",
+        YValue::OutputSize => "Median Output Size",
+        YValue::Speedup => "Speedup over one thread",
+        YValue::Efficiency => "Parallel Efficiency",
+        YValue::KarpFlatt => "Karp-Flatt serial fraction",
+        YValue::Comparisons => "Median Comparisons",
+        YValue::Writes => "Median Writes",
+        YValue::OrientationTests => "Median Orientation Tests",
+        YValue::TangentSteps => "Median Tangent Search Steps",
+    };
+    let description = match plot.normalize {
+        Normalization::None => description.to_string(),
+        Normalization::N => format!("{} / n", description),
+        Normalization::NLogN => format!("{} / n log n", description),
+        Normalization::NH => format!("{} / n h", description),
+    };
+    if plot.log_y {
+        format!("{} (logarithmic scale)", description)
+    } else {
+        description
+    }
+}
+
+// range of the values with some space around them
+fn fit_range(values: impl Iterator<Item = f64>, log: bool) -> (f64, f64) {
+    let (min, max) = values.fold((f64::MAX, f64::MIN), |(min, max), v| {
+        (min.min(v), max.max(v))
+    });
+    if min > max {
+        (0.0, 1.0)
+    } else if log {
+        (min.max(f64::MIN_POSITIVE) / 2.0, max * 2.0)
+    } else {
+        (
+            min - (max - min) * 0.1,
+            max + (max - min) * 0.1 + f64::EPSILON,
+        )
+    }
+}
+
+// the series of a plot spec as a line plot with a logarithmic x axis
+struct SeriesPlot<'a> {
+    plot: &'a PlotSpec,
+    series: &'a [Series],
+    style: &'a StyleSpec,
+}
+
+impl Drawing for SeriesPlot<'_> {
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let (plot, series) = (self.plot, self.series);
+        let font = font_family(&self.style.font);
+        let xs = series.iter().flat_map(|s| s.points.iter().map(|p| p.x));
+        let x_first = xs.clone().min().unwrap_or(1).max(1) as f64 * 0.8;
+        let x_last = xs.max().unwrap_or(10).max(1) as f64 * 1.2;
+        let (x_format, x_description): (fn(&f64) -> String, &str) = match plot.x {
+            XValue::Size => (|x| format!("{:.0e}", x), "Input Size (logarithmic scale)"),
+            XValue::Threads => (|x| format!("{}", x), "Threads (logarithmic scale)"),
+        };
+        // speedups and fractions are close to 1, runtimes span many orders of magnitude
+        let y_format: fn(&f64) -> String = if plot.y.is_scaling() {
+            |y| format!("{:.2}", y)
+        } else {
+            |y| format!("{:.1e}", y)
+        };
+
+        let (y_first, y_last) = match plot.y_range {
+            Some([first, last]) => (first, last),
+            None => fit_range(
+                series
+                    .iter()
+                    .flat_map(|s| s.points.iter().flat_map(|p| [p.low, p.high])),
+                plot.log_y,
+            ),
+        };
+
+        root.fill(&WHITE)?;
+        let root = root.margin(10, 10, 10, 10);
+
+        let mut chart = ChartBuilder::on(&root);
+        chart
+            .caption(plot.title.as_str(), (font, 26).into_font())
+            .x_label_area_size(30)
+            .y_label_area_size(55);
+
+        // log and linear y axes are different coordinate types, so the drawing is shared by a macro
+        macro_rules! draw {
+            ($chart:expr) => {{
+                let mut chart = $chart;
+                chart
+                    .configure_mesh()
+                    .max_light_lines(0)
+                    .label_style((font, 12))
+                    .axis_desc_style((font, 15))
+                    .y_label_formatter(&y_format)
+                    .y_desc(y_description(plot))
+                    .x_label_formatter(&x_format)
+                    .x_desc(x_description)
+                    .draw()?;
+
+                for (i, s) in series.iter().enumerate() {
+                    let color = color(i);
+                    match plot.errors {
+                        ErrorDisplay::None => {}
+                        ErrorDisplay::Bars => {
+                            chart.draw_series(s.points.iter().map(|p| {
+                                ErrorBar::new_vertical(p.x as f64, p.low, p.value, p.high, color, 6)
+                            }))?;
+                        }
+                        ErrorDisplay::Band => {
+                            // upper bounds from left to right, then lower bounds back
+                            let outline = s
+                                .points
+                                .iter()
+                                .map(|p| (p.x as f64, p.high))
+                                .chain(s.points.iter().rev().map(|p| (p.x as f64, p.low)));
+                            chart.draw_series(std::iter::once(Polygon::new(
+                                outline.collect::<Vec<_>>(),
+                                color.mix(0.2),
+                            )))?;
+                        }
+                    }
+                    if !s.fit.is_empty() {
+                        chart.draw_series(DashedLineSeries::new(
+                            s.fit.iter().map(|(x, y)| (*x as f64, *y)),
+                            6,
+                            4,
+                            color.into(),
+                        ))?;
+                    }
+                    chart
+                        .draw_series(
+                            LineSeries::new(s.points.iter().map(|p| (p.x as f64, p.value)), color)
+                                .point_size(2),
+                        )?
+                        .label(s.name.clone())
+                        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+                }
+
+                chart
+                    .configure_series_labels()
+                    .position(SeriesLabelPosition::UpperLeft)
+                    .label_font((font, 12))
+                    .border_style(BLACK)
+                    .background_style(WHITE.mix(0.75))
+                    .draw()?;
+            }};
+        }
+
+        if plot.log_y {
+            draw!(chart.build_cartesian_2d(
+                (x_first..x_last).log_scale(),
+                (y_first..y_last).log_scale()
+            )?);
+        } else {
+            draw!(chart.build_cartesian_2d((x_first..x_last).log_scale(), y_first..y_last)?);
+        }
+
+        Ok(())
+    }
+}
+
+/**
+    Draws the series as a line plot with a logarithmic x axis into the file of the plot, in the
+    same style as the plots of the original experiments.
+*/
+pub fn draw_series(
+    plot: &PlotSpec,
+    series: &[Series],
+    style: &StyleSpec,
+) -> Result<(), Box<dyn Error>> {
+    let drawing = SeriesPlot {
+        plot,
+        series,
+        style,
+    };
+    render_file(&plot.path, plot.size(style), &drawing)
+}
+
+// the plot as an SVG image, e.g. for the HTML report
+pub fn series_svg(
+    plot: &PlotSpec,
+    series: &[Series],
+    style: &StyleSpec,
+) -> Result<String, Box<dyn Error>> {
+    let drawing = SeriesPlot {
+        plot,
+        series,
+        style,
+    };
+    render_svg(plot.size(style), &drawing)
+}
+
+pub fn draw_plot(
+    plot: &PlotSpec,
+    experiment: &ExperimentSpec,
+    results: &[Measurement],
+    label: &dyn Fn(&str) -> String,
+    style: &StyleSpec,
+) -> Result<(), Box<dyn Error>> {
+    draw_series(plot, &plot_series(plot, experiment, results, label)?, style)
+}
+
+// like collect_series, but a plot without any measurement is an error
+pub fn plot_series(
+    plot: &PlotSpec,
+    experiment: &ExperimentSpec,
+    results: &[Measurement],
+    label: &dyn Fn(&str) -> String,
+) -> Result<Vec<Series>, Box<dyn Error>> {
+    let series = collect_series(plot, experiment, results, label);
+    if series.is_empty() {
+        return Err(format!("plot {} has no measurements", plot.title).into());
+    }
+    Ok(series)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::counters::Operations;
+    use crate::plotting::{collect_series, series_svg, Format, Series, SeriesPoint};
+    use crate::runner::Measurement;
+    use crate::spec::{
+        BenchmarkSpec, ErrorDisplay, ExperimentSpec, Normalization, PlotSpec, SeriesKey, StyleSpec,
+        XValue, YValue,
+    };
+    use crate::tuning::AUTO_THREADS;
+
+    fn measurement(algorithm: &str, size: usize, threads: usize, seconds: f64) -> Measurement {
+        Measurement {
+            experiment: "test".to_string(),
+            algorithm: algorithm.to_string(),
+            generator: "random".to_string(),
+            seed: 0,
+            size,
+            threads,
+            repetition: 0,
+            seconds,
+            output_size: Some(size / 10),
+            outlier: false,
+            skipped: false,
+            operations: None,
+        }
+    }
+
+    fn plot_spec() -> PlotSpec {
+        PlotSpec {
+            experiment: "test".to_string(),
+            title: "Test".to_string(),
+            path: "test.png".to_string(),
+            width: None,
+            height: None,
+            series: vec![SeriesKey::Threads],
+            x: XValue::Size,
+            algorithm: Some("a".to_string()),
+            generator: None,
+            threads: None,
+            y: YValue::Time,
+            normalize: Normalization::None,
+            y_range: None,
+            log_y: false,
+            errors: ErrorDisplay::None,
+            fit: false,
+        }
+    }
+
+    fn experiment() -> ExperimentSpec {
+        let spec = BenchmarkSpec::parse(
+            r#"
+            [[experiment]]
+            name = "test"
+            algorithms = ["a", "b"]
+            generators = ["random"]
+            sizes = [10, 100]
+            "#,
+        )
+        .unwrap();
+        spec.experiments[0].clone()
+    }
+
+    fn point(x: usize, value: f64, low: f64, high: f64) -> SeriesPoint {
+        SeriesPoint {
+            x,
+            value,
+            low,
+            high,
+        }
+    }
+
+    #[test]
+    fn series_median_of_repetitions_and_filter() {
+        let results = vec![
+            measurement("a", 10, 1, 1.0),
+            measurement("a", 10, 1, 3.0),
+            measurement("a", 10, 1, 2.5),
+            measurement("a", 100, 1, 5.0),
+            measurement("a", 10, 2, 1.0),
+            measurement("b", 10, 1, 7.0),
+        ];
+        let series = collect_series(&plot_spec(), &experiment(), &results, &|name| {
+            name.to_string()
+        });
+        assert_eq!(
+            series,
+            vec![
+                Series {
+                    name: "1 threads".to_string(),
+                    points: vec![
+                        point(10, 2500.0, 1000.0, 3000.0),
+                        point(100, 5000.0, 5000.0, 5000.0)
+                    ],
+                    fit: vec![],
+                },
+                Series {
+                    name: "2 threads".to_string(),
+                    points: vec![point(10, 1000.0, 1000.0, 1000.0)],
+                    fit: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn series_output_size_with_labels() {
+        let results = vec![measurement("a", 100, 1, 1.0)];
+        let mut plot = plot_spec();
+        plot.y = YValue::OutputSize;
+        plot.series = vec![SeriesKey::Algorithm, SeriesKey::Generator];
+        let series = collect_series(&plot, &experiment(), &results, &|name| name.to_uppercase());
+        assert_eq!(series[0].name, "A, RANDOM");
+        assert_eq!(series[0].points, vec![point(100, 10.0, 10.0, 10.0)]);
+    }
+
+    #[test]
+    fn series_over_threads() {
+        let results = vec![
+            measurement("a", 10, 1, 4.0),
+            measurement("a", 10, 2, 2.0),
+            measurement("a", 100, 1, 8.0),
+            // left out, "auto" is not a point on the thread axis
+            measurement("a", 10, AUTO_THREADS, 1.0),
+        ];
+        let mut plot = plot_spec();
+        plot.x = XValue::Threads;
+        plot.series = vec![SeriesKey::Size];
+        let series = collect_series(&plot, &experiment(), &results, &|name| name.to_string());
+        assert_eq!(series[0].name, "n=1e1");
+        assert_eq!(
+            series[0].points,
+            vec![
+                point(1, 4000.0, 4000.0, 4000.0),
+                point(2, 2000.0, 2000.0, 2000.0)
+            ]
+        );
+        assert_eq!(series[1].name, "n=1e2");
+    }
+
+    #[test]
+    fn normalized_operation_counts() {
+        let counted = |size: usize, comparisons| Measurement {
+            operations: Some(Operations {
+                comparisons,
+                ..Operations::default()
+            }),
+            ..measurement("a", size, 1, 1.0)
+        };
+        // the measurement without counts is left out instead of plotted as zero
+        let results = vec![
+            counted(16, 64),
+            counted(1024, 5120),
+            measurement("a", 1024, 1, 1.0),
+        ];
+        let mut plot = plot_spec();
+        plot.y = YValue::Comparisons;
+        plot.normalize = Normalization::NLogN;
+        let series = collect_series(&plot, &experiment(), &results, &|name| name.to_string());
+        assert_eq!(
+            series[0].points,
+            vec![point(16, 1.0, 1.0, 1.0), point(1024, 0.5, 0.5, 0.5)]
+        );
+    }
+
+    #[test]
+    fn skipped_sizes_are_named() {
+        let mut skipped = measurement("a", 1000, 1, f64::NAN);
+        skipped.skipped = true;
+        let results = vec![
+            measurement("a", 10, 1, 1.0),
+            measurement("a", 100, 1, 2.0),
+            skipped.clone(),
+            Measurement {
+                size: 10000,
+                ..skipped
+            },
+        ];
+        let series = collect_series(&plot_spec(), &experiment(), &results, &|name| {
+            name.to_string()
+        });
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].name, "1 threads (skipped from n=1e3)");
+        assert_eq!(series[0].points.len(), 2);
+    }
+
+    #[test]
+    fn formats_and_svg_output() {
+        assert_eq!(Format::of("plots/a.png"), Ok(Format::Png));
+        assert_eq!(Format::of("a.SVG"), Ok(Format::Svg));
+        assert!(Format::of("a.pdf").is_err());
+        assert!(Format::of("plots").is_err());
+
+        let series = vec![Series {
+            name: "1 threads".to_string(),
+            points: vec![point(10, 1.0, 0.5, 1.5), point(100, 2.0, 1.5, 2.5)],
+            fit: vec![],
+        }];
+        let mut plot = plot_spec();
+        plot.width = Some(300);
+        // a font that is not installed falls back to sans-serif
+        let style = StyleSpec {
+            font: "no such font".to_string(),
+            ..StyleSpec::default()
+        };
+        let svg = series_svg(&plot, &series, &style).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("width=\"300\" height=\"480\""));
+        assert!(svg.contains("1 threads"));
+    }
+}
@@ -0,0 +1,254 @@
+use crate::plotting::{plot_series, series_svg};
+use crate::results::Results;
+use crate::runner::runtime_table;
+use crate::spec::{BenchmarkSpec, ExperimentSpec};
+use crate::tuning::thread_count;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.75em; text-align: left; }
+td.number { text-align: right; font-family: monospace; }
+figure { display: inline-block; margin: 0.5em; }
+";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn list<T: ToString>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// the parameters an experiment was run with, as rows of (name, value)
+fn parameters(experiment: &ExperimentSpec, results: &Results) -> Vec<(&'static str, String)> {
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    // the seeds of the repetitions are consecutive, so the first one identifies all inputs
+    let first_seed = results
+        .measurements
+        .iter()
+        .find(|m| m.experiment == experiment.name && m.repetition == 0)
+        .map(|m| m.seed);
+    let seed = match (experiment.seed, first_seed) {
+        (Some(seed), _) => seed.to_string(),
+        (None, Some(seed)) => format!("random ({})", seed),
+        (None, None) => "random".to_string(),
+    };
+
+    vec![
+        ("task", optional(experiment.task.clone())),
+        ("algorithms", list(&experiment.algorithms)),
+        ("generators", list(&experiment.generators)),
+        (
+            if experiment.weak_scaling {
+                "sizes per thread"
+            } else {
+                "sizes"
+            },
+            list(&experiment.sizes),
+        ),
+        (
+            "threads",
+            list(
+                &experiment
+                    .threads
+                    .iter()
+                    .map(|&t| thread_count(t))
+                    .collect::<Vec<_>>(),
+            ),
+        ),
+        ("repetitions", experiment.repetitions.to_string()),
+        ("max repetitions", experiment.max_repetitions().to_string()),
+        ("warmup", experiment.warmup.to_string()),
+        (
+            "precision",
+            optional(experiment.precision.map(|p| format!("{}%", p * 100.0))),
+        ),
+        ("seed", seed),
+        (
+            "time budget",
+            optional(experiment.time_budget.map(|t| format!("{} s", t))),
+        ),
+        (
+            "operations",
+            if experiment.count_operations {
+                "counted in separate runs".to_string()
+            } else {
+                "-".to_string()
+            },
+        ),
+    ]
+}
+
+/**
+    A single HTML page with the environment of the run and, for each experiment, its parameters,
+    its runtime table and all of its plots. The plots are embedded as SVG, so the page does not
+    refer to any other file.
+*/
+pub fn report_html(
+    spec: &BenchmarkSpec,
+    results: &Results,
+    label: &dyn Fn(&str) -> String,
+) -> Result<String, Box<dyn Error>> {
+    let environment = &results.environment;
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html><head><meta charset=\"utf-8\">")?;
+    writeln!(html, "<title>Benchmark Report</title>")?;
+    writeln!(html, "<style>{}</style></head><body>", STYLE)?;
+    writeln!(html, "<h1>Benchmark Report</h1>")?;
+    writeln!(html, "<table>")?;
+    writeln!(
+        html,
+        "<tr><th>commit</th><td>{}</td></tr>",
+        escape(&environment.commit)
+    )?;
+    writeln!(
+        html,
+        "<tr><th>rustc</th><td>{}</td></tr>",
+        escape(&environment.rustc)
+    )?;
+    writeln!(html, "<tr><th>cpus</th><td>{}</td></tr>", environment.cpus)?;
+    writeln!(html, "</table>")?;
+
+    for experiment in spec.experiments.iter() {
+        writeln!(html, "<h2>{}</h2>", escape(&experiment.name))?;
+
+        writeln!(html, "<table>")?;
+        for (name, value) in parameters(experiment, results) {
+            writeln!(
+                html,
+                "<tr><th>{}</th><td>{}</td></tr>",
+                name,
+                escape(&value)
+            )?;
+        }
+        writeln!(html, "</table>")?;
+
+        writeln!(
+            html,
+            "<p>Median runtime ± MAD in milliseconds, * marks outliers.</p>"
+        )?;
+        let (header, rows) = runtime_table(experiment, &results.measurements, label);
+        writeln!(html, "<table>")?;
+        write!(html, "<tr><th></th>")?;
+        for size in header.iter() {
+            write!(html, "<th>{}</th>", escape(size))?;
+        }
+        writeln!(html, "</tr>")?;
+        for (name, cells) in rows.iter() {
+            write!(html, "<tr><th>{}</th>", escape(name))?;
+            for cell in cells.iter() {
+                write!(html, "<td class=\"number\">{}</td>", escape(cell))?;
+            }
+            writeln!(html, "</tr>")?;
+        }
+        writeln!(html, "</table>")?;
+
+        for plot in spec
+            .plots
+            .iter()
+            .filter(|p| p.experiment == experiment.name)
+        {
+            let series = plot_series(plot, experiment, &results.measurements, label)?;
+            writeln!(
+                html,
+                "<figure>{}<figcaption>{}</figcaption></figure>",
+                series_svg(plot, &series, &spec.style)?,
+                escape(&plot.path)
+            )?;
+        }
+    }
+
+    writeln!(html, "</body></html>")?;
+    Ok(html)
+}
+
+pub fn write_report(
+    path: &str,
+    spec: &BenchmarkSpec,
+    results: &Results,
+    label: &dyn Fn(&str) -> String,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, report_html(spec, results, label)?).map_err(|e| format!("{}: {}", path, e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::report::report_html;
+    use crate::results::{Environment, Results};
+    use crate::runner::Measurement;
+    use crate::spec::BenchmarkSpec;
+
+    #[test]
+    fn self_contained_report() {
+        let spec = BenchmarkSpec::parse(
+            r#"
+            report = "report.html"
+
+            [[experiment]]
+            name = "inputs"
+            algorithms = ["a"]
+            generators = ["random"]
+            sizes = [10, 100]
+            time_budget = 2.5
+
+            [[plot]]
+            experiment = "inputs"
+            title = "A <&> B"
+            path = "inputs.svg"
+            series = ["algorithm"]
+            "#,
+        )
+        .unwrap();
+        let measurement = |size: usize, seconds: f64, skipped: bool| Measurement {
+            experiment: "inputs".to_string(),
+            algorithm: "a".to_string(),
+            generator: "random".to_string(),
+            seed: 42,
+            size,
+            threads: 1,
+            repetition: 0,
+            seconds,
+            output_size: None,
+            outlier: false,
+            skipped,
+            operations: None,
+        };
+        let results = Results {
+            environment: Environment {
+                cpus: 4,
+                rustc: "rustc 1.0.0".to_string(),
+                commit: "abc1234".to_string(),
+            },
+            measurements: vec![measurement(10, 0.002, false), measurement(100, 3.0, true)],
+        };
+
+        let html = report_html(&spec, &results, &|name| name.to_uppercase()).unwrap();
+        assert!(html.contains("<td>abc1234</td>"));
+        assert!(html.contains("<th>seed</th><td>random (42)</td>"));
+        assert!(html.contains("<th>time budget</th><td>2.5 s</td>"));
+        assert!(html.contains("<th>A, RANDOM, 1 threads</th>"));
+        assert!(html.contains("2.000 ± 0.000"));
+        assert!(html.contains(">skipped<"));
+        // the plot is embedded, not linked
+        assert!(html.contains("<svg"));
+        assert!(!html.contains("<img"));
+        assert!(html.contains("A &lt;&amp;&gt; B"));
+    }
+}
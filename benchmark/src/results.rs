@@ -0,0 +1,220 @@
+use crate::counters::Operations;
+use crate::runner::Measurement;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::thread::available_parallelism;
+
+// the machine and code version a benchmark was run with
+#[derive(Debug, PartialEq, Clone)]
+pub struct Environment {
+    pub cpus: usize,
+    pub rustc: String,
+    pub commit: String,
+}
+
+impl Environment {
+    pub fn current() -> Environment {
+        Environment {
+            cpus: available_parallelism().map_or(1, |n| n.get()),
+            rustc: env!("BENCHMARK_RUSTC_VERSION").to_string(),
+            commit: git_commit(),
+        }
+    }
+}
+
+// the checked out commit, marked as dirty if there are uncommitted changes
+fn git_commit() -> String {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+    };
+
+    match git(&["rev-parse", "--short", "HEAD"]) {
+        Some(commit) => match git(&["status", "--porcelain", "--untracked-files=no"]) {
+            Some(status) if !status.trim().is_empty() => format!("{}-dirty", commit.trim()),
+            _ => commit.trim().to_string(),
+        },
+        None => "unknown".to_string(),
+    }
+}
+
+// one line of a results file
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+struct Record {
+    experiment: String,
+    algorithm: String,
+    generator: String,
+    seed: u64,
+    n: usize,
+    threads: usize,
+    repetition: usize,
+    wall_time_seconds: f64,
+    output_size: Option<usize>,
+    outlier: bool,
+    // missing in files written before the time budget existed
+    #[serde(default)]
+    skipped: bool,
+    // empty unless the experiment counted operations
+    #[serde(default)]
+    comparisons: Option<u64>,
+    #[serde(default)]
+    writes: Option<u64>,
+    #[serde(default)]
+    orientation_tests: Option<u64>,
+    #[serde(default)]
+    tangent_steps: Option<u64>,
+    cpus: usize,
+    rustc: String,
+    commit: String,
+}
+
+// the measurements of one benchmark run together with the environment they were taken in
+#[derive(Debug, PartialEq, Clone)]
+pub struct Results {
+    pub environment: Environment,
+    pub measurements: Vec<Measurement>,
+}
+
+/**
+    Writes every individual measurement as one line of a CSV file. The environment is repeated on
+    every line so that each line can be understood on its own, e.g. after concatenating files.
+*/
+pub fn write_results(path: &str, results: &Results) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut writer = csv::Writer::from_path(path).map_err(|e| format!("{}: {}", path, e))?;
+    for m in results.measurements.iter() {
+        writer.serialize(Record {
+            experiment: m.experiment.clone(),
+            algorithm: m.algorithm.clone(),
+            generator: m.generator.clone(),
+            seed: m.seed,
+            n: m.size,
+            threads: m.threads,
+            repetition: m.repetition,
+            wall_time_seconds: m.seconds,
+            output_size: m.output_size,
+            outlier: m.outlier,
+            skipped: m.skipped,
+            comparisons: m.operations.map(|o| o.comparisons),
+            writes: m.operations.map(|o| o.writes),
+            orientation_tests: m.operations.map(|o| o.orientation_tests),
+            tangent_steps: m.operations.map(|o| o.tangent_steps),
+            cpus: results.environment.cpus,
+            rustc: results.environment.rustc.clone(),
+            commit: results.environment.commit.clone(),
+        })?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/**
+    Reads a results file written by `write_results`. The environment is taken from the first line,
+    an empty file has an unknown environment.
+*/
+pub fn read_results(path: &str) -> Result<Results, Box<dyn Error>> {
+    let mut reader = csv::Reader::from_path(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut environment = None;
+    let mut measurements = vec![];
+
+    for record in reader.deserialize() {
+        let record: Record = record.map_err(|e| format!("{}: {}", path, e))?;
+        environment.get_or_insert(Environment {
+            cpus: record.cpus,
+            rustc: record.rustc,
+            commit: record.commit,
+        });
+        measurements.push(Measurement {
+            experiment: record.experiment,
+            algorithm: record.algorithm,
+            generator: record.generator,
+            seed: record.seed,
+            size: record.n,
+            threads: record.threads,
+            repetition: record.repetition,
+            seconds: record.wall_time_seconds,
+            output_size: record.output_size,
+            outlier: record.outlier,
+            skipped: record.skipped,
+            operations: record.comparisons.map(|comparisons| Operations {
+                comparisons,
+                writes: record.writes.unwrap_or(0),
+                orientation_tests: record.orientation_tests.unwrap_or(0),
+                tangent_steps: record.tangent_steps.unwrap_or(0),
+            }),
+        });
+    }
+
+    Ok(Results {
+        environment: environment.unwrap_or(Environment {
+            cpus: 0,
+            rustc: "unknown".to_string(),
+            commit: "unknown".to_string(),
+        }),
+        measurements,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::counters::Operations;
+    use crate::results::{read_results, write_results, Environment, Results};
+    use crate::runner::Measurement;
+    use std::env::temp_dir;
+
+    #[test]
+    fn write_and_read_back() {
+        let measurement = |algorithm: &str, output_size| Measurement {
+            experiment: "inputs".to_string(),
+            algorithm: algorithm.to_string(),
+            generator: "uniform_square".to_string(),
+            seed: 12345678901234567890,
+            size: 1000,
+            threads: 8,
+            repetition: 2,
+            seconds: 0.125,
+            output_size,
+            outlier: output_size.is_none(),
+            skipped: algorithm == "gift_wrapping",
+            operations: output_size.map(|h| Operations {
+                comparisons: 10,
+                writes: 0,
+                orientation_tests: 1000 * h as u64,
+                tangent_steps: 7,
+            }),
+        };
+        let results = Results {
+            environment: Environment {
+                cpus: 16,
+                rustc: "rustc 1.0.0 (abc, \"quoted\")".to_string(),
+                commit: "abc1234-dirty".to_string(),
+            },
+            measurements: vec![
+                measurement("grahams_scan", Some(42)),
+                measurement("sort", None),
+                measurement("gift_wrapping", Some(42)),
+            ],
+        };
+
+        let path = temp_dir().join("benchmark_results_test.csv");
+        let path = path.to_str().unwrap();
+        write_results(path, &results).unwrap();
+        assert_eq!(read_results(path).unwrap(), results);
+    }
+
+    #[test]
+    fn current_environment() {
+        let environment = Environment::current();
+        assert!(environment.cpus >= 1);
+        assert!(!environment.rustc.is_empty());
+    }
+}
@@ -0,0 +1,647 @@
+use crate::counters::{counted, Operations};
+use crate::plotting::draw_plot;
+use crate::report::write_report;
+use crate::results::{read_results, write_results, Environment, Results};
+use crate::spec::{BenchmarkSpec, ExperimentSpec};
+use crate::statistics::{outliers, summarize};
+use crate::tuning::thread_count;
+use std::collections::hash_map::RandomState;
+use std::error::Error;
+use std::hash::{BuildHasher, Hasher};
+
+// the result of a single run of an algorithm on a generated input
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Sample {
+    pub seconds: f64,
+    pub output_size: Option<usize>,
+}
+
+/**
+    Connects a benchmark spec to the algorithms and input generators of a project. Names used in
+    the spec are resolved by the workload.
+*/
+pub trait Workload {
+    fn algorithms(&self, task: Option<&str>) -> Vec<String>;
+    fn generators(&self, task: Option<&str>) -> Vec<String>;
+
+    // human readable name of an algorithm or generator for legends and tables
+    fn label(&self, name: &str) -> String {
+        name.to_string()
+    }
+
+    /**
+        Generates an input of the given size from the seed, runs the algorithm on it once and
+        returns the measurement. Only the algorithm itself should be timed.
+    */
+    fn run(
+        &self,
+        task: Option<&str>,
+        algorithm: &str,
+        generator: &str,
+        size: usize,
+        threads: usize,
+        seed: u64,
+    ) -> Sample;
+}
+
+// a single run of an experiment
+#[derive(Debug, PartialEq, Clone)]
+pub struct Measurement {
+    pub experiment: String,
+    pub algorithm: String,
+    pub generator: String,
+    pub seed: u64,
+    pub size: usize,
+    pub threads: usize,
+    pub repetition: usize,
+    pub seconds: f64,
+    pub output_size: Option<usize>,
+    // the runtime is far away from the other runs of the same configuration
+    pub outlier: bool,
+    // the size was not run because it would exceed the time budget, seconds holds the predicted
+    // runtime (or NaN if there is no prediction)
+    pub skipped: bool,
+    // counted in a separate run on the same input if the experiment counts operations
+    pub operations: Option<Operations>,
+}
+
+/**
+    Predicts the runtime of a size from the median runtimes of the smaller sizes by assuming that it
+    grows like a power of the size, with the exponent taken from the last two sizes. The runtime is
+    assumed to grow at least linearly, so noise can not make a large size look cheap.
+*/
+pub fn extrapolate(history: &[(usize, f64)], size: usize) -> Option<f64> {
+    let &(last_size, last_seconds) = history.last()?;
+    let exponent = match history.len() {
+        1 => 1.0,
+        n => {
+            let (previous_size, previous_seconds) = history[n - 2];
+            ((last_seconds / previous_seconds).ln()
+                / (last_size as f64 / previous_size as f64).ln())
+            .max(1.0)
+        }
+    };
+    Some(last_seconds * (size as f64 / last_size as f64).powf(exponent))
+}
+
+// replaces "all" by every algorithm of the workload and checks that all names are known
+fn resolve_names(
+    requested: &[String],
+    known: Vec<String>,
+    kind: &str,
+    experiment: &str,
+) -> Result<Vec<String>, String> {
+    if requested.iter().any(|name| name == "all") {
+        return Ok(known);
+    }
+    for name in requested {
+        if !known.contains(name) {
+            return Err(format!(
+                "experiment {}: unknown {} {}, known are: {}",
+                experiment,
+                kind,
+                name,
+                known.join(", ")
+            ));
+        }
+    }
+    Ok(requested.to_vec())
+}
+
+fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+pub fn run_experiment(
+    workload: &dyn Workload,
+    experiment: &ExperimentSpec,
+) -> Result<Vec<Measurement>, String> {
+    let task = experiment.task.as_deref();
+    let algorithms = resolve_names(
+        &experiment.algorithms,
+        workload.algorithms(task),
+        "algorithm",
+        &experiment.name,
+    )?;
+    let generators = resolve_names(
+        &experiment.generators,
+        workload.generators(task),
+        "generator",
+        &experiment.name,
+    )?;
+
+    // all algorithms and thread counts are run on the same inputs
+    let seed = experiment.seed.unwrap_or_else(random_seed);
+
+    let mut measurements = vec![];
+    for generator in generators.iter() {
+        for algorithm in algorithms.iter() {
+            for &threads in experiment.threads.iter() {
+                println!(
+                    "{}: {}, {}, {} threads",
+                    experiment.name,
+                    workload.label(generator),
+                    workload.label(algorithm),
+                    thread_count(threads)
+                );
+
+                // (size, median seconds) of the measured sizes to predict the larger ones
+                let mut history: Vec<(usize, f64)> = vec![];
+                let mut over_budget = false;
+
+                for &size in experiment.sizes.iter() {
+                    let size = experiment.input_size(size, threads);
+                    let measurement =
+                        |repetition, seed, seconds, output_size, outlier, skipped| Measurement {
+                            experiment: experiment.name.clone(),
+                            algorithm: algorithm.clone(),
+                            generator: generator.clone(),
+                            seed,
+                            size,
+                            threads,
+                            repetition,
+                            seconds,
+                            output_size,
+                            outlier,
+                            skipped,
+                            operations: None,
+                        };
+
+                    let prediction = extrapolate(&history, size);
+                    let budget = experiment.time_budget.unwrap_or(f64::INFINITY);
+                    if over_budget || prediction.is_some_and(|p| p > budget) {
+                        over_budget = true;
+                        let predicted = prediction.unwrap_or(f64::NAN);
+                        println!("{}: skipped, predicted {:.1}s", size, predicted);
+                        measurements.push(measurement(0, seed, predicted, None, false, true));
+                        continue;
+                    }
+
+                    // a run over the budget can not be cancelled safely, so the larger sizes are
+                    // skipped after it
+                    let mut warmup_seconds = 0.0;
+                    for _ in 0..experiment.warmup {
+                        let sample = workload.run(task, algorithm, generator, size, threads, seed);
+                        warmup_seconds = sample.seconds;
+                        if warmup_seconds > budget {
+                            break;
+                        }
+                    }
+                    if warmup_seconds > budget {
+                        over_budget = true;
+                        println!("{}: skipped, warmup took {:.1}s", size, warmup_seconds);
+                        measurements.push(measurement(0, seed, warmup_seconds, None, false, true));
+                        continue;
+                    }
+
+                    let mut samples = vec![];
+                    let mut seconds: Vec<f64> = vec![];
+                    while seconds.len() < experiment.max_repetitions() {
+                        let seed = seed.wrapping_add(seconds.len() as u64);
+                        let sample = workload.run(task, algorithm, generator, size, threads, seed);
+                        samples.push((seed, sample));
+                        seconds.push(sample.seconds);
+
+                        if sample.seconds > budget {
+                            over_budget = true;
+                            break;
+                        }
+                        if seconds.len() >= experiment.repetitions
+                            && experiment
+                                .precision
+                                .is_none_or(|p| summarize(&seconds).relative_error() <= p)
+                        {
+                            break;
+                        }
+                    }
+
+                    let flags = outliers(&seconds);
+                    for (repetition, ((seed, sample), outlier)) in
+                        samples.into_iter().zip(flags).enumerate()
+                    {
+                        let mut measured = measurement(
+                            repetition,
+                            seed,
+                            sample.seconds,
+                            sample.output_size,
+                            outlier,
+                            false,
+                        );
+                        // counting slows the algorithms down, so it is not done in the timed runs
+                        if experiment.count_operations {
+                            let (_, operations) = counted(|| {
+                                workload.run(task, algorithm, generator, size, threads, seed)
+                            });
+                            measured.operations = Some(operations);
+                        }
+                        measurements.push(measured);
+                    }
+
+                    let summary = summarize(&seconds);
+                    history.push((size, summary.median));
+                    println!(
+                        "{}: median {:.6}s, MAD {:.6}s, {} runs, {} outliers",
+                        size, summary.median, summary.mad, summary.count, summary.outliers
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(measurements)
+}
+
+// the header and the named rows of the runtime table of an experiment
+pub type Table = (Vec<String>, Vec<(String, Vec<String>)>);
+
+/**
+    The median runtime and its MAD in milliseconds of every algorithm, generator and thread count
+    of an experiment for each input size. Sizes with outliers are marked by a *, sizes over the
+    time budget as skipped.
+*/
+pub fn runtime_table(
+    experiment: &ExperimentSpec,
+    results: &[Measurement],
+    label: &dyn Fn(&str) -> String,
+) -> Table {
+    let mut rows: Vec<(&str, &str, usize)> = vec![];
+    for m in results.iter().filter(|m| m.experiment == experiment.name) {
+        let row = (m.algorithm.as_str(), m.generator.as_str(), m.threads);
+        if !rows.contains(&row) {
+            rows.push(row);
+        }
+    }
+
+    let prefix = if experiment.weak_scaling { "n/p" } else { "n" };
+    let header = experiment
+        .sizes
+        .iter()
+        .map(|size| format!("{}={:.0e}", prefix, *size as f64))
+        .collect();
+
+    let rows = rows
+        .into_iter()
+        .map(|(algorithm, generator, threads)| {
+            let name = format!(
+                "{}, {}, {} threads",
+                label(algorithm),
+                label(generator),
+                thread_count(threads)
+            );
+            let cells = experiment
+                .sizes
+                .iter()
+                .map(|&size| {
+                    let runs: Vec<&Measurement> = results
+                        .iter()
+                        .filter(|m| {
+                            m.experiment == experiment.name
+                                && m.algorithm == algorithm
+                                && m.generator == generator
+                                && m.threads == threads
+                                && m.size == experiment.input_size(size, threads)
+                        })
+                        .collect();
+                    if runs.iter().any(|m| m.skipped) {
+                        "skipped".to_string()
+                    } else if runs.is_empty() {
+                        "-".to_string()
+                    } else {
+                        let seconds: Vec<f64> = runs.iter().map(|m| m.seconds).collect();
+                        let summary = summarize(&seconds);
+                        format!(
+                            "{:.3} ± {:.3}{}",
+                            summary.median * 1000.0,
+                            summary.mad * 1000.0,
+                            if summary.outliers > 0 { "*" } else { " " }
+                        )
+                    }
+                })
+                .collect();
+            (name, cells)
+        })
+        .collect();
+    (header, rows)
+}
+
+// prints the runtime table of an experiment
+pub fn print_table(workload: &dyn Workload, experiment: &ExperimentSpec, results: &[Measurement]) {
+    let (header, rows) = runtime_table(experiment, results, &|name| workload.label(name));
+    print!("{:<50}", experiment.name);
+    for size in header.iter() {
+        print!("{:>22}", size);
+    }
+    println!();
+
+    for (name, cells) in rows {
+        print!("{:<50}", name);
+        for cell in cells.iter() {
+            print!("{:>22}", cell);
+        }
+        println!();
+    }
+}
+
+// prints the tables, draws the plots and writes the HTML report of the spec from the measurements
+fn report(
+    workload: &dyn Workload,
+    spec: &BenchmarkSpec,
+    results: &Results,
+) -> Result<(), Box<dyn Error>> {
+    let label = |name: &str| workload.label(name);
+    for experiment in spec.experiments.iter() {
+        print_table(workload, experiment, &results.measurements);
+    }
+
+    for plot in spec.plots.iter() {
+        // the spec is validated, so every plot has its experiment
+        let experiment = spec.experiment(&plot.experiment).unwrap();
+        draw_plot(plot, experiment, &results.measurements, &label, &spec.style)?;
+    }
+
+    if let Some(path) = spec.report.as_deref() {
+        write_report(path, spec, results, &label)?;
+        println!("report written to {}", path);
+    }
+    Ok(())
+}
+
+/**
+    Runs every experiment of the spec, writes the measurements to the results file of the spec (if
+    any), prints a table per experiment, draws all plots and writes the report of the spec (if any).
+    Returns the individual measurements.
+*/
+pub fn run_spec(
+    workload: &dyn Workload,
+    spec: &BenchmarkSpec,
+) -> Result<Vec<Measurement>, Box<dyn Error>> {
+    let mut results = vec![];
+    for experiment in spec.experiments.iter() {
+        results.extend(run_experiment(workload, experiment)?);
+    }
+
+    let results = Results {
+        environment: Environment::current(),
+        measurements: results,
+    };
+    if let Some(path) = spec.results.as_deref() {
+        write_results(path, &results)?;
+        println!("results written to {}", path);
+    }
+
+    report(workload, spec, &results)?;
+    Ok(results.measurements)
+}
+
+/**
+    Prints the tables, redraws the plots and rewrites the report of the spec from its results file
+    without running any experiment.
+*/
+pub fn plot_results(workload: &dyn Workload, spec: &BenchmarkSpec) -> Result<(), Box<dyn Error>> {
+    let path = spec
+        .results
+        .as_deref()
+        .ok_or("the spec has no results file")?;
+    let results = read_results(path)?;
+    println!(
+        "results of commit {} on {} cpus, {}",
+        results.environment.commit, results.environment.cpus, results.environment.rustc
+    );
+    report(workload, spec, &results)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::counters::{count, Counter};
+    use crate::runner::{extrapolate, run_experiment, Sample, Workload};
+    use crate::spec::BenchmarkSpec;
+
+    // pretends that fast takes size * threads and slow size^2 * threads nanoseconds, the work is
+    // counted as orientation tests in the "counting" task
+    struct FakeWorkload();
+
+    impl Workload for FakeWorkload {
+        fn algorithms(&self, _: Option<&str>) -> Vec<String> {
+            vec!["fast".to_string(), "slow".to_string()]
+        }
+
+        fn generators(&self, _: Option<&str>) -> Vec<String> {
+            vec!["random".to_string()]
+        }
+
+        fn run(
+            &self,
+            task: Option<&str>,
+            algorithm: &str,
+            _: &str,
+            size: usize,
+            threads: usize,
+            _: u64,
+        ) -> Sample {
+            let work = if algorithm == "slow" {
+                size * size
+            } else {
+                size
+            };
+            if task == Some("counting") {
+                count(Counter::OrientationTests, work as u64);
+            }
+            Sample {
+                seconds: (work * threads) as f64 * 1e-9,
+                output_size: Some(size / 2),
+            }
+        }
+    }
+
+    #[test]
+    fn runs_all_combinations() {
+        let spec = BenchmarkSpec::parse(
+            r#"
+            [[experiment]]
+            name = "test"
+            algorithms = ["all"]
+            generators = ["random"]
+            sizes = [10, 100]
+            threads = [1, 2]
+            repetitions = 3
+            seed = 7
+            "#,
+        )
+        .unwrap();
+
+        let results = run_experiment(&FakeWorkload(), &spec.experiments[0]).unwrap();
+        // 2 algorithms * 2 sizes * 2 threads * 3 repetitions
+        assert_eq!(results.len(), 24);
+        assert!(results.iter().all(|m| !m.skipped));
+        assert!(results
+            .iter()
+            .filter(|m| m.algorithm == "fast")
+            .all(|m| m.seconds == (m.size * m.threads) as f64 * 1e-9));
+        assert!(results.iter().all(|m| m.seed == 7 + m.repetition as u64));
+    }
+
+    #[test]
+    fn counts_operations_of_every_repetition() {
+        let spec = BenchmarkSpec::parse(
+            r#"
+            [[experiment]]
+            name = "test"
+            task = "counting"
+            algorithms = ["slow"]
+            generators = ["random"]
+            sizes = [10, 100]
+            repetitions = 2
+            count_operations = true
+            "#,
+        )
+        .unwrap();
+
+        let results = run_experiment(&FakeWorkload(), &spec.experiments[0]).unwrap();
+        assert_eq!(results.len(), 4);
+        assert!(results.iter().all(|m| m
+            .operations
+            .is_some_and(|o| o.orientation_tests == (m.size * m.size) as u64)));
+
+        let mut experiment = spec.experiments[0].clone();
+        experiment.count_operations = false;
+        let results = run_experiment(&FakeWorkload(), &experiment).unwrap();
+        assert!(results.iter().all(|m| m.operations.is_none()));
+    }
+
+    #[test]
+    fn extrapolation() {
+        assert_eq!(extrapolate(&[], 100), None);
+        assert_eq!(extrapolate(&[(10, 1.0)], 100), Some(10.0));
+        assert_eq!(extrapolate(&[(10, 1.0), (100, 100.0)], 1000), Some(10000.0));
+        // never predicts a sublinear growth
+        assert_eq!(extrapolate(&[(10, 1.0), (100, 2.0)], 1000), Some(20.0));
+    }
+
+    #[test]
+    fn time_budget_skips_larger_sizes() {
+        let spec = BenchmarkSpec::parse(
+            r#"
+            [[experiment]]
+            name = "test"
+            algorithms = ["all"]
+            generators = ["random"]
+            sizes = [10, 100, 1000, 10000]
+            repetitions = 3
+            time_budget = 5e-6
+            "#,
+        )
+        .unwrap();
+
+        let results = run_experiment(&FakeWorkload(), &spec.experiments[0]).unwrap();
+        let runs = |algorithm: &str, skipped: bool| -> Vec<usize> {
+            results
+                .iter()
+                .filter(|m| m.algorithm == algorithm && m.skipped == skipped)
+                .map(|m| m.size)
+                .collect()
+        };
+        // fast: 10000 is predicted to take 1e-5 seconds
+        assert_eq!(
+            runs("fast", false),
+            vec![10, 10, 10, 100, 100, 100, 1000, 1000, 1000]
+        );
+        assert_eq!(runs("fast", true), vec![10000]);
+        // slow: 100 is predicted to be in the budget, but its warmup run already takes too long
+        assert_eq!(runs("slow", false), vec![10, 10, 10]);
+        assert_eq!(runs("slow", true), vec![100, 1000, 10000]);
+        assert!(results
+            .iter()
+            .any(|m| m.algorithm == "slow" && m.size == 100 && m.seconds == 1e-5));
+    }
+
+    // every tenth run is very slow, the others vary slightly with the seed
+    struct NoisyWorkload();
+
+    impl Workload for NoisyWorkload {
+        fn algorithms(&self, _: Option<&str>) -> Vec<String> {
+            vec!["noisy".to_string()]
+        }
+
+        fn generators(&self, _: Option<&str>) -> Vec<String> {
+            vec!["random".to_string()]
+        }
+
+        fn run(&self, _: Option<&str>, _: &str, _: &str, _: usize, _: usize, seed: u64) -> Sample {
+            let seconds = if seed % 10 == 9 {
+                10.0
+            } else {
+                1.0 + (seed % 3) as f64 * 0.01
+            };
+            Sample {
+                seconds,
+                output_size: None,
+            }
+        }
+    }
+
+    fn noisy_spec(precision: f64) -> String {
+        format!(
+            r#"
+            [[experiment]]
+            name = "test"
+            algorithms = ["noisy"]
+            generators = ["random"]
+            sizes = [10]
+            repetitions = 3
+            max_repetitions = 30
+            precision = {}
+            seed = 0
+            "#,
+            precision
+        )
+    }
+
+    #[test]
+    fn repeats_until_precise() {
+        let spec = BenchmarkSpec::parse(&noisy_spec(0.5)).unwrap();
+        let results = run_experiment(&NoisyWorkload(), &spec.experiments[0]).unwrap();
+        assert_eq!(results.len(), 3);
+
+        let spec = BenchmarkSpec::parse(&noisy_spec(0.0001)).unwrap();
+        let results = run_experiment(&NoisyWorkload(), &spec.experiments[0]).unwrap();
+        assert_eq!(results.len(), 30);
+        assert!(results.iter().all(|m| m.outlier == (m.seed % 10 == 9)));
+    }
+
+    #[test]
+    fn weak_scaling_grows_the_input() {
+        let spec = BenchmarkSpec::parse(
+            r#"
+            [[experiment]]
+            name = "test"
+            algorithms = ["fast"]
+            generators = ["random"]
+            sizes = [10]
+            threads = [1, 2, 4]
+            repetitions = 1
+            weak_scaling = true
+            "#,
+        )
+        .unwrap();
+
+        let results = run_experiment(&FakeWorkload(), &spec.experiments[0]).unwrap();
+        let sizes: Vec<(usize, usize)> = results.iter().map(|m| (m.threads, m.size)).collect();
+        assert_eq!(sizes, vec![(1, 10), (2, 20), (4, 40)]);
+    }
+
+    #[test]
+    fn unknown_algorithm() {
+        let spec = BenchmarkSpec::parse(
+            r#"
+            [[experiment]]
+            name = "test"
+            algorithms = ["medium"]
+            generators = ["random"]
+            sizes = [10]
+            "#,
+        )
+        .unwrap();
+
+        assert!(run_experiment(&FakeWorkload(), &spec.experiments[0]).is_err());
+    }
+}
@@ -0,0 +1,304 @@
+use crate::plotting::{matches, series_name, Series, SeriesPoint};
+use crate::runner::Measurement;
+use crate::spec::{ExperimentSpec, PlotSpec, YValue};
+use crate::statistics::{summarize, Summary};
+
+/**
+    Least squares fit of Amdahl's law S(p) = 1 / (f + (1 - f) / p) to measured speedups. Written as
+    1/S - 1/p = f * (1 - 1/p) the serial fraction f is a linear regression through the origin.
+*/
+pub fn amdahl_serial_fraction(speedups: &[(usize, f64)]) -> f64 {
+    let (mut xy, mut xx) = (0.0, 0.0);
+    for &(p, speedup) in speedups.iter().filter(|(p, _)| *p > 1) {
+        let x = 1.0 - 1.0 / p as f64;
+        xy += x * (1.0 / speedup - 1.0 / p as f64);
+        xx += x * x;
+    }
+    if xx == 0.0 {
+        0.0
+    } else {
+        (xy / xx).clamp(0.0, 1.0)
+    }
+}
+
+pub fn amdahl_speedup(serial_fraction: f64, threads: usize) -> f64 {
+    1.0 / (serial_fraction + (1.0 - serial_fraction) / threads as f64)
+}
+
+/**
+    Least squares fit of Gustafson's law S(p) = p - f * (p - 1) to measured scaled speedups of a
+    weak scaling experiment.
+*/
+pub fn gustafson_serial_fraction(speedups: &[(usize, f64)]) -> f64 {
+    let (mut xy, mut xx) = (0.0, 0.0);
+    for &(p, speedup) in speedups.iter() {
+        let x = p as f64 - 1.0;
+        xy += x * (p as f64 - speedup);
+        xx += x * x;
+    }
+    if xx == 0.0 {
+        0.0
+    } else {
+        (xy / xx).clamp(0.0, 1.0)
+    }
+}
+
+pub fn gustafson_speedup(serial_fraction: f64, threads: usize) -> f64 {
+    threads as f64 - serial_fraction * (threads as f64 - 1.0)
+}
+
+// the Karp-Flatt metric, it is undefined for a single thread
+pub fn karp_flatt(speedup: f64, threads: usize) -> Option<f64> {
+    if threads < 2 {
+        return None;
+    }
+    let p = threads as f64;
+    Some((1.0 / speedup - 1.0 / p) / (1.0 - 1.0 / p))
+}
+
+// speedup of the runs with p threads over the one thread runs with its confidence interval
+fn speedup(single: &Summary, parallel: &Summary, threads: usize, weak: bool) -> SeriesPoint {
+    // with weak scaling the p threads did p times the work
+    let work = if weak { threads as f64 } else { 1.0 };
+    SeriesPoint {
+        x: threads,
+        value: work * single.median / parallel.median,
+        low: work * single.ci_low / parallel.ci_high,
+        high: work * single.ci_high / parallel.ci_low,
+    }
+}
+
+type ScalingGroup<'a> = ((&'a str, &'a str, usize), String, Vec<(usize, Vec<f64>)>);
+
+/**
+    Series of the speedup, the efficiency or the Karp-Flatt metric over the thread count, one for
+    each algorithm, generator and (per thread) input size selected by the plot. Configurations
+    without a one thread run are left out since they have no baseline.
+*/
+pub fn scaling_series(
+    plot: &PlotSpec,
+    experiment: &ExperimentSpec,
+    results: &[Measurement],
+    label: &dyn Fn(&str) -> String,
+) -> Vec<Series> {
+    let weak = experiment.weak_scaling;
+
+    // ((algorithm, generator, size of the one thread run), name, [(threads, runtimes)])
+    let mut groups: Vec<ScalingGroup> = vec![];
+    for m in results.iter().filter(|m| matches(plot, m)) {
+        let size = if weak { m.size / m.threads } else { m.size };
+        let key = (m.algorithm.as_str(), m.generator.as_str(), size);
+        let group = match groups.iter().position(|(k, _, _)| *k == key) {
+            Some(i) => &mut groups[i].2,
+            None => {
+                groups.push((key, series_name(plot, experiment, m, label), vec![]));
+                &mut groups.last_mut().unwrap().2
+            }
+        };
+        match group.iter_mut().find(|(threads, _)| *threads == m.threads) {
+            Some((_, runtimes)) => runtimes.push(m.seconds),
+            None => group.push((m.threads, vec![m.seconds])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter_map(|(_, name, runs)| {
+            let single = summarize(&runs.iter().find(|(threads, _)| *threads == 1)?.1);
+            let speedups: Vec<SeriesPoint> = runs
+                .iter()
+                .map(|(threads, runtimes)| speedup(&single, &summarize(runtimes), *threads, weak))
+                .collect();
+
+            let points = match plot.y {
+                YValue::Efficiency => speedups
+                    .iter()
+                    .map(|s| {
+                        let p = s.x as f64;
+                        SeriesPoint {
+                            x: s.x,
+                            value: s.value / p,
+                            low: s.low / p,
+                            high: s.high / p,
+                        }
+                    })
+                    .collect(),
+                // a higher speedup means a smaller serial fraction
+                YValue::KarpFlatt => speedups
+                    .iter()
+                    .filter_map(|s| {
+                        Some(SeriesPoint {
+                            x: s.x,
+                            value: karp_flatt(s.value, s.x)?,
+                            low: karp_flatt(s.high, s.x)?,
+                            high: karp_flatt(s.low, s.x)?,
+                        })
+                    })
+                    .collect(),
+                _ => speedups.clone(),
+            };
+
+            let mut series = Series {
+                name,
+                points,
+                fit: vec![],
+            };
+
+            if plot.fit {
+                let measured: Vec<(usize, f64)> = speedups.iter().map(|s| (s.x, s.value)).collect();
+                let max_threads = measured.iter().map(|(p, _)| *p).max().unwrap_or(1);
+                let (law, fraction, curve): (&str, f64, fn(f64, usize) -> f64) = if weak {
+                    let fraction = gustafson_serial_fraction(&measured);
+                    ("Gustafson", fraction, gustafson_speedup)
+                } else {
+                    let fraction = amdahl_serial_fraction(&measured);
+                    ("Amdahl", fraction, amdahl_speedup)
+                };
+                series.name = format!(
+                    "{} ({} fit: {:.1}% serial)",
+                    series.name,
+                    law,
+                    fraction * 100.0
+                );
+                series.fit = (1..=max_threads).map(|p| (p, curve(fraction, p))).collect();
+            }
+
+            Some(series)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runner::Measurement;
+    use crate::scaling::{
+        amdahl_serial_fraction, amdahl_speedup, gustafson_serial_fraction, gustafson_speedup,
+        karp_flatt, scaling_series,
+    };
+    use crate::spec::BenchmarkSpec;
+
+    #[test]
+    fn fits_recover_the_serial_fraction() {
+        let threads = [1, 2, 4, 8, 16];
+        let amdahl: Vec<(usize, f64)> = threads
+            .iter()
+            .map(|&p| (p, amdahl_speedup(0.1, p)))
+            .collect();
+        assert!((amdahl_serial_fraction(&amdahl) - 0.1).abs() < 1e-12);
+
+        let gustafson: Vec<(usize, f64)> = threads
+            .iter()
+            .map(|&p| (p, gustafson_speedup(0.2, p)))
+            .collect();
+        assert!((gustafson_serial_fraction(&gustafson) - 0.2).abs() < 1e-12);
+
+        // the Karp-Flatt metric of an exact Amdahl speedup is its serial fraction
+        assert!((karp_flatt(amdahl_speedup(0.1, 8), 8).unwrap() - 0.1).abs() < 1e-12);
+        assert_eq!(karp_flatt(1.0, 1), None);
+    }
+
+    fn spec(y: &str, weak: bool) -> BenchmarkSpec {
+        BenchmarkSpec::parse(&format!(
+            r#"
+            [[experiment]]
+            name = "test"
+            algorithms = ["a"]
+            generators = ["random"]
+            sizes = [1000]
+            threads = [1, 2, 4]
+            weak_scaling = {}
+
+            [[plot]]
+            experiment = "test"
+            title = "Test"
+            path = "test.png"
+            series = ["algorithm", "size"]
+            x = "threads"
+            y = "{}"
+            fit = {}
+            "#,
+            weak,
+            y,
+            y == "speedup"
+        ))
+        .unwrap()
+    }
+
+    // every run takes (0.25 + 0.75 / p) seconds for n = 1000, so the serial fraction is 25%
+    fn results(weak: bool) -> Vec<Measurement> {
+        [1, 2, 4]
+            .iter()
+            .flat_map(|&threads| {
+                (0..3).map(move |repetition| Measurement {
+                    experiment: "test".to_string(),
+                    algorithm: "a".to_string(),
+                    generator: "random".to_string(),
+                    seed: 0,
+                    size: if weak { 1000 * threads } else { 1000 },
+                    threads,
+                    repetition,
+                    seconds: if weak {
+                        // the parallel part grows with p, the serial part does not
+                        0.25 + 0.75
+                    } else {
+                        0.25 + 0.75 / threads as f64
+                    },
+                    output_size: None,
+                    outlier: false,
+                    skipped: false,
+                    operations: None,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn strong_scaling() {
+        let label = |name: &str| name.to_string();
+        let spec = spec("speedup", false);
+        let series = scaling_series(
+            &spec.plots[0],
+            &spec.experiments[0],
+            &results(false),
+            &label,
+        );
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].name, "a, n=1e3 (Amdahl fit: 25.0% serial)");
+        let speedups: Vec<f64> = series[0].points.iter().map(|p| p.value).collect();
+        assert_eq!(speedups, vec![1.0, 1.6, 1.0 / 0.4375]);
+        assert_eq!(series[0].fit.len(), 4);
+
+        let spec = self::spec("efficiency", false);
+        let series = scaling_series(
+            &spec.plots[0],
+            &spec.experiments[0],
+            &results(false),
+            &label,
+        );
+        assert_eq!(series[0].points[1].value, 0.8);
+
+        let spec = self::spec("karp_flatt", false);
+        let series = scaling_series(
+            &spec.plots[0],
+            &spec.experiments[0],
+            &results(false),
+            &label,
+        );
+        assert_eq!(series[0].points.len(), 2);
+        assert!(series[0]
+            .points
+            .iter()
+            .all(|p| (p.value - 0.25).abs() < 1e-12));
+    }
+
+    #[test]
+    fn weak_scaling() {
+        let label = |name: &str| name.to_string();
+        let spec = spec("speedup", true);
+        let series = scaling_series(&spec.plots[0], &spec.experiments[0], &results(true), &label);
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].name, "a, n/p=1e3 (Gustafson fit: 0.0% serial)");
+        let speedups: Vec<f64> = series[0].points.iter().map(|p| p.value).collect();
+        assert_eq!(speedups, vec![1.0, 2.0, 4.0]);
+    }
+}
@@ -0,0 +1,518 @@
+use crate::counters::Counter;
+use crate::plotting::Format;
+use crate::tuning::{parse_threads, AUTO_THREADS};
+use serde::{Deserialize, Deserializer};
+use std::error::Error;
+use std::fs;
+
+/**
+    A benchmark run described in a TOML file: a list of experiments that produce measurements and a
+    list of plots that each show a selection of the measurements of one experiment.
+*/
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BenchmarkSpec {
+    // CSV file the measurements are written to and the plots can be redrawn from
+    #[serde(default)]
+    pub results: Option<String>,
+    // HTML file with the parameters, the runtime tables and all plots of the run
+    #[serde(default)]
+    pub report: Option<String>,
+    #[serde(default)]
+    pub style: StyleSpec,
+    #[serde(default, rename = "experiment")]
+    pub experiments: Vec<ExperimentSpec>,
+    #[serde(default, rename = "plot")]
+    pub plots: Vec<PlotSpec>,
+}
+
+/**
+    Size and font of all plots. The font is a family name or one of the generic families serif,
+    sans-serif and monospace. If it is not installed, the generic sans-serif font is used instead.
+*/
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct StyleSpec {
+    pub width: u32,
+    pub height: u32,
+    pub font: String,
+}
+
+impl Default for StyleSpec {
+    fn default() -> StyleSpec {
+        StyleSpec {
+            width: 640,
+            height: 480,
+            font: "sans-serif".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExperimentSpec {
+    pub name: String,
+    // project specific kind of experiment, e.g. sorting or merging in project_1
+    #[serde(default)]
+    pub task: Option<String>,
+    // names of the algorithms, "all" selects every algorithm the workload knows
+    pub algorithms: Vec<String>,
+    pub generators: Vec<String>,
+    pub sizes: Vec<usize>,
+    // "auto" (AUTO_THREADS) takes the thread count of the tuning profile for each size
+    #[serde(default = "default_threads", deserialize_with = "deserialize_threads")]
+    pub threads: Vec<usize>,
+    // minimum number of measured runs per configuration
+    #[serde(default = "default_repetitions")]
+    pub repetitions: usize,
+    // runs before the measured ones that are not recorded, e.g. to fill the caches
+    #[serde(default = "default_warmup")]
+    pub warmup: usize,
+    // the runs are repeated until the 95% confidence interval of the median is within precision
+    // of the median (relative) or max_repetitions runs were measured
+    #[serde(default)]
+    pub precision: Option<f64>,
+    #[serde(default)]
+    pub max_repetitions: Option<usize>,
+    // repetition i generates its input with seed + i, a random seed is chosen if missing
+    #[serde(default)]
+    pub seed: Option<u64>,
+    // seconds a single run may take, larger sizes are skipped once a run is predicted or measured
+    // to take longer
+    #[serde(default)]
+    pub time_budget: Option<f64>,
+    // weak scaling: the sizes are per thread, so the input size grows with the thread count
+    #[serde(default)]
+    pub weak_scaling: bool,
+    // runs every repetition once more without timing it to count its operations
+    #[serde(default)]
+    pub count_operations: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeriesKey {
+    Algorithm,
+    Generator,
+    Threads,
+    Size,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum XValue {
+    #[default]
+    Size,
+    Threads,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorDisplay {
+    #[default]
+    None,
+    Bars,
+    Band,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum YValue {
+    // median runtime in milliseconds divided by the input size
+    #[default]
+    TimePerElement,
+    // median runtime in milliseconds
+    Time,
+    // median size of the output, e.g. the number of points on the upper hull
+    OutputSize,
+    // runtime with one thread divided by the runtime with p threads, scaled by p for weak scaling
+    Speedup,
+    // speedup divided by p
+    Efficiency,
+    // experimentally determined serial fraction (1/speedup - 1/p) / (1 - 1/p)
+    KarpFlatt,
+    // median number of operations, needs an experiment that counts operations
+    Comparisons,
+    Writes,
+    OrientationTests,
+    TangentSteps,
+}
+
+impl YValue {
+    pub fn counter(&self) -> Option<Counter> {
+        match self {
+            YValue::Comparisons => Some(Counter::Comparisons),
+            YValue::Writes => Some(Counter::Writes),
+            YValue::OrientationTests => Some(Counter::OrientationTests),
+            YValue::TangentSteps => Some(Counter::TangentSteps),
+            _ => None,
+        }
+    }
+
+    // the values derived from the one thread runtime, they need the thread count on the x axis
+    pub fn is_scaling(&self) -> bool {
+        matches!(
+            self,
+            YValue::Speedup | YValue::Efficiency | YValue::KarpFlatt
+        )
+    }
+}
+
+// what operation counts are divided by, to compare them to the bound of an algorithm
+#[derive(Debug, PartialEq, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Normalization {
+    #[default]
+    None,
+    N,
+    // base 2 logarithm
+    NLogN,
+    // h is the output size, e.g. the number of points on the upper hull
+    NH,
+}
+
+impl Normalization {
+    pub fn divisor(&self, n: usize, h: Option<usize>) -> f64 {
+        let n = n as f64;
+        match self {
+            Normalization::None => 1.0,
+            Normalization::N => n,
+            Normalization::NLogN => n * n.log2().max(1.0),
+            Normalization::NH => n * h.unwrap_or(1).max(1) as f64,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlotSpec {
+    pub experiment: String,
+    pub title: String,
+    // a .png or .svg file
+    pub path: String,
+    // override the size of the style
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    // the fields that distinguish the lines of the plot, joined for the legend
+    pub series: Vec<SeriesKey>,
+    #[serde(default)]
+    pub x: XValue,
+    // only measurements matching all given filters are plotted
+    #[serde(default)]
+    pub algorithm: Option<String>,
+    #[serde(default)]
+    pub generator: Option<String>,
+    #[serde(default)]
+    pub threads: Option<usize>,
+    #[serde(default)]
+    pub y: YValue,
+    // only for operation counts
+    #[serde(default)]
+    pub normalize: Normalization,
+    // fitted to the data if missing
+    #[serde(default)]
+    pub y_range: Option<[f64; 2]>,
+    #[serde(default)]
+    pub log_y: bool,
+    // shows the 95% confidence interval of the median of every point
+    #[serde(default)]
+    pub errors: ErrorDisplay,
+    // draws the fitted Amdahl (strong scaling) or Gustafson (weak scaling) speedup curve
+    #[serde(default)]
+    pub fit: bool,
+}
+
+fn default_threads() -> Vec<usize> {
+    vec![1]
+}
+
+fn deserialize_threads<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<usize>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Threads {
+        Count(usize),
+        Name(String),
+    }
+
+    Vec::<Threads>::deserialize(deserializer)?
+        .into_iter()
+        .map(|threads| match threads {
+            Threads::Count(threads) => parse_threads(&threads.to_string()),
+            Threads::Name(name) => parse_threads(&name),
+        })
+        .collect::<Result<Vec<usize>, String>>()
+        .map_err(serde::de::Error::custom)
+}
+
+fn default_repetitions() -> usize {
+    5
+}
+
+fn default_warmup() -> usize {
+    1
+}
+
+impl ExperimentSpec {
+    // the number of elements of a run with the given size of the spec
+    pub fn input_size(&self, size: usize, threads: usize) -> usize {
+        if self.weak_scaling {
+            size * threads
+        } else {
+            size
+        }
+    }
+
+    pub fn max_repetitions(&self) -> usize {
+        self.max_repetitions.unwrap_or(self.repetitions)
+    }
+}
+
+impl PlotSpec {
+    pub fn size(&self, style: &StyleSpec) -> (u32, u32) {
+        (
+            self.width.unwrap_or(style.width),
+            self.height.unwrap_or(style.height),
+        )
+    }
+}
+
+impl BenchmarkSpec {
+    pub fn parse(text: &str) -> Result<BenchmarkSpec, Box<dyn Error>> {
+        let spec: BenchmarkSpec = toml::from_str(text)?;
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    pub fn load(path: &str) -> Result<BenchmarkSpec, Box<dyn Error>> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        BenchmarkSpec::parse(&text).map_err(|e| format!("{}: {}", path, e).into())
+    }
+
+    pub fn experiment(&self, name: &str) -> Option<&ExperimentSpec> {
+        self.experiments.iter().find(|e| e.name == name)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (i, experiment) in self.experiments.iter().enumerate() {
+            if self.experiments[..i]
+                .iter()
+                .any(|e| e.name == experiment.name)
+            {
+                return Err(format!("duplicate experiment {}", experiment.name));
+            }
+            if experiment.repetitions == 0 {
+                return Err(format!("experiment {} has no repetitions", experiment.name));
+            }
+            if experiment.max_repetitions() < experiment.repetitions {
+                return Err(format!(
+                    "experiment {} has fewer max_repetitions than repetitions",
+                    experiment.name
+                ));
+            }
+            if experiment.time_budget.is_some_and(|t| t <= 0.0) {
+                return Err(format!(
+                    "experiment {} needs a positive time_budget",
+                    experiment.name
+                ));
+            }
+            if experiment.weak_scaling && experiment.threads.contains(&AUTO_THREADS) {
+                return Err(format!(
+                    "experiment {} can not scale the input with auto threads",
+                    experiment.name
+                ));
+            }
+            if experiment.precision.is_some_and(|p| p <= 0.0) {
+                return Err(format!(
+                    "experiment {} needs a positive precision",
+                    experiment.name
+                ));
+            }
+        }
+        if self.style.width == 0 || self.style.height == 0 {
+            return Err("the style needs a positive width and height".to_string());
+        }
+        for plot in self.plots.iter() {
+            Format::of(&plot.path)?;
+            if plot.width == Some(0) || plot.height == Some(0) {
+                return Err(format!(
+                    "plot {} needs a positive width and height",
+                    plot.title
+                ));
+            }
+            if self.experiment(&plot.experiment).is_none() {
+                return Err(format!(
+                    "plot {} refers to unknown experiment {}",
+                    plot.title, plot.experiment
+                ));
+            }
+            if plot.y.is_scaling() && plot.x != XValue::Threads {
+                return Err(format!("plot {} needs x = \"threads\"", plot.title));
+            }
+            if plot.fit && plot.y != YValue::Speedup {
+                return Err(format!("plot {} can only fit the speedup", plot.title));
+            }
+            if plot.y.counter().is_none() && plot.normalize != Normalization::None {
+                return Err(format!(
+                    "plot {} can only normalize operation counts",
+                    plot.title
+                ));
+            }
+            if plot.y.counter().is_some()
+                && self
+                    .experiment(&plot.experiment)
+                    .is_some_and(|e| !e.count_operations)
+            {
+                return Err(format!(
+                    "plot {} needs an experiment with count_operations = true",
+                    plot.title
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spec::{BenchmarkSpec, ErrorDisplay, Normalization, SeriesKey, XValue, YValue};
+    use crate::tuning::AUTO_THREADS;
+
+    const SPEC: &str = r#"
+        [[experiment]]
+        name = "inputs"
+        algorithms = ["grahams_scan", "gift_wrapping"]
+        generators = ["curve", "line"]
+        sizes = [1000, 10000, 100000]
+        time_budget = 30.0
+
+        [[plot]]
+        experiment = "inputs"
+        title = "Grahams Scan"
+        path = "grahams.png"
+        series = ["generator"]
+        algorithm = "grahams_scan"
+        y_range = [1e-6, 1e-1]
+        log_y = true
+    "#;
+
+    #[test]
+    fn parse_with_defaults() {
+        let spec = BenchmarkSpec::parse(SPEC).unwrap();
+        let experiment = &spec.experiments[0];
+        assert_eq!(experiment.threads, vec![1]);
+        assert_eq!(experiment.repetitions, 5);
+        assert_eq!(experiment.warmup, 1);
+        assert_eq!(experiment.max_repetitions(), 5);
+        assert_eq!(experiment.task, None);
+        assert_eq!(experiment.seed, None);
+        assert_eq!(experiment.time_budget, Some(30.0));
+        assert_eq!(spec.results, None);
+        assert_eq!(spec.plots[0].series, vec![SeriesKey::Generator]);
+        assert_eq!(spec.plots[0].y, YValue::TimePerElement);
+        assert_eq!(spec.plots[0].y_range, Some([1e-6, 1e-1]));
+        assert_eq!(spec.plots[0].errors, ErrorDisplay::None);
+        assert_eq!(spec.plots[0].x, XValue::Size);
+        assert!(!experiment.weak_scaling);
+        assert_eq!(experiment.input_size(1000, 4), 1000);
+        assert_eq!(spec.report, None);
+        assert_eq!(spec.plots[0].size(&spec.style), (640, 480));
+        assert!(!experiment.count_operations);
+        assert_eq!(spec.plots[0].normalize, Normalization::None);
+    }
+
+    #[test]
+    fn style_and_plot_format() {
+        let styled = format!(
+            "[style]\nwidth = 1000\nfont = \"serif\"\n{}",
+            SPEC.replace("log_y = true", "log_y = true\n        height = 300")
+        );
+        let spec = BenchmarkSpec::parse(&styled).unwrap();
+        assert_eq!(spec.style.font, "serif");
+        assert_eq!(spec.plots[0].size(&spec.style), (1000, 300));
+
+        assert!(BenchmarkSpec::parse(&SPEC.replace("grahams.png", "grahams.svg")).is_ok());
+        assert!(BenchmarkSpec::parse(&SPEC.replace("grahams.png", "grahams.jpg")).is_err());
+        assert!(BenchmarkSpec::parse(&styled.replace("width = 1000", "width = 0")).is_err());
+    }
+
+    #[test]
+    fn scaling_plots_need_threads() {
+        let speedup = SPEC.replace("log_y = true", "y = \"speedup\"\n        fit = true");
+        assert!(BenchmarkSpec::parse(&speedup).is_err());
+        let speedup = speedup.replace("fit = true", "x = \"threads\"\n        fit = true");
+        assert!(BenchmarkSpec::parse(&speedup).is_ok());
+        let efficiency = speedup.replace("\"speedup\"", "\"efficiency\"");
+        assert!(BenchmarkSpec::parse(&efficiency).is_err());
+    }
+
+    #[test]
+    fn operation_plots_need_counted_operations() {
+        let operations = SPEC.replace(
+            "log_y = true",
+            "y = \"orientation_tests\"\n        normalize = \"n_h\"",
+        );
+        assert!(BenchmarkSpec::parse(&operations).is_err());
+        let counted = operations.replace(
+            "time_budget",
+            "count_operations = true\n        time_budget",
+        );
+        let spec = BenchmarkSpec::parse(&counted).unwrap();
+        assert_eq!(spec.plots[0].normalize, Normalization::NH);
+        assert_eq!(spec.plots[0].normalize.divisor(1000, Some(4)), 4000.0);
+        assert_eq!(Normalization::NLogN.divisor(1024, None), 10240.0);
+
+        let normalized_time = counted.replace("y = \"orientation_tests\"", "y = \"time\"");
+        assert!(BenchmarkSpec::parse(&normalized_time).is_err());
+    }
+
+    #[test]
+    fn auto_threads() {
+        let auto = SPEC.replace(
+            "time_budget",
+            "threads = [1, \"auto\"]\n        time_budget",
+        );
+        let spec = BenchmarkSpec::parse(&auto).unwrap();
+        assert_eq!(spec.experiments[0].threads, vec![1, AUTO_THREADS]);
+
+        assert!(BenchmarkSpec::parse(&auto.replace("auto", "many")).is_err());
+        assert!(BenchmarkSpec::parse(&auto.replace("[1, ", "[0, ")).is_err());
+        let weak = auto.replace("time_budget", "weak_scaling = true\n        time_budget");
+        assert!(BenchmarkSpec::parse(&weak).is_err());
+    }
+
+    #[test]
+    fn adaptive_repetitions() {
+        let spec = SPEC.replace(
+            "sizes = [1000, 10000, 100000]",
+            "sizes = [1000]\n        precision = 0.05\n        max_repetitions = 50",
+        );
+        let spec = BenchmarkSpec::parse(&spec).unwrap();
+        assert_eq!(spec.experiments[0].precision, Some(0.05));
+        assert_eq!(spec.experiments[0].max_repetitions(), 50);
+
+        let spec = SPEC.replace(
+            "sizes = [1000, 10000, 100000]",
+            "sizes = [1000]\n        max_repetitions = 2",
+        );
+        assert!(BenchmarkSpec::parse(&spec).is_err());
+    }
+
+    #[test]
+    fn unknown_experiment_in_plot() {
+        let spec = SPEC.replace(
+            "experiment = \"inputs\"\n        title",
+            "experiment = \"other\"\n        title",
+        );
+        assert!(BenchmarkSpec::parse(&spec).is_err());
+    }
+
+    #[test]
+    fn unknown_field() {
+        let spec = SPEC
+            .replace("repetitions", "repetition")
+            .replace("sizes = ", "repeats = 2\n        sizes = ");
+        assert!(BenchmarkSpec::parse(&spec).is_err());
+    }
+}
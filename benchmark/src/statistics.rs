@@ -0,0 +1,231 @@
+// scales the median absolute deviation to the standard deviation for normally distributed values
+const MAD_TO_STANDARD_DEVIATION: f64 = 1.4826;
+// values further than this many (scaled) MADs away from the median are outliers
+const OUTLIER_THRESHOLD: f64 = 3.0;
+// quantile of the standard normal distribution for a 95% confidence interval
+const Z_95: f64 = 1.96;
+
+// robust statistics of the repeated runs of one configuration
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Summary {
+    pub count: usize,
+    pub median: f64,
+    // median absolute deviation from the median
+    pub mad: f64,
+    // mean of the values that are not outliers
+    pub mean: f64,
+    // distribution free 95% confidence interval of the median
+    pub ci_low: f64,
+    pub ci_high: f64,
+    pub outliers: usize,
+}
+
+impl Summary {
+    // half the width of the confidence interval relative to the median
+    pub fn relative_error(&self) -> f64 {
+        if self.median == 0.0 {
+            return if self.ci_high == self.ci_low {
+                0.0
+            } else {
+                f64::INFINITY
+            };
+        }
+        (self.ci_high - self.ci_low) / 2.0 / self.median.abs()
+    }
+}
+
+fn median_of_sorted(sorted: &[f64]) -> f64 {
+    let n = sorted.len();
+    if n % 2 == 1 {
+        sorted[n / 2]
+    } else {
+        (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0
+    }
+}
+
+fn sorted(values: &[f64]) -> Vec<f64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    sorted
+}
+
+pub fn median(values: &[f64]) -> f64 {
+    median_of_sorted(&sorted(values))
+}
+
+pub fn median_absolute_deviation(values: &[f64]) -> f64 {
+    let center = median(values);
+    let deviations: Vec<f64> = values.iter().map(|v| (v - center).abs()).collect();
+    median(&deviations)
+}
+
+/**
+    Flags the values that are more than OUTLIER_THRESHOLD standard deviations (estimated by the
+    MAD) away from the median. Nothing is flagged if at least half of the values are equal.
+*/
+pub fn outliers(values: &[f64]) -> Vec<bool> {
+    if values.is_empty() {
+        return vec![];
+    }
+    let median = median(values);
+    let limit = OUTLIER_THRESHOLD * MAD_TO_STANDARD_DEVIATION * median_absolute_deviation(values);
+    values
+        .iter()
+        .map(|v| limit > 0.0 && (v - median).abs() > limit)
+        .collect()
+}
+
+/**
+    The confidence interval of the median uses the order statistics around the middle, so it does
+    not assume normally distributed runtimes. With few values it spans from the minimum to the
+    maximum.
+*/
+pub fn summarize(values: &[f64]) -> Summary {
+    assert!(!values.is_empty());
+    let sorted = sorted(values);
+    let n = sorted.len();
+
+    let spread = Z_95 * (n as f64).sqrt() / 2.0;
+    let low_rank = ((n as f64 / 2.0 - spread).floor() as usize).max(1);
+    let high_rank = ((1.0 + n as f64 / 2.0 + spread).ceil() as usize).min(n);
+
+    let flags = outliers(values);
+    let kept: Vec<f64> = values
+        .iter()
+        .zip(flags.iter())
+        .filter(|(_, outlier)| !**outlier)
+        .map(|(v, _)| *v)
+        .collect();
+
+    Summary {
+        count: n,
+        median: median_of_sorted(&sorted),
+        mad: median_absolute_deviation(values),
+        mean: kept.iter().sum::<f64>() / kept.len() as f64,
+        ci_low: sorted[low_rank - 1],
+        ci_high: sorted[high_rank - 1],
+        outliers: n - kept.len(),
+    }
+}
+
+// standard normal cumulative distribution function, Abramowitz and Stegun 7.1.26
+fn normal_cdf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs() / std::f64::consts::SQRT_2);
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - polynomial * (-x * x / 2.0).exp();
+    if x >= 0.0 {
+        (1.0 + erf) / 2.0
+    } else {
+        (1.0 - erf) / 2.0
+    }
+}
+
+/**
+    Two-sided Mann-Whitney U test with the normal approximation and a correction for ties. Returns
+    the probability that samples at least this different are drawn from the same distribution.
+    Like the median it does not assume normally distributed runtimes.
+*/
+pub fn mann_whitney_p_value(a: &[f64], b: &[f64]) -> f64 {
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    if a.is_empty() || b.is_empty() {
+        return 1.0;
+    }
+
+    // rank all values together, ties get the average of their ranks
+    let mut all: Vec<(f64, bool)> = a
+        .iter()
+        .map(|v| (*v, true))
+        .chain(b.iter().map(|v| (*v, false)))
+        .collect();
+    all.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    let mut rank_sum_a = 0.0;
+    let mut tie_correction = 0.0;
+    let mut i = 0;
+    while i < all.len() {
+        let mut j = i;
+        while j < all.len() && all[j].0 == all[i].0 {
+            j += 1;
+        }
+        let rank = (i + j + 1) as f64 / 2.0;
+        rank_sum_a += rank * all[i..j].iter().filter(|(_, in_a)| *in_a).count() as f64;
+        let ties = (j - i) as f64;
+        tie_correction += ties * ties * ties - ties;
+        i = j;
+    }
+
+    let u = rank_sum_a - n1 * (n1 + 1.0) / 2.0;
+    let n = n1 + n2;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - tie_correction / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        return 1.0;
+    }
+    let z = (u - n1 * n2 / 2.0).abs() / variance.sqrt();
+    (2.0 * (1.0 - normal_cdf(z))).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::statistics::{
+        mann_whitney_p_value, median, median_absolute_deviation, normal_cdf, outliers, summarize,
+    };
+
+    #[test]
+    fn median_and_mad() {
+        assert_eq!(median(&[3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(&[4.0, 1.0, 3.0, 2.0]), 2.5);
+        assert_eq!(median_absolute_deviation(&[1.0, 2.0, 3.0, 4.0, 100.0]), 1.0);
+    }
+
+    #[test]
+    fn flags_outliers() {
+        let values = [1.0, 1.1, 0.9, 1.0, 1.05, 10.0];
+        assert_eq!(
+            outliers(&values),
+            vec![false, false, false, false, false, true]
+        );
+        // identical values have no spread, so nothing is an outlier
+        assert_eq!(outliers(&[2.0, 2.0, 2.0, 5.0]), vec![false; 4]);
+    }
+
+    #[test]
+    fn summary() {
+        let values = [1.0, 1.1, 0.9, 1.0, 1.05, 10.0];
+        let summary = summarize(&values);
+        assert_eq!(summary.count, 6);
+        assert_eq!(summary.median, 1.025);
+        assert_eq!(summary.outliers, 1);
+        assert!((summary.mean - 1.01).abs() < 1e-12);
+        assert!(summary.ci_low <= summary.median && summary.median <= summary.ci_high);
+    }
+
+    #[test]
+    fn confidence_interval_shrinks() {
+        let values = |n: usize| -> Vec<f64> { (0..n).map(|i| (i % 10) as f64 + 10.0).collect() };
+        let few = summarize(&values(5));
+        let many = summarize(&values(1000));
+        assert_eq!((few.ci_low, few.ci_high), (10.0, 14.0));
+        assert!(many.relative_error() < few.relative_error());
+        assert!(many.relative_error() < 0.1);
+    }
+
+    #[test]
+    fn normal_distribution() {
+        assert!((normal_cdf(0.0) - 0.5).abs() < 1e-7);
+        assert!((normal_cdf(1.96) - 0.975).abs() < 1e-4);
+        assert!((normal_cdf(-1.0) - 0.158655).abs() < 1e-4);
+    }
+
+    #[test]
+    fn mann_whitney() {
+        let a: Vec<f64> = (0..20).map(|i| 1.0 + i as f64 * 0.01).collect();
+        let shifted: Vec<f64> = a.iter().map(|v| v + 0.5).collect();
+        let interleaved: Vec<f64> = a.iter().map(|v| v + 0.005).collect();
+        assert!(mann_whitney_p_value(&a, &shifted) < 0.001);
+        assert!(mann_whitney_p_value(&a, &interleaved) > 0.5);
+        assert_eq!(mann_whitney_p_value(&[1.0, 1.0], &[1.0, 1.0]), 1.0);
+        assert_eq!(mann_whitney_p_value(&a, &[]), 1.0);
+    }
+}
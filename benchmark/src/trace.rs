@@ -0,0 +1,267 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+// a timed section of the work of one thread
+#[derive(Debug, PartialEq, Clone)]
+pub struct Span {
+    pub name: &'static str,
+    // small number of the thread, in the order in which the threads recorded their first span
+    pub thread: u64,
+    // since the start of the recording
+    pub start: Duration,
+    pub duration: Duration,
+    // number of elements the span worked on, if it is known
+    pub size: Option<usize>,
+}
+
+struct Recording {
+    start: Instant,
+    spans: Vec<Span>,
+}
+
+// number of recordings of record and record_locally that are running, checked before anything
+// else, so that spans cost a single load while nothing is recorded
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+// whether record is running
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static RECORDING: Mutex<Option<Recording>> = Mutex::new(None);
+// only one recording at a time, e.g. when tests run in parallel
+static RECORDER: Mutex<()> = Mutex::new(());
+static NEXT_THREAD: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static THREAD: u64 = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+    static LOCAL: RefCell<Option<Recording>> = const { RefCell::new(None) };
+}
+
+// a panic while recording must not disable tracing for the rest of the process
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// records the span from its creation until it is dropped
+#[must_use]
+pub struct SpanGuard(Option<(&'static str, Option<usize>, Instant)>);
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        let Some((name, size, start)) = self.0.take() else {
+            return;
+        };
+        let end = Instant::now();
+        let thread = THREAD.with(|thread| *thread);
+        let add = |recording: &mut Recording| {
+            recording.spans.push(Span {
+                name,
+                thread,
+                start: start.saturating_duration_since(recording.start),
+                duration: end - start,
+                size,
+            })
+        };
+        if ENABLED.load(Ordering::Relaxed) {
+            if let Some(recording) = lock(&RECORDING).as_mut() {
+                add(recording);
+            }
+        }
+        LOCAL.with(|local| {
+            if let Some(recording) = local.borrow_mut().as_mut() {
+                add(recording);
+            }
+        });
+    }
+}
+
+pub fn span(name: &'static str) -> SpanGuard {
+    if ACTIVE.load(Ordering::Relaxed) == 0 {
+        return SpanGuard(None);
+    }
+    SpanGuard(Some((name, None, Instant::now())))
+}
+
+pub fn span_with_size(name: &'static str, size: usize) -> SpanGuard {
+    if ACTIVE.load(Ordering::Relaxed) == 0 {
+        return SpanGuard(None);
+    }
+    SpanGuard(Some((name, Some(size), Instant::now())))
+}
+
+/**
+    Runs f and returns the spans recorded by all threads while it ran, ordered by their start.
+    Without a recording the spans are not timed at all, so the instrumented algorithms can stay
+    instrumented in the benchmarks.
+*/
+pub fn record<R>(f: impl FnOnce() -> R) -> (R, Vec<Span>) {
+    let _recorder = lock(&RECORDER);
+    *lock(&RECORDING) = Some(Recording {
+        start: Instant::now(),
+        spans: vec![],
+    });
+    ENABLED.store(true, Ordering::Relaxed);
+    ACTIVE.fetch_add(1, Ordering::Relaxed);
+
+    let result = f();
+
+    ACTIVE.fetch_sub(1, Ordering::Relaxed);
+    ENABLED.store(false, Ordering::Relaxed);
+    let mut spans = lock(&RECORDING).take().map_or(vec![], |r| r.spans);
+    spans.sort_by_key(|span| (span.start, span.thread));
+    (result, spans)
+}
+
+/**
+    Runs f and returns only the spans of the calling thread, so unlike `record` the result does
+    not depend on other threads and several recordings can run at the same time. The spans of a
+    nested call are not added to the outer one.
+*/
+pub fn record_locally<R>(f: impl FnOnce() -> R) -> (R, Vec<Span>) {
+    ACTIVE.fetch_add(1, Ordering::Relaxed);
+    let outer = LOCAL.with(|local| {
+        local.replace(Some(Recording {
+            start: Instant::now(),
+            spans: vec![],
+        }))
+    });
+
+    let result = f();
+
+    let recording = LOCAL.with(|local| local.replace(outer));
+    ACTIVE.fetch_sub(1, Ordering::Relaxed);
+    let mut spans = recording.map_or(vec![], |r| r.spans);
+    spans.sort_by_key(|span| span.start);
+    (result, spans)
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/**
+    The spans in the Chrome trace event format, which chrome://tracing and Perfetto show as one
+    timeline per thread. Every span is a complete event with times in microseconds.
+*/
+pub fn chrome_trace(process: &str, spans: &[Span]) -> String {
+    let mut events = vec![format!(
+        "{{\"name\":\"process_name\",\"ph\":\"M\",\"pid\":1,\"args\":{{\"name\":\"{}\"}}}}",
+        escape(process)
+    )];
+    for span in spans.iter() {
+        let args = match span.size {
+            Some(size) => format!(",\"args\":{{\"n\":{}}}", size),
+            None => String::new(),
+        };
+        events.push(format!(
+            "{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":1,\"tid\":{}{}}}",
+            escape(span.name),
+            escape(process),
+            span.start.as_secs_f64() * 1e6,
+            span.duration.as_secs_f64() * 1e6,
+            span.thread,
+            args
+        ));
+    }
+    format!(
+        "{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[\n{}\n]}}\n",
+        events.join(",\n")
+    )
+}
+
+pub fn write_chrome_trace(path: &str, process: &str, spans: &[Span]) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, chrome_trace(process, spans)).map_err(|e| format!("{}: {}", path, e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trace::{chrome_trace, record, record_locally, span, span_with_size, Span};
+    use std::time::Duration;
+
+    #[test]
+    fn records_spans_of_all_threads() {
+        let (sum, spans) = record(|| {
+            let _outer = span("outer");
+            std::thread::scope(|scope| {
+                let handle = scope.spawn(|| {
+                    let _inner = span_with_size("inner", 123456);
+                    1
+                });
+                let _inner = span_with_size("inner", 123457);
+                handle.join().unwrap() + 1
+            })
+        });
+        assert_eq!(sum, 2);
+
+        let inner: Vec<&Span> = spans
+            .iter()
+            .filter(|s| s.name == "inner" && s.size.is_some_and(|n| n >= 123456))
+            .collect();
+        assert_eq!(inner.len(), 2);
+        assert_ne!(inner[0].thread, inner[1].thread);
+        let outer = spans.iter().find(|s| s.name == "outer").unwrap();
+        assert!(inner.iter().all(
+            |s| s.start >= outer.start && s.start + s.duration <= outer.start + outer.duration
+        ));
+    }
+
+    #[test]
+    fn records_only_the_own_thread_locally() {
+        let (_, spans) = record_locally(|| {
+            let _outer = span("outer");
+            std::thread::scope(|scope| {
+                scope.spawn(|| drop(span("other thread")));
+            });
+            let (_, inner) = record_locally(|| drop(span_with_size("inner", 2)));
+            assert_eq!(inner.len(), 1);
+            drop(span_with_size("after", 1));
+        });
+        let names: Vec<&str> = spans.iter().map(|s| s.name).collect();
+        // the outer span started first, it is ordered by its start
+        assert_eq!(names, vec!["outer", "after"]);
+        assert!(spans.iter().all(|s| s.thread == spans[0].thread));
+    }
+
+    #[test]
+    fn nothing_is_recorded_outside_of_a_recording() {
+        drop(span("before"));
+        let (_, spans) = record(|| {});
+        assert!(spans.iter().all(|s| s.name != "before"));
+    }
+
+    #[test]
+    fn chrome_format() {
+        let spans = [Span {
+            name: "leaf \"merge\"",
+            thread: 3,
+            start: Duration::from_micros(10),
+            duration: Duration::from_micros(5),
+            size: Some(100),
+        }];
+        let json = chrome_trace("project_1", &spans);
+        assert!(json.starts_with("{\"displayTimeUnit\":\"ms\",\"traceEvents\":["));
+        assert!(json.contains(
+            "{\"name\":\"leaf \\\"merge\\\"\",\"cat\":\"project_1\",\"ph\":\"X\",\"ts\":10.000,\"dur\":5.000,\"pid\":1,\"tid\":3,\"args\":{\"n\":100}}"
+        ));
+    }
+}
@@ -0,0 +1,292 @@
+use crate::statistics::summarize;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::thread::available_parallelism;
+use std::time::Instant;
+
+/**
+    The thread count "auto" (in specs and on the command line): the parallel entry points look up
+    the thread count and the sequential cutoff for the input size in the tuning profile.
+*/
+pub const AUTO_THREADS: usize = 0;
+
+// "auto" or a thread count
+pub fn parse_threads(text: &str) -> Result<usize, String> {
+    match text {
+        "auto" => Ok(AUTO_THREADS),
+        _ => match text.parse() {
+            Ok(0) | Err(_) => Err(format!("invalid thread count {}", text)),
+            Ok(threads) => Ok(threads),
+        },
+    }
+}
+
+// the thread count as written in tables and legends
+pub fn thread_count(threads: usize) -> String {
+    match threads {
+        AUTO_THREADS => "auto".to_string(),
+        threads => threads.to_string(),
+    }
+}
+
+// the best setting of an algorithm measured for inputs of at least size elements
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tuning {
+    pub algorithm: String,
+    pub size: usize,
+    pub threads: usize,
+    // inputs (or parts of inputs) of at most this many elements are handled sequentially
+    pub cutoff: usize,
+}
+
+/**
+    The settings chosen by `calibrate` on one machine, stored as TOML next to the benchmark spec
+    of a project.
+*/
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TuningProfile {
+    // the machine the profile was measured on
+    pub cpus: usize,
+    #[serde(default)]
+    pub tuning: Vec<Tuning>,
+}
+
+impl TuningProfile {
+    pub fn parse(text: &str) -> Result<TuningProfile, Box<dyn Error>> {
+        let profile: TuningProfile = toml::from_str(text)?;
+        if let Some(tuning) = profile
+            .tuning
+            .iter()
+            .find(|t| t.threads == 0 || t.cutoff == 0)
+        {
+            return Err(format!(
+                "tuning of {} for n={} needs positive threads and cutoff",
+                tuning.algorithm, tuning.size
+            )
+            .into());
+        }
+        Ok(profile)
+    }
+
+    pub fn load(path: &str) -> Result<TuningProfile, Box<dyn Error>> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        TuningProfile::parse(&text).map_err(|e| format!("{}: {}", path, e).into())
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string(self)?).map_err(|e| format!("{}: {}", path, e))?;
+        Ok(())
+    }
+
+    /**
+        The setting measured for the largest size not above n, or for the smallest size if n is
+        smaller than all of them.
+    */
+    pub fn lookup(&self, algorithm: &str, n: usize) -> Option<&Tuning> {
+        let tunings = self.tuning.iter().filter(|t| t.algorithm == algorithm);
+        tunings
+            .clone()
+            .filter(|t| t.size <= n)
+            .max_by_key(|t| t.size)
+            .or_else(|| tunings.min_by_key(|t| t.size))
+    }
+
+    /**
+        The thread count and cutoff to run the algorithm with. A fixed thread count is kept with
+        the given default cutoff, "auto" uses the profile or all cpus if the algorithm was not
+        calibrated.
+    */
+    pub fn resolve(
+        &self,
+        algorithm: &str,
+        n: usize,
+        threads: usize,
+        default_cutoff: usize,
+    ) -> (usize, usize) {
+        if threads != AUTO_THREADS {
+            return (threads, default_cutoff);
+        }
+        match self.lookup(algorithm, n) {
+            Some(tuning) => (tuning.threads, tuning.cutoff),
+            None => (
+                available_parallelism().map_or(1, |n| n.get()),
+                default_cutoff,
+            ),
+        }
+    }
+}
+
+static PROFILE: OnceLock<TuningProfile> = OnceLock::new();
+
+/**
+    Makes the profile at path the one used for "auto" in this process. A missing file is not an
+    error, "auto" then uses all cpus. Only the first call has an effect.
+*/
+pub fn load_profile(path: &str) -> Result<(), Box<dyn Error>> {
+    let profile = if Path::new(path).exists() {
+        TuningProfile::load(path)?
+    } else {
+        TuningProfile::default()
+    };
+    let _ = PROFILE.set(profile);
+    Ok(())
+}
+
+// the profile loaded by load_profile, empty if none was loaded
+pub fn profile() -> &'static TuningProfile {
+    PROFILE.get_or_init(TuningProfile::default)
+}
+
+/**
+    Measures the algorithm for every combination of thread count and cutoff on each input size and
+    keeps the one with the smallest median runtime. `run(size, threads, cutoff)` runs the algorithm
+    once on a fresh input, only the algorithm should be timed.
+*/
+pub fn calibrate(
+    algorithm: &str,
+    sizes: &[usize],
+    threads: &[usize],
+    cutoffs: &[usize],
+    repetitions: usize,
+    run: &dyn Fn(usize, usize, usize) -> f64,
+) -> Vec<Tuning> {
+    let mut tunings = vec![];
+    for &size in sizes {
+        let mut best: Option<(f64, Tuning)> = None;
+        for &p in threads {
+            // a single thread and cutoffs of the input size or more all mean a sequential run
+            for &cutoff in cutoffs
+                .iter()
+                .filter(|&&c| c == cutoffs[0] || (p > 1 && c < size))
+            {
+                let seconds: Vec<f64> = (0..repetitions.max(1))
+                    .map(|_| run(size, p, cutoff))
+                    .collect();
+                let median = summarize(&seconds).median;
+                if best.as_ref().is_none_or(|(fastest, _)| median < *fastest) {
+                    best = Some((
+                        median,
+                        Tuning {
+                            algorithm: algorithm.to_string(),
+                            size,
+                            threads: p,
+                            cutoff,
+                        },
+                    ));
+                }
+            }
+        }
+        if let Some((median, tuning)) = best {
+            println!(
+                "{}, n={:.0e}: {} threads, cutoff {}, median {:.6}s",
+                algorithm, size as f64, tuning.threads, tuning.cutoff, median
+            );
+            tunings.push(tuning);
+        }
+    }
+    tunings
+}
+
+// the thread counts worth trying on this machine: powers of two up to the number of cpus
+pub fn candidate_threads() -> Vec<usize> {
+    let cpus = available_parallelism().map_or(1, |n| n.get());
+    let mut threads: Vec<usize> = (0..).map(|i| 1 << i).take_while(|&p| p < cpus).collect();
+    threads.push(cpus);
+    threads
+}
+
+// times f, e.g. to implement the run of calibrate
+pub fn seconds(f: impl FnOnce()) -> f64 {
+    let now = Instant::now();
+    f();
+    now.elapsed().as_secs_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tuning::{
+        calibrate, candidate_threads, parse_threads, thread_count, Tuning, TuningProfile,
+        AUTO_THREADS,
+    };
+    use std::env::temp_dir;
+
+    fn tuning(size: usize, threads: usize, cutoff: usize) -> Tuning {
+        Tuning {
+            algorithm: "sort".to_string(),
+            size,
+            threads,
+            cutoff,
+        }
+    }
+
+    #[test]
+    fn lookup_by_size() {
+        let profile = TuningProfile {
+            cpus: 8,
+            tuning: vec![tuning(1000, 1, 1000), tuning(100000, 8, 4096)],
+        };
+        assert_eq!(profile.lookup("sort", 10), Some(&profile.tuning[0]));
+        assert_eq!(profile.lookup("sort", 99999), Some(&profile.tuning[0]));
+        assert_eq!(profile.lookup("sort", 1000000), Some(&profile.tuning[1]));
+        assert_eq!(profile.lookup("merge", 1000), None);
+
+        assert_eq!(profile.resolve("sort", 5000, AUTO_THREADS, 1), (1, 1000));
+        assert_eq!(profile.resolve("sort", 5000, 4, 1), (4, 1));
+        assert!(profile.resolve("merge", 5000, AUTO_THREADS, 1).0 >= 1);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let profile = TuningProfile {
+            cpus: 4,
+            tuning: vec![tuning(1000, 2, 64)],
+        };
+        let path = temp_dir().join("benchmark_tuning_test.toml");
+        let path = path.to_str().unwrap();
+        profile.save(path).unwrap();
+        assert_eq!(TuningProfile::load(path).unwrap(), profile);
+
+        assert!(TuningProfile::parse(
+            "cpus = 4\n[[tuning]]\nalgorithm = \"a\"\nsize = 1\nthreads = 0\ncutoff = 1"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn calibration_picks_the_fastest_setting() {
+        // pretends that a cutoff of 100 and 4 threads is best for large inputs, one thread for
+        // small ones
+        let run = |size: usize, threads: usize, cutoff: usize| {
+            let overhead = threads as f64 * 1000.0;
+            let grain = (cutoff as f64 - 100.0).abs();
+            size as f64 / threads as f64 + overhead + grain
+        };
+        let tunings = calibrate(
+            "sort",
+            &[10, 1000000],
+            &[1, 2, 4],
+            &[1, 100, 10000],
+            1,
+            &run,
+        );
+        assert_eq!(tunings, vec![tuning(10, 1, 1), tuning(1000000, 4, 100)]);
+    }
+
+    #[test]
+    fn auto_threads() {
+        assert_eq!(parse_threads("auto"), Ok(AUTO_THREADS));
+        assert_eq!(parse_threads("4"), Ok(4));
+        assert!(parse_threads("0").is_err());
+        assert_eq!(thread_count(AUTO_THREADS), "auto");
+        assert_eq!(thread_count(2), "2");
+        assert_eq!(candidate_threads()[0], 1);
+    }
+}
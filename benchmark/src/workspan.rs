@@ -0,0 +1,138 @@
+use crate::counters::counted_locally;
+use std::fmt::Write;
+
+/**
+    Work and span of a fork-join computation on a virtual PRAM, in counted operations (see
+    `counters`). The work is the number of operations of all tasks, the span the number of
+    operations on the longest chain of tasks that have to run one after another. Both only depend
+    on the input, not on the machine, so simulated runs are comparable everywhere.
+*/
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct WorkSpan {
+    pub work: u64,
+    pub span: u64,
+}
+
+impl WorkSpan {
+    // a task that runs on a single processor
+    pub fn sequential(operations: u64) -> WorkSpan {
+        WorkSpan {
+            work: operations,
+            span: operations,
+        }
+    }
+
+    // the task followed by next, e.g. the merge after the recursive calls
+    pub fn then(self, next: WorkSpan) -> WorkSpan {
+        WorkSpan {
+            work: self.work + next.work,
+            span: self.span + next.span,
+        }
+    }
+
+    // tasks that are forked together and joined before anything else happens
+    pub fn parallel(tasks: impl IntoIterator<Item = WorkSpan>) -> WorkSpan {
+        tasks
+            .into_iter()
+            .fold(WorkSpan::default(), |all, task| WorkSpan {
+                work: all.work + task.work,
+                span: all.span.max(task.span),
+            })
+    }
+
+    // the average number of processors that can be kept busy
+    pub fn parallelism(&self) -> f64 {
+        self.work as f64 / self.span.max(1) as f64
+    }
+
+    /**
+        Upper bound of the time on p processors by Brent's theorem, (work - span) / p + span. A
+        greedy scheduler is never slower, one processor takes exactly the work.
+    */
+    pub fn brent(&self, processors: usize) -> f64 {
+        (self.work - self.span) as f64 / processors.max(1) as f64 + self.span as f64
+    }
+}
+
+// runs f on the calling thread and takes its operations as a sequential task
+pub fn measure<R>(f: impl FnOnce() -> R) -> (R, WorkSpan) {
+    let (result, operations) = counted_locally(f);
+    (result, WorkSpan::sequential(operations.total()))
+}
+
+/**
+    A table of simulated runs with the processor count they were simulated for. The speedup is
+    the work of the first run divided by the Brent bound, so the first run should be the one with
+    a single processor.
+*/
+pub fn simulation_table(title: &str, runs: &[(usize, WorkSpan)]) -> String {
+    let mut table = String::new();
+    let _ = writeln!(
+        table,
+        "{:<30}{:>16}{:>16}{:>14}{:>18}{:>10}",
+        title, "work", "span", "parallelism", "Brent time", "speedup"
+    );
+    let sequential = runs.first().map_or(0, |(_, run)| run.work);
+    for (processors, run) in runs.iter() {
+        let time = run.brent(*processors);
+        let _ = writeln!(
+            table,
+            "{:<30}{:>16}{:>16}{:>14.2}{:>18.0}{:>10.2}",
+            format!("p={}", processors),
+            run.work,
+            run.span,
+            run.parallelism(),
+            time,
+            sequential as f64 / time
+        );
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::counters::{count, Counter};
+    use crate::workspan::{measure, simulation_table, WorkSpan};
+
+    #[test]
+    fn fork_join_composition() {
+        // two tasks of 4 and 6 operations in parallel, followed by a merge of 10
+        let run = WorkSpan::parallel([WorkSpan::sequential(4), WorkSpan::sequential(6)])
+            .then(WorkSpan::sequential(10));
+        assert_eq!(run, WorkSpan { work: 20, span: 16 });
+        assert_eq!(run.parallelism(), 1.25);
+        assert_eq!(run.brent(1), 20.0);
+        assert_eq!(run.brent(2), 18.0);
+        assert_eq!(WorkSpan::parallel([]), WorkSpan::default());
+    }
+
+    #[test]
+    fn measured_tasks() {
+        let (result, task) = measure(|| {
+            count(Counter::Comparisons, 3);
+            count(Counter::Writes, 2);
+            "done"
+        });
+        assert_eq!(result, "done");
+        assert_eq!(task, WorkSpan::sequential(5));
+
+        let table = simulation_table(
+            "merge sort",
+            &[
+                (1, WorkSpan::sequential(100)),
+                (
+                    2,
+                    WorkSpan {
+                        work: 100,
+                        span: 60,
+                    },
+                ),
+            ],
+        );
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("merge sort"));
+        // (100 - 60) / 2 + 60 = 80 and a speedup of 100 / 80
+        assert!(lines[2].starts_with("p=2") && lines[2].ends_with("80      1.25"));
+    }
+}
@@ -0,0 +1,327 @@
+use crate::merge::MergeEvent;
+use benchmark::plotting::{color, font_family, render_animation, Drawing};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::GREY;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+use std::error::Error;
+use std::ops::Range;
+
+// rows of the animation from bottom to top
+const ROWS: [&str; 3] = ["output", "right", "left"];
+
+// what an animation frame shows after the events up to it
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct MergeAnimationState {
+    pub left: Vec<i64>,
+    pub right: Vec<i64>,
+    // the thread that merges each element, None until the chunks are known
+    pub left_threads: Vec<Option<usize>>,
+    pub right_threads: Vec<Option<usize>>,
+    pub output_threads: Vec<Option<usize>>,
+    // the merged values written so far
+    pub output: Vec<Option<i64>>,
+    // the ranks in left of the first elements of the chunks of right
+    pub ranks: Vec<usize>,
+    // the part of output of each thread, by thread
+    pub chunks: Vec<Range<usize>>,
+    // the thread the last event was about
+    pub highlight: Option<usize>,
+    pub caption: String,
+}
+
+fn assign(threads: &mut [Option<usize>], range: &Range<usize>, thread: usize) {
+    for owner in &mut threads[range.clone()] {
+        *owner = Some(thread);
+    }
+}
+
+impl MergeAnimationState {
+    pub fn new(left: &[i64], right: &[i64]) -> MergeAnimationState {
+        let n = left.len() + right.len();
+        MergeAnimationState {
+            left: left.to_vec(),
+            right: right.to_vec(),
+            left_threads: vec![None; left.len()],
+            right_threads: vec![None; right.len()],
+            output_threads: vec![None; n],
+            output: vec![None; n],
+            ..MergeAnimationState::default()
+        }
+    }
+
+    pub fn apply(&mut self, event: &MergeEvent) {
+        match event {
+            MergeEvent::Rank {
+                thread,
+                right,
+                rank,
+            } => {
+                self.ranks.push(*rank);
+                self.highlight = Some(*thread);
+                self.caption = format!(
+                    "thread {}: right[{}] = {} belongs before left[{}]",
+                    thread, right, self.right[*right], rank
+                );
+            }
+            MergeEvent::Chunk {
+                thread,
+                left,
+                right,
+                output,
+            } => {
+                assign(&mut self.left_threads, left, *thread);
+                assign(&mut self.right_threads, right, *thread);
+                assign(&mut self.output_threads, output, *thread);
+                if self.chunks.len() <= *thread {
+                    self.chunks.resize(*thread + 1, 0..0);
+                }
+                self.chunks[*thread] = output.clone();
+                self.highlight = Some(*thread);
+                self.caption = format!(
+                    "thread {} merges left[{}..{}] and right[{}..{}]",
+                    thread, left.start, left.end, right.start, right.end
+                );
+            }
+            MergeEvent::LeafMerge { thread } => {
+                let output = self.chunks[*thread].clone();
+                // the leaf merge is stable, so its result is the sorted values of its chunk
+                let mut values: Vec<i64> = self
+                    .left_threads
+                    .iter()
+                    .zip(&self.left)
+                    .chain(self.right_threads.iter().zip(&self.right))
+                    .filter(|(owner, _)| **owner == Some(*thread))
+                    .map(|(_, value)| *value)
+                    .collect();
+                values.sort();
+                for (slot, value) in self.output[output.clone()].iter_mut().zip(values) {
+                    *slot = Some(value);
+                }
+                self.highlight = Some(*thread);
+                self.caption = format!(
+                    "thread {} wrote output[{}..{}]",
+                    thread, output.start, output.end
+                );
+            }
+        }
+    }
+}
+
+/**
+    The state after every event, followed by a last frame with the merged output, so that an
+    animation has one frame per event.
+*/
+pub fn frames(left: &[i64], right: &[i64], events: &[MergeEvent]) -> Vec<MergeAnimationState> {
+    let mut state = MergeAnimationState::new(left, right);
+    let mut frames: Vec<MergeAnimationState> = events
+        .iter()
+        .map(|event| {
+            state.apply(event);
+            state.clone()
+        })
+        .collect();
+    frames.push(MergeAnimationState {
+        highlight: None,
+        caption: format!(
+            "merged {} elements with {} threads",
+            state.output.len(),
+            state.chunks.len()
+        ),
+        ..state
+    });
+    frames
+}
+
+struct Frame<'a> {
+    title: &'a str,
+    state: &'a MergeAnimationState,
+    step: usize,
+    steps: usize,
+}
+
+impl Drawing for Frame<'_> {
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let font = font_family("sans-serif");
+        let state = self.state;
+        root.fill(&WHITE)?;
+        let root = root.margin(10, 10, 10, 10);
+
+        let caption = format!(
+            "{} {}/{}: {}",
+            self.title, self.step, self.steps, state.caption
+        );
+        let n = state.output.len().max(1);
+        let mut chart = ChartBuilder::on(&root)
+            .caption(caption, (font, 18).into_font())
+            .x_label_area_size(35)
+            .y_label_area_size(55)
+            .build_cartesian_2d(0f64..n as f64, 0f64..ROWS.len() as f64)?;
+        chart
+            .configure_mesh()
+            .disable_mesh()
+            .y_labels(0)
+            .label_style((font, 12))
+            .draw()?;
+
+        // the values are only readable if the cells are wide enough
+        let show_values = n <= 64;
+        let value_style = (font, 11)
+            .into_font()
+            .color(&BLACK)
+            .pos(Pos::new(HPos::Center, VPos::Center));
+        let rows = [
+            (&state.output, &state.output_threads),
+            (
+                &state.right.iter().copied().map(Some).collect(),
+                &state.right_threads,
+            ),
+            (
+                &state.left.iter().copied().map(Some).collect(),
+                &state.left_threads,
+            ),
+        ];
+        for (row, (values, threads)) in rows.iter().enumerate() {
+            let y = row as f64;
+            chart.draw_series(threads.iter().enumerate().map(|(i, thread)| {
+                let fill = match thread {
+                    Some(thread) => color(thread + 1).mix(0.4).filled(),
+                    None => GREY.mix(0.2).filled(),
+                };
+                Rectangle::new([(i as f64, y + 0.15), (i as f64 + 1.0, y + 0.85)], fill)
+            }))?;
+            if show_values {
+                chart.draw_series(values.iter().enumerate().filter_map(|(i, value)| {
+                    value.map(|value| {
+                        Text::new(
+                            value.to_string(),
+                            (i as f64 + 0.5, y + 0.5),
+                            value_style.clone(),
+                        )
+                    })
+                }))?;
+            }
+            chart.draw_series(std::iter::once(Text::new(
+                ROWS[row],
+                (0.0, y + 0.95),
+                (font, 12).into_font().color(&BLACK),
+            )))?;
+        }
+
+        // each rank splits left between two threads
+        for rank in state.ranks.iter() {
+            chart.draw_series(LineSeries::new(
+                [(*rank as f64, 2.05), (*rank as f64, 2.95)],
+                RED.stroke_width(3),
+            ))?;
+        }
+        if let Some(thread) = state.highlight {
+            if let Some(output) = state.chunks.get(thread) {
+                chart.draw_series(std::iter::once(Rectangle::new(
+                    [(output.start as f64, 0.1), (output.end as f64, 0.9)],
+                    RED.stroke_width(2),
+                )))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/**
+    Renders one frame per event of a parallel merge of left and right and a last frame with the
+    merged output, as an animated GIF or a directory of frames like the hull animations.
+*/
+pub fn animate(
+    path: &str,
+    size: (u32, u32),
+    title: &str,
+    left: &[i64],
+    right: &[i64],
+    events: &[MergeEvent],
+    delay_ms: u32,
+) -> Result<(), Box<dyn Error>> {
+    let frames = frames(left, right, events);
+    let steps = frames.len();
+    let frames: Vec<Frame> = frames
+        .iter()
+        .enumerate()
+        .map(|(i, state)| Frame {
+            title,
+            state,
+            step: i + 1,
+            steps,
+        })
+        .collect();
+    render_animation(path, size, &frames, delay_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::animation::{animate, frames};
+    use crate::input_generation::random_sorted_halves_with_seed;
+    use crate::merge::{parallel_merge_observed, MergeEvent, MergeKernel};
+    use std::env::temp_dir;
+    use std::fs;
+
+    fn observed_merge(n: i64, threads: usize) -> (Vec<i64>, Vec<i64>, Vec<MergeEvent>) {
+        let (left, right) = random_sorted_halves_with_seed(n, 1);
+        let mut output = vec![0; n as usize];
+        let mut events = vec![];
+        parallel_merge_observed(
+            &left,
+            &right,
+            &mut output,
+            threads,
+            MergeKernel::Standard,
+            &mut |e| events.push(e),
+        );
+        (left, right, events)
+    }
+
+    #[test]
+    fn replaying_the_events_merges_the_input() {
+        let (left, right, events) = observed_merge(40, 4);
+        // a rank, a chunk and a leaf merge per thread
+        assert_eq!(events.len(), 12);
+        assert!(matches!(events[0], MergeEvent::Rank { rank: 0, .. }));
+        // the leaf merges come last, in the order the threads finished them
+        let mut finished: Vec<usize> = events[8..]
+            .iter()
+            .map(|event| match event {
+                MergeEvent::LeafMerge { thread } => *thread,
+                event => panic!("{:?} after the chunks", event),
+            })
+            .collect();
+        finished.sort();
+        assert_eq!(finished, vec![0, 1, 2, 3]);
+
+        let frames = frames(&left, &right, &events);
+        assert_eq!(frames.len(), events.len() + 1);
+        let last = &frames[frames.len() - 1];
+        let output: Vec<i64> = last.output.iter().map(|x| x.unwrap()).collect();
+        assert_eq!(output, (0..40).collect::<Vec<i64>>());
+        assert!(last.left_threads.iter().all(Option::is_some));
+        assert_eq!(last.chunks.len(), 4);
+        // nothing is written before the leaf merges
+        assert!(frames[7].output.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn writes_frames_and_gif() {
+        let (left, right, events) = observed_merge(16, 3);
+
+        let directory = temp_dir().join("project_1_animation_frames");
+        let _ = fs::remove_dir_all(&directory);
+        let directory = directory.to_str().unwrap();
+        animate(directory, (400, 200), "Test", &left, &right, &events, 100).unwrap();
+        assert_eq!(fs::read_dir(directory).unwrap().count(), events.len() + 1);
+
+        let gif = temp_dir().join("project_1_animation.gif");
+        let gif = gif.to_str().unwrap();
+        animate(gif, (400, 200), "Test", &left, &right, &events, 100).unwrap();
+        assert!(fs::metadata(gif).unwrap().len() > 0);
+    }
+}
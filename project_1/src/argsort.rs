@@ -0,0 +1,171 @@
+use crate::comparator_sort::fully_parallel_merge_sort_by;
+
+/**
+    The permutation that sorts keys: keys[argsort[0]] <= keys[argsort[1]] <= ... The positions are
+    sorted by their keys with the stable comparator sort, so equal keys appear in the order of
+    their positions. threads may be `AUTO_THREADS`.
+*/
+pub fn argsort<T: Ord + Sync>(keys: &[T], threads: usize) -> Vec<usize> {
+    let mut permutation: Vec<usize> = (0..keys.len()).collect();
+    let sorted =
+        fully_parallel_merge_sort_by(&mut permutation, threads, |a, b| keys[*a].cmp(&keys[*b]));
+    // only a panic of Ord::cmp makes the sort fail, it is passed on
+    if let Err(error) = sorted {
+        panic!("{}", error);
+    }
+    permutation
+}
+
+/**
+    Sorts keys stably and moves every value along with its key, i.e. to the position its key ends
+    up at.
+*/
+pub fn sort_pairs<K: Ord + Sync, V>(keys: &mut [K], values: &mut [V], threads: usize) {
+    assert_eq!(
+        keys.len(),
+        values.len(),
+        "every key needs exactly one value"
+    );
+    let permutation = argsort(keys, threads);
+    apply_permutation(keys, &permutation);
+    apply_permutation(values, &permutation);
+}
+
+/**
+    Rearranges data in place so that data[i] becomes the element that was at permutation[i], e.g.
+    applying the result of `argsort` sorts the keys. Every cycle of the permutation is followed
+    once with swaps. Panics if permutation is not a permutation of the positions of data.
+*/
+pub fn apply_permutation<T>(data: &mut [T], permutation: &[usize]) {
+    assert_eq!(
+        data.len(),
+        permutation.len(),
+        "the permutation has to have a position for every element"
+    );
+    let mut done = vec![false; data.len()];
+    for &position in permutation {
+        assert!(
+            position < data.len() && !done[position],
+            "{} is out of bounds or appears twice",
+            position
+        );
+        done[position] = true;
+    }
+
+    done.fill(false);
+    for start in 0..data.len() {
+        // data[start] is carried along the cycle until it reaches the position it belongs to
+        let mut current = start;
+        while !done[current] {
+            done[current] = true;
+            let next = permutation[current];
+            if next == start {
+                break;
+            }
+            data.swap(current, next);
+            current = next;
+        }
+    }
+}
+
+// the permutation that undoes the given one, e.g. the rank of every key for the result of argsort
+pub fn invert_permutation(permutation: &[usize]) -> Vec<usize> {
+    let mut inverse = vec![0; permutation.len()];
+    for (i, &position) in permutation.iter().enumerate() {
+        inverse[position] = i;
+    }
+    inverse
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::argsort::{apply_permutation, argsort, invert_permutation, sort_pairs};
+    use crate::input_generation::{gen_input_with_seed, shuffled_with_seed};
+    use crate::NUM_PROCESSORS_TEST;
+    use benchmark::tuning::AUTO_THREADS;
+
+    // inputs with many, few and no duplicate keys
+    fn keys() -> Vec<Vec<i64>> {
+        vec![
+            vec![],
+            vec![4],
+            vec![2, 2, 1, 2, 1],
+            gen_input_with_seed(5000, 1).iter().map(|x| x % 3).collect(),
+            gen_input_with_seed(5000, 2)
+                .iter()
+                .map(|x| x % 100)
+                .collect(),
+            vec![9; 3000],
+            shuffled_with_seed(4000, 3),
+        ]
+    }
+
+    #[test]
+    fn argsort_is_a_stable_sort() {
+        for keys in keys() {
+            // the standard library sort is stable as well
+            let mut expected: Vec<usize> = (0..keys.len()).collect();
+            expected.sort_by_key(|&i| keys[i]);
+            for threads in [1, 2, 3, NUM_PROCESSORS_TEST, AUTO_THREADS] {
+                assert_eq!(
+                    argsort(&keys, threads),
+                    expected,
+                    "{} keys with {} threads",
+                    keys.len(),
+                    threads
+                );
+            }
+        }
+
+        let words = ["pear", "apple", "fig", "apple", "banana"];
+        assert_eq!(argsort(&words, 2), vec![1, 3, 4, 2, 0]);
+    }
+
+    #[test]
+    fn values_move_with_their_keys() {
+        for keys in keys() {
+            let mut expected: Vec<(i64, String)> = keys
+                .iter()
+                .enumerate()
+                .map(|(i, key)| (*key, format!("value {}", i)))
+                .collect();
+            expected.sort_by_key(|(key, _)| *key);
+
+            for threads in [1, 3, NUM_PROCESSORS_TEST] {
+                let mut sorted_keys = keys.clone();
+                let mut values: Vec<String> =
+                    (0..keys.len()).map(|i| format!("value {}", i)).collect();
+                sort_pairs(&mut sorted_keys, &mut values, threads);
+                let pairs: Vec<(i64, String)> = sorted_keys.into_iter().zip(values).collect();
+                assert_eq!(
+                    pairs,
+                    expected,
+                    "{} keys with {} threads",
+                    keys.len(),
+                    threads
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn permutations() {
+        let permutation = argsort(&gen_input_with_seed(1000, 4), 4);
+        let inverse = invert_permutation(&permutation);
+        let mut data: Vec<usize> = (0..1000).collect();
+        apply_permutation(&mut data, &permutation);
+        assert_eq!(data, permutation);
+        apply_permutation(&mut data, &inverse);
+        assert_eq!(data, (0..1000).collect::<Vec<usize>>());
+
+        let mut letters = vec!['a', 'b', 'c', 'd'];
+        apply_permutation(&mut letters, &[2, 0, 3, 1]);
+        assert_eq!(letters, vec!['c', 'a', 'd', 'b']);
+    }
+
+    #[test]
+    #[should_panic(expected = "appears twice")]
+    fn not_a_permutation() {
+        apply_permutation(&mut [1, 2, 3], &[0, 2, 2]);
+    }
+}
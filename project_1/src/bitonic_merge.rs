@@ -0,0 +1,562 @@
+use benchmark::counters::{count, Counter};
+use std::cmp::{max, min};
+
+// block width of the portable bitonic merge network used by MergeKernel::Bitonic
+pub const BITONIC_BLOCK: usize = 8;
+// block width of the AVX2 networks, 4 registers of i64 or 2 of i32 (faster than narrower blocks)
+pub const SIMD_BLOCK: usize = 16;
+
+// sorts a bitonic block in place with a half cleaner cascade (W has to be a power of two)
+fn bitonic_clean<T: Copy + Ord, const W: usize>(block: &mut [T; W]) {
+    let mut gap = W / 2;
+    while gap > 0 {
+        for i in 0..W {
+            if i & gap == 0 {
+                let (low, high) = (min(block[i], block[i + gap]), max(block[i], block[i + gap]));
+                block[i] = low;
+                block[i + gap] = high;
+            }
+        }
+        gap /= 2;
+    }
+}
+
+/**
+    Portable bitonic merge network: given two sorted blocks, afterwards `low` holds the W smallest
+    and `high` the W largest elements of both, each sorted. All compare-exchanges are min/max
+    operations, so there are no data dependent branches.
+*/
+pub fn bitonic_merge_network<T: Copy + Ord, const W: usize>(low: &mut [T; W], high: &mut [T; W]) {
+    // low followed by the reversed high block is a bitonic sequence
+    high.reverse();
+    for i in 0..W {
+        let (a, b) = (min(low[i], high[i]), max(low[i], high[i]));
+        low[i] = a;
+        high[i] = b;
+    }
+    bitonic_clean(low);
+    bitonic_clean(high);
+}
+
+// scalar merge of the last few elements, ties are taken from right like sequential_merge
+fn scalar_merge<T: Copy + Ord>(left: &[T], right: &[T], output: &mut [T]) {
+    let (mut i, mut j) = (0, 0);
+    let mut comparisons = 0;
+    for slot in output.iter_mut() {
+        let take_left = if i == left.len() || j == right.len() {
+            j == right.len()
+        } else {
+            comparisons += 1;
+            left[i] < right[j]
+        };
+        if take_left {
+            *slot = left[i];
+            i += 1;
+        } else {
+            *slot = right[j];
+            j += 1;
+        }
+    }
+    count(Counter::Comparisons, comparisons);
+}
+
+/**
+    Merges two sorted slices block-wise with a W-wide bitonic merge network. The W largest elements
+    seen so far are carried in a register block, and the next block is loaded from the side with
+    the smaller head. Once that side has fewer than W elements left, the carried block and the
+    remaining elements are merged with a scalar merge.
+*/
+pub fn bitonic_merge_with<T: Copy + Ord, const W: usize>(
+    left: &[T],
+    right: &[T],
+    output: &mut [T],
+    network: impl Fn(&mut [T; W], &mut [T; W]),
+) {
+    count(Counter::Writes, output.len() as u64);
+    if left.len() < W || right.len() < W {
+        scalar_merge(left, right, output);
+        return;
+    }
+
+    // the network compares the blocks element-wise and then cleans both halves of 2W elements
+    let network_comparisons = (W + W * W.trailing_zeros() as usize) as u64;
+    let mut comparisons = 0;
+    let mut low: [T; W] = left[..W].try_into().unwrap();
+    let mut high: [T; W] = right[..W].try_into().unwrap();
+    let (mut i, mut j, mut k) = (W, W, 0);
+
+    loop {
+        network(&mut low, &mut high);
+        comparisons += network_comparisons;
+        output[k..k + W].copy_from_slice(&low);
+        k += W;
+
+        // load the next block from the side with the smaller head
+        comparisons += (i < left.len() && j < right.len()) as u64;
+        let take_left = j == right.len() || (i < left.len() && left[i] < right[j]);
+        let (side, position) = if take_left {
+            (left, &mut i)
+        } else {
+            (right, &mut j)
+        };
+
+        if *position + W > side.len() {
+            // not a full block left on this side: merge the carried block with its rest first
+            let side_rest = &side[*position..];
+            let mut carried: Vec<T> = vec![high[0]; W + side_rest.len()];
+            scalar_merge(&high, side_rest, &mut carried);
+            let other_rest = if take_left { &right[j..] } else { &left[i..] };
+            scalar_merge(&carried, other_rest, &mut output[k..]);
+            count(Counter::Comparisons, comparisons);
+            return;
+        }
+
+        low = side[*position..*position + W].try_into().unwrap();
+        *position += W;
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+pub mod avx2 {
+    use std::arch::x86_64::*;
+
+    // the most registers a block of the network is held in, 16 elements of 4 lanes
+    const MAX_REGISTERS: usize = 4;
+
+    /**
+        A SIMD register of LANES elements with the operations the bitonic network needs. All of
+        them are compiled for AVX2 and must only be called if the CPU supports it.
+    */
+    pub trait Lanes: Copy {
+        type Element: Copy;
+        const LANES: usize;
+
+        /**
+            Loads the first LANES elements of source.
+            # Safety
+            The CPU has to support AVX2 and source has to hold at least LANES elements.
+        */
+        unsafe fn load(source: &[Self::Element]) -> Self;
+
+        /**
+            # Safety
+            The CPU has to support AVX2 and destination has to hold at least LANES elements.
+        */
+        unsafe fn store(self, destination: &mut [Self::Element]);
+
+        /**
+            # Safety
+            The CPU has to support AVX2.
+        */
+        unsafe fn min_max(a: Self, b: Self) -> (Self, Self);
+
+        /**
+            # Safety
+            The CPU has to support AVX2.
+        */
+        unsafe fn reverse(self) -> Self;
+
+        /**
+            Half cleaner cascade on a register holding a bitonic sequence.
+            # Safety
+            The CPU has to support AVX2.
+        */
+        unsafe fn clean(self) -> Self;
+    }
+
+    #[derive(Clone, Copy)]
+    pub struct I64x4(__m256i);
+
+    #[derive(Clone, Copy)]
+    pub struct I32x8(__m256i);
+
+    #[derive(Clone, Copy)]
+    pub struct I32x4(__m128i);
+
+    impl Lanes for I64x4 {
+        type Element = i64;
+        const LANES: usize = 4;
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn load(source: &[i64]) -> I64x4 {
+            debug_assert!(source.len() >= 4);
+            I64x4(_mm256_loadu_si256(source.as_ptr() as *const __m256i))
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn store(self, destination: &mut [i64]) {
+            debug_assert!(destination.len() >= 4);
+            _mm256_storeu_si256(destination.as_mut_ptr() as *mut __m256i, self.0)
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn min_max(a: I64x4, b: I64x4) -> (I64x4, I64x4) {
+            let greater = _mm256_cmpgt_epi64(a.0, b.0);
+            (
+                I64x4(_mm256_blendv_epi8(a.0, b.0, greater)),
+                I64x4(_mm256_blendv_epi8(b.0, a.0, greater)),
+            )
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn reverse(self) -> I64x4 {
+            I64x4(_mm256_permute4x64_epi64::<0b00_01_10_11>(self.0))
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn clean(self) -> I64x4 {
+            // compare lanes (0, 2) and (1, 3)
+            let swapped = I64x4(_mm256_permute4x64_epi64::<0b01_00_11_10>(self.0));
+            let (low, high) = I64x4::min_max(self, swapped);
+            let v = _mm256_blend_epi32::<0b1111_0000>(low.0, high.0);
+            // compare lanes (0, 1) and (2, 3)
+            let swapped = I64x4(_mm256_permute4x64_epi64::<0b10_11_00_01>(v));
+            let (low, high) = I64x4::min_max(I64x4(v), swapped);
+            I64x4(_mm256_blend_epi32::<0b1100_1100>(low.0, high.0))
+        }
+    }
+
+    impl Lanes for I32x8 {
+        type Element = i32;
+        const LANES: usize = 8;
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn load(source: &[i32]) -> I32x8 {
+            debug_assert!(source.len() >= 8);
+            I32x8(_mm256_loadu_si256(source.as_ptr() as *const __m256i))
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn store(self, destination: &mut [i32]) {
+            debug_assert!(destination.len() >= 8);
+            _mm256_storeu_si256(destination.as_mut_ptr() as *mut __m256i, self.0)
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn min_max(a: I32x8, b: I32x8) -> (I32x8, I32x8) {
+            (
+                I32x8(_mm256_min_epi32(a.0, b.0)),
+                I32x8(_mm256_max_epi32(a.0, b.0)),
+            )
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn reverse(self) -> I32x8 {
+            let reversed = _mm256_setr_epi32(7, 6, 5, 4, 3, 2, 1, 0);
+            I32x8(_mm256_permutevar8x32_epi32(self.0, reversed))
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn clean(self) -> I32x8 {
+            // compare lanes i and i + 4 by swapping the 128 bit halves
+            let (low, high) = (
+                _mm256_min_epi32(self.0, _mm256_permute2x128_si256::<0x01>(self.0, self.0)),
+                _mm256_max_epi32(self.0, _mm256_permute2x128_si256::<0x01>(self.0, self.0)),
+            );
+            let v = _mm256_blend_epi32::<0b1111_0000>(low, high);
+            // compare lanes i and i + 2 within each half
+            let swapped = _mm256_shuffle_epi32::<0b01_00_11_10>(v);
+            let (low, high) = (_mm256_min_epi32(v, swapped), _mm256_max_epi32(v, swapped));
+            let v = _mm256_blend_epi32::<0b1100_1100>(low, high);
+            // compare neighbouring lanes
+            let swapped = _mm256_shuffle_epi32::<0b10_11_00_01>(v);
+            let (low, high) = (_mm256_min_epi32(v, swapped), _mm256_max_epi32(v, swapped));
+            I32x8(_mm256_blend_epi32::<0b1010_1010>(low, high))
+        }
+    }
+
+    impl Lanes for I32x4 {
+        type Element = i32;
+        const LANES: usize = 4;
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn load(source: &[i32]) -> I32x4 {
+            debug_assert!(source.len() >= 4);
+            I32x4(_mm_loadu_si128(source.as_ptr() as *const __m128i))
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn store(self, destination: &mut [i32]) {
+            debug_assert!(destination.len() >= 4);
+            _mm_storeu_si128(destination.as_mut_ptr() as *mut __m128i, self.0)
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn min_max(a: I32x4, b: I32x4) -> (I32x4, I32x4) {
+            (
+                I32x4(_mm_min_epi32(a.0, b.0)),
+                I32x4(_mm_max_epi32(a.0, b.0)),
+            )
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn reverse(self) -> I32x4 {
+            I32x4(_mm_shuffle_epi32::<0b00_01_10_11>(self.0))
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn clean(self) -> I32x4 {
+            // compare lanes (0, 2) and (1, 3)
+            let swapped = _mm_shuffle_epi32::<0b01_00_11_10>(self.0);
+            let (low, high) = (
+                _mm_min_epi32(self.0, swapped),
+                _mm_max_epi32(self.0, swapped),
+            );
+            let v = _mm_blend_epi32::<0b1100>(low, high);
+            // compare lanes (0, 1) and (2, 3)
+            let swapped = _mm_shuffle_epi32::<0b10_11_00_01>(v);
+            let (low, high) = (_mm_min_epi32(v, swapped), _mm_max_epi32(v, swapped));
+            I32x4(_mm_blend_epi32::<0b1010>(low, high))
+        }
+    }
+
+    /**
+        Half cleaner cascade on a bitonic sequence held in registers: the gaps of at least a whole
+        register compare registers with each other, the smaller ones are left to `Lanes::clean`.
+    */
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn clean_block<V: Lanes>(registers: &mut [V]) {
+        let mut gap = registers.len() / 2;
+        while gap > 0 {
+            for r in 0..registers.len() {
+                if r & gap == 0 {
+                    (registers[r], registers[r + gap]) =
+                        V::min_max(registers[r], registers[r + gap]);
+                }
+            }
+            gap /= 2;
+        }
+        for register in registers.iter_mut() {
+            *register = register.clean();
+        }
+    }
+
+    /**
+        AVX2 version of `bitonic_merge_network` for blocks of W elements, which are held in
+        W / LANES registers of V each (at most 4, W / LANES has to be a power of two).
+        # Safety
+        The CPU has to support AVX2.
+    */
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn bitonic_merge_network<V: Lanes, const W: usize>(
+        low: &mut [V::Element; W],
+        high: &mut [V::Element; W],
+    ) {
+        let registers = W / V::LANES;
+        assert!(
+            registers * V::LANES == W && registers.is_power_of_two() && registers <= MAX_REGISTERS,
+            "blocks of {} elements do not fit into registers of {} lanes",
+            W,
+            V::LANES
+        );
+
+        let mut a = [V::load(&low[..]); MAX_REGISTERS];
+        let mut b = a;
+        for r in 0..registers {
+            a[r] = V::load(&low[r * V::LANES..]);
+            // low followed by the reversed high block is a bitonic sequence
+            b[r] = V::load(&high[(registers - 1 - r) * V::LANES..]).reverse();
+        }
+        for r in 0..registers {
+            (a[r], b[r]) = V::min_max(a[r], b[r]);
+        }
+        clean_block(&mut a[..registers]);
+        clean_block(&mut b[..registers]);
+        for r in 0..registers {
+            a[r].store(&mut low[r * V::LANES..]);
+            b[r].store(&mut high[r * V::LANES..]);
+        }
+    }
+}
+
+/**
+    Bitonic block merge for i64. Uses the AVX2 network on SIMD_BLOCK-element blocks if the CPU
+    supports it and the portable network on BITONIC_BLOCK-element blocks otherwise.
+*/
+pub fn bitonic_merge(left: &[i64], right: &[i64], output: &mut [i64]) {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        bitonic_merge_with::<i64, SIMD_BLOCK>(left, right, output, |low, high| unsafe {
+            // checked above that avx2 is available
+            avx2::bitonic_merge_network::<avx2::I64x4, SIMD_BLOCK>(low, high)
+        });
+        return;
+    }
+
+    bitonic_merge_with::<i64, BITONIC_BLOCK>(left, right, output, bitonic_merge_network);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bitonic_merge::{
+        bitonic_merge, bitonic_merge_network, bitonic_merge_with, BITONIC_BLOCK,
+    };
+    use crate::input_generation::{
+        alternating, left_fits_between_last_two_elements_in_right, random_sorted_halves_with_seed,
+        right_fits_between_two_elements_in_left_halves, sorted, LeftRightSplit,
+    };
+    use crate::merge::sequential_merge;
+    use benchmark::counters::{counted_locally, Operations};
+
+    #[test]
+    fn counted_network_and_scalar_comparisons() {
+        let network = bitonic_merge_network::<i64, 4>;
+        let mut output = vec![0i64; 8];
+        let (_, operations) = counted_locally(|| {
+            bitonic_merge_with(&[0, 2, 4, 6], &[1, 3, 5, 7], &mut output, network)
+        });
+        // a single network of 4 element-wise and 2 * 4 cleaning compare-exchanges, no tail
+        assert_eq!(
+            operations,
+            Operations {
+                comparisons: 12,
+                writes: 8,
+                ..Operations::default()
+            }
+        );
+        assert_eq!(output, (0..8).collect::<Vec<i64>>());
+
+        let mut output = vec![0i64; 3];
+        let (_, operations) =
+            counted_locally(|| bitonic_merge_with(&[1], &[0, 2], &mut output, network));
+        // too short for a block, the scalar merge stops comparing once left runs out
+        assert_eq!(
+            operations,
+            Operations {
+                comparisons: 2,
+                writes: 3,
+                ..Operations::default()
+            }
+        );
+    }
+
+    #[test]
+    fn network_merges_two_blocks() {
+        let mut low: [i64; 4] = [1, 4, 6, 7];
+        let mut high: [i64; 4] = [2, 3, 5, 8];
+        bitonic_merge_network(&mut low, &mut high);
+        assert_eq!(low, [1, 2, 3, 4]);
+        assert_eq!(high, [5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn bitonic_merge_matches_sequential_merge() {
+        let generators: [fn(i64) -> LeftRightSplit; 5] = [
+            alternating,
+            sorted,
+            |n| random_sorted_halves_with_seed(n, 1),
+            left_fits_between_last_two_elements_in_right,
+            right_fits_between_two_elements_in_left_halves,
+        ];
+        for generator in generators {
+            for n in [6, 34, 1000, 1002] {
+                let (left, right) = generator(n);
+                let mut expected: Vec<i64> = vec![0i64; left.len() + right.len()];
+                let mut output: Vec<i64> = vec![0i64; left.len() + right.len()];
+                sequential_merge(&left, &right, &mut expected);
+                bitonic_merge(&left, &right, &mut output);
+                assert_eq!(output, expected);
+                bitonic_merge_with::<i64, BITONIC_BLOCK>(
+                    &left,
+                    &right,
+                    &mut output,
+                    bitonic_merge_network,
+                );
+                assert_eq!(output, expected);
+            }
+        }
+    }
+
+    // sorted i32 halves with many duplicates whose lengths are not multiples of the block sizes
+    fn halves_i32() -> (Vec<i32>, Vec<i32>) {
+        let mut left: Vec<i32> = (0..300).map(|x| (x * 7) % 50 - 20).collect();
+        let mut right: Vec<i32> = (0..257).map(|x| (x * 3) % 40 - 20).collect();
+        left.sort();
+        right.sort();
+        (left, right)
+    }
+
+    // like bitonic_merge for i32, the AVX2 network holds its blocks in registers of 8 lanes
+    fn bitonic_merge_i32(left: &[i32], right: &[i32], output: &mut [i32]) {
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") {
+            use crate::bitonic_merge::{avx2, SIMD_BLOCK};
+            bitonic_merge_with::<i32, SIMD_BLOCK>(left, right, output, |low, high| unsafe {
+                // checked above that avx2 is available
+                avx2::bitonic_merge_network::<avx2::I32x8, SIMD_BLOCK>(low, high)
+            });
+            return;
+        }
+
+        bitonic_merge_with::<i32, BITONIC_BLOCK>(left, right, output, bitonic_merge_network);
+    }
+
+    #[test]
+    fn portable_network_block_sizes_i32() {
+        let (left, right) = halves_i32();
+        let mut expected: Vec<i32> = [left.clone(), right.clone()].concat();
+        expected.sort();
+
+        let mut output: Vec<i32> = vec![0; expected.len()];
+        bitonic_merge_with::<i32, 4>(&left, &right, &mut output, bitonic_merge_network);
+        assert_eq!(output, expected);
+        bitonic_merge_with::<i32, 8>(&left, &right, &mut output, bitonic_merge_network);
+        assert_eq!(output, expected);
+        bitonic_merge_with::<i32, 16>(&left, &right, &mut output, bitonic_merge_network);
+        assert_eq!(output, expected);
+        bitonic_merge_i32(&left, &right, &mut output);
+        assert_eq!(output, expected);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn avx2_networks_all_lanes_and_block_sizes() {
+        use crate::bitonic_merge::avx2::{bitonic_merge_network, I32x4, I32x8, I64x4, Lanes};
+
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+
+        // merges with the AVX2 network of V on W-element blocks and compares to the sorted input
+        fn check<V: Lanes, const W: usize>(left: &[V::Element], right: &[V::Element])
+        where
+            V::Element: Ord + std::fmt::Debug + Default,
+        {
+            let mut expected: Vec<V::Element> = [left, right].concat();
+            expected.sort();
+            let mut output = vec![V::Element::default(); expected.len()];
+            bitonic_merge_with::<V::Element, W>(left, right, &mut output, |low, high| unsafe {
+                // checked by the caller that avx2 is available
+                bitonic_merge_network::<V, W>(low, high)
+            });
+            assert_eq!(output, expected, "{} lanes, blocks of {}", V::LANES, W);
+        }
+
+        let (left, right) = random_sorted_halves_with_seed(1002, 2);
+        check::<I64x4, 4>(&left, &right);
+        check::<I64x4, 8>(&left, &right);
+        check::<I64x4, 16>(&left, &right);
+        let (left, right) = halves_i32();
+        check::<I32x4, 4>(&left, &right);
+        check::<I32x4, 8>(&left, &right);
+        check::<I32x4, 16>(&left, &right);
+        check::<I32x8, 8>(&left, &right);
+        check::<I32x8, 16>(&left, &right);
+    }
+}
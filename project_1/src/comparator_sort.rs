@@ -0,0 +1,360 @@
+use crate::merge::{chunks, merge_threads};
+use crate::utils::{batch_ranks_by, Bound};
+use benchmark::trace::span_with_size;
+use benchmark::tuning::profile;
+use std::any::Any;
+use std::cmp::Ordering;
+use std::iter::zip;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+// runs f and turns a panic of the comparator into an error with the message it panicked with
+fn catch<R>(f: impl FnOnce() -> R) -> Result<R, String> {
+    catch_unwind(AssertUnwindSafe(f)).map_err(|payload: Box<dyn Any + Send>| {
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "unknown panic".to_string()
+        };
+        format!("the comparator panicked: {}", message)
+    })
+}
+
+// merges left and right like `sequential_merge` with the comparator, ties are taken from left
+fn merge_by<T: Copy, F: Fn(&T, &T) -> Ordering>(
+    left: &[T],
+    right: &[T],
+    output: &mut [T],
+    compare: &F,
+) {
+    let (mut i, mut j, mut k) = (0, 0, 0);
+
+    while i < left.len() && j < right.len() {
+        if compare(&right[j], &left[i]) == Ordering::Less {
+            output[k] = right[j];
+            j += 1;
+        } else {
+            output[k] = left[i];
+            i += 1;
+        }
+        k += 1;
+    }
+
+    output[k..k + left.len() - i].copy_from_slice(&left[i..]);
+    k += left.len() - i;
+    output[k..].copy_from_slice(&right[j..]);
+}
+
+/**
+    Merges the sorted slices left and right into output like `parallel_merge`, ordered by the
+    comparator. If the comparator panics in any of the threads, output holds left followed by
+    right afterwards, so it is still a permutation of the inputs, and the panic is returned as an
+    error. threads may be `AUTO_THREADS`.
+*/
+pub fn parallel_merge_by<T: Copy + Send + Sync, F: Fn(&T, &T) -> Ordering + Sync>(
+    left: &[T],
+    right: &[T],
+    output: &mut [T],
+    threads: usize,
+    compare: &F,
+) -> Result<(), String> {
+    let threads = merge_threads(output.len(), threads);
+    let result = try_parallel_merge_by(left, right, output, threads, compare);
+    if result.is_err() {
+        let (left_output, right_output) = output.split_at_mut(left.len());
+        left_output.copy_from_slice(left);
+        right_output.copy_from_slice(right);
+    }
+    result
+}
+
+// the merge of parallel_merge_by, which leaves output half-merged if the comparator panics
+fn try_parallel_merge_by<T: Copy + Send + Sync, F: Fn(&T, &T) -> Ordering + Sync>(
+    left: &[T],
+    right: &[T],
+    output: &mut [T],
+    num_processors: usize,
+    compare: &F,
+) -> Result<(), String> {
+    let _span = span_with_size("parallel merge", output.len());
+    let n = right.len();
+    if n == 0 {
+        output.copy_from_slice(left);
+        return Ok(());
+    }
+    let (threads, chunk_size) = chunks(n, num_processors);
+
+    // upper bounds, so that the elements of left equal to the first one of a chunk stay in front
+    let queries: Vec<T> = (1..threads).map(|i| right[i * chunk_size]).collect();
+    let ranks = catch(|| batch_ranks_by(left, &queries, Bound::Upper, threads, compare))?;
+    let rank_vector: Vec<usize> = [0].into_iter().chain(ranks).chain([left.len()]).collect();
+
+    std::thread::scope(|scope| {
+        let mut rest = output;
+        let handles: Vec<_> = zip(0..threads, right.chunks(chunk_size))
+            .map(|(i, right_chunk)| {
+                let left_slice = &left[rank_vector[i]..rank_vector[i + 1]];
+                let current_chunk;
+                (current_chunk, rest) =
+                    std::mem::take(&mut rest).split_at_mut(left_slice.len() + right_chunk.len());
+                scope.spawn(move || {
+                    let _span = span_with_size("leaf merge", current_chunk.len());
+                    catch(|| merge_by(left_slice, right_chunk, current_chunk, compare))
+                })
+            })
+            .collect();
+        // every thread is joined, the first panic is reported
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .fold(Ok(()), Result::and)
+    })
+}
+
+/**
+    Sorts input with scratch like the merge sorts, ordered by the comparator. The halves are
+    sorted on their own threads and merged in parallel if fully_parallel, else sequentially.
+    When a merge panics, its output is restored from scratch, which still holds the two sorted
+    halves, so both input and scratch are always permutations of the original elements.
+*/
+fn sort_by<T: Copy + Send + Sync, F: Fn(&T, &T) -> Ordering + Sync>(
+    input: &mut [T],
+    scratch: &mut [T],
+    num_processors: usize,
+    cutoff: usize,
+    fully_parallel: bool,
+    compare: &F,
+) -> Result<(), String> {
+    let len = input.len();
+    if len < 2 {
+        return Ok(());
+    }
+    let mid = len / 2;
+
+    let (left_input, right_input) = input.split_at_mut(mid);
+    let (left_scratch, right_scratch) = scratch.split_at_mut(mid);
+
+    let sequential = num_processors < 2 || len <= cutoff;
+    let (left, right) = if sequential {
+        (
+            sort_by(left_input, left_scratch, 1, cutoff, false, compare),
+            sort_by(right_input, right_scratch, 1, cutoff, false, compare),
+        )
+    } else {
+        let _span = span_with_size("sort", len);
+        let left_processors = num_processors / 2;
+        let right_processors = num_processors - left_processors;
+        std::thread::scope(|scope| {
+            let left = scope.spawn(|| {
+                sort_by(
+                    left_input,
+                    left_scratch,
+                    left_processors,
+                    cutoff,
+                    fully_parallel,
+                    compare,
+                )
+            });
+            let right = sort_by(
+                right_input,
+                right_scratch,
+                right_processors,
+                cutoff,
+                fully_parallel,
+                compare,
+            );
+            (left.join().unwrap(), right)
+        })
+    };
+    // a half that failed is a permutation of its elements already, it is not merged
+    left.and(right)?;
+
+    let merged = if sequential || !fully_parallel {
+        catch(|| merge_by(left_scratch, right_scratch, input, compare))
+    } else {
+        try_parallel_merge_by(left_scratch, right_scratch, input, num_processors, compare)
+    };
+    if merged.is_err() {
+        input.copy_from_slice(scratch);
+    } else {
+        scratch.copy_from_slice(input);
+    }
+    merged
+}
+
+/**
+    Sorts input stably with the comparator like `parallel_merge_sort`, the halves in parallel and
+    the merges sequentially. If the comparator panics, input still holds a permutation of its
+    elements and the panic is returned as an error. threads may be `AUTO_THREADS`.
+*/
+pub fn parallel_merge_sort_by<T: Copy + Send + Sync, F: Fn(&T, &T) -> Ordering + Sync>(
+    input: &mut [T],
+    threads: usize,
+    compare: F,
+) -> Result<(), String> {
+    let (threads, cutoff) = profile().resolve("PSSM", input.len(), threads, 1);
+    let mut scratch = input.to_vec();
+    sort_by(input, &mut scratch, threads, cutoff, false, &compare)
+}
+
+// like parallel_merge_sort_by, but with the parallel merge like `fully_parallel_merge_sort`
+pub fn fully_parallel_merge_sort_by<T: Copy + Send + Sync, F: Fn(&T, &T) -> Ordering + Sync>(
+    input: &mut [T],
+    threads: usize,
+    compare: F,
+) -> Result<(), String> {
+    let (threads, cutoff) = profile().resolve("PSPM", input.len(), threads, 1);
+    let mut scratch = input.to_vec();
+    sort_by(input, &mut scratch, threads, cutoff, true, &compare)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::comparator_sort::{
+        fully_parallel_merge_sort_by, parallel_merge_by, parallel_merge_sort_by,
+    };
+    use crate::input_generation::{gen_input_with_seed, random_sorted_halves_with_seed};
+    use crate::NUM_PROCESSORS_TEST;
+    use benchmark::tuning::AUTO_THREADS;
+    use std::cmp::Ordering;
+    use std::panic::{set_hook, take_hook};
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+    use std::sync::Once;
+    use turborand::prelude::*;
+
+    // the injected panics are expected, only the messages of all other panics are printed
+    fn hide_injected_panics() {
+        static HIDE: Once = Once::new();
+        HIDE.call_once(|| {
+            let previous = take_hook();
+            set_hook(Box::new(move |info| {
+                if info.payload().downcast_ref::<&str>() != Some(&"injected panic") {
+                    previous(info);
+                }
+            }));
+        });
+    }
+
+    type SortBy = fn(&mut [(i64, usize)], usize, &PanickingComparator) -> Result<(), String>;
+
+    // both sorts, with the comparator passed by reference
+    fn sorts() -> Vec<(&'static str, SortBy)> {
+        vec![
+            ("PSSM", |input, threads, compare| {
+                parallel_merge_sort_by(input, threads, |a, b| compare.compare(a, b))
+            }),
+            ("PSPM", |input, threads, compare| {
+                fully_parallel_merge_sort_by(input, threads, |a, b| compare.compare(a, b))
+            }),
+        ]
+    }
+
+    // compares the keys of (key, position) pairs and panics at the given comparison
+    struct PanickingComparator {
+        comparisons: AtomicUsize,
+        panic_at: usize,
+    }
+
+    impl PanickingComparator {
+        fn new(panic_at: usize) -> PanickingComparator {
+            PanickingComparator {
+                comparisons: AtomicUsize::new(0),
+                panic_at,
+            }
+        }
+
+        fn compare(&self, a: &(i64, usize), b: &(i64, usize)) -> Ordering {
+            if self.comparisons.fetch_add(1, AtomicOrdering::Relaxed) == self.panic_at {
+                panic!("injected panic");
+            }
+            a.0.cmp(&b.0)
+        }
+    }
+
+    // pairs of keys with many duplicates and their positions, to check the stability
+    fn pairs(size: usize, seed: u64) -> Vec<(i64, usize)> {
+        gen_input_with_seed(size, seed)
+            .iter()
+            .map(|x| x % 50)
+            .zip(0..)
+            .collect()
+    }
+
+    #[test]
+    fn sorts_stably_with_comparator() {
+        for size in [0, 1, 2, 17, 3000] {
+            let input = pairs(size, size as u64);
+            let mut expected = input.clone();
+            expected.sort_by_key(|(key, _)| *key);
+            for (name, sort) in sorts() {
+                for threads in [1, 2, 3, NUM_PROCESSORS_TEST, AUTO_THREADS] {
+                    let mut output = input.clone();
+                    let comparator = PanickingComparator::new(usize::MAX);
+                    assert_eq!(sort(&mut output, threads, &comparator), Ok(()));
+                    assert_eq!(output, expected, "{} on {} with {}", name, size, threads);
+                }
+            }
+        }
+
+        let mut words = vec!["pear", "fig", "banana", "apple"];
+        parallel_merge_sort_by(&mut words, 2, |a, b| a.len().cmp(&b.len())).unwrap();
+        assert_eq!(words, vec!["fig", "pear", "apple", "banana"]);
+    }
+
+    #[test]
+    fn panics_leave_a_permutation() {
+        hide_injected_panics();
+        let input = pairs(2000, 1);
+        let mut sorted = input.clone();
+        sorted.sort();
+        let rand = Rng::with_seed(2);
+        for (name, sort) in sorts() {
+            for threads in [1, 2, 3, NUM_PROCESSORS_TEST] {
+                for _ in 0..20 {
+                    // a sort of 2000 elements makes more than 10000 comparisons
+                    let panic_at = rand.usize(0..10000);
+                    let mut output = input.clone();
+                    let comparator = PanickingComparator::new(panic_at);
+                    let result = sort(&mut output, threads, &comparator);
+                    let message = format!("{} with {} threads at {}", name, threads, panic_at);
+                    assert_eq!(
+                        result,
+                        Err("the comparator panicked: injected panic".to_string()),
+                        "{}",
+                        message
+                    );
+                    output.sort();
+                    assert_eq!(output, sorted, "{}", message);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn merge_panics_leave_the_inputs() {
+        hide_injected_panics();
+        let (left, right) = random_sorted_halves_with_seed(1000, 3);
+        let left: Vec<(i64, usize)> = left.into_iter().zip(0..).collect();
+        let right: Vec<(i64, usize)> = right.into_iter().zip(1000..).collect();
+        let rand = Rng::with_seed(4);
+        for threads in [1, 2, NUM_PROCESSORS_TEST] {
+            let mut output = vec![(0, 0); 1000];
+            let comparator = PanickingComparator::new(usize::MAX);
+            let result = parallel_merge_by(&left, &right, &mut output, threads, &|a, b| {
+                comparator.compare(a, b)
+            });
+            assert_eq!(result, Ok(()));
+            assert!(output.windows(2).all(|w| w[0].0 <= w[1].0));
+
+            for _ in 0..10 {
+                let comparator = PanickingComparator::new(rand.usize(0..500));
+                let result = parallel_merge_by(&left, &right, &mut output, threads, &|a, b| {
+                    comparator.compare(a, b)
+                });
+                assert!(result.is_err(), "{} threads", threads);
+                assert_eq!(output, [left.clone(), right.clone()].concat());
+            }
+        }
+    }
+}
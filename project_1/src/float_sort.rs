@@ -0,0 +1,260 @@
+use crate::comparator_sort::fully_parallel_merge_sort_by;
+use crate::sorter::Sorter;
+
+// where the NaNs of an input end up, they are not ordered by `totalOrder` among the numbers
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NanPolicy {
+    First,
+    Last,
+    // the sort fails and leaves the input unchanged
+    Reject,
+}
+
+/**
+    Floats that can be sorted by the IEEE 754 `totalOrder` predicate, e.g. -inf < -1.0 < -0.0 <
+    +0.0 < 1.0 < inf. The key maps every value to an i64 with the same order, so that the
+    sorters on i64 like the radix sort can sort floats as well.
+*/
+pub trait TotalOrder: Copy + Send + Sync {
+    fn is_nan(self) -> bool;
+    fn total_order_key(self) -> i64;
+    fn from_total_order_key(key: i64) -> Self;
+}
+
+// the bits of a negative float grow with its magnitude, so all but the sign bit are flipped
+fn flip_negative(bits: i64) -> i64 {
+    if bits < 0 {
+        bits ^ i64::MAX
+    } else {
+        bits
+    }
+}
+
+impl TotalOrder for f64 {
+    fn is_nan(self) -> bool {
+        f64::is_nan(self)
+    }
+
+    fn total_order_key(self) -> i64 {
+        flip_negative(self.to_bits() as i64)
+    }
+
+    fn from_total_order_key(key: i64) -> f64 {
+        // flipping is its own inverse
+        f64::from_bits(flip_negative(key) as u64)
+    }
+}
+
+impl TotalOrder for f32 {
+    fn is_nan(self) -> bool {
+        f32::is_nan(self)
+    }
+
+    fn total_order_key(self) -> i64 {
+        // sign extending keeps the order of the 32 bit keys
+        flip_negative((self.to_bits() as i32) as i64)
+    }
+
+    fn from_total_order_key(key: i64) -> f32 {
+        f32::from_bits(flip_negative(key) as i32 as u32)
+    }
+}
+
+/**
+    The numbers and the NaNs of input, both in the order of input. Fails without touching input
+    if there is a NaN and the policy rejects them.
+*/
+fn split_nans<T: TotalOrder>(input: &[T], policy: NanPolicy) -> Result<(Vec<T>, Vec<T>), String> {
+    if policy == NanPolicy::Reject {
+        if let Some(position) = input.iter().position(|x| x.is_nan()) {
+            return Err(format!("NaN at position {} cannot be sorted", position));
+        }
+    }
+    Ok(input.iter().partition(|x| !x.is_nan()))
+}
+
+// writes the sorted numbers and the NaNs to input in the order given by the policy
+fn place<T: TotalOrder>(input: &mut [T], numbers: &[T], nans: &[T], policy: NanPolicy) {
+    let (first, second) = match policy {
+        NanPolicy::First => (nans, numbers),
+        _ => (numbers, nans),
+    };
+    let (first_output, second_output) = input.split_at_mut(first.len());
+    first_output.copy_from_slice(first);
+    second_output.copy_from_slice(second);
+}
+
+/**
+    Sorts the floats of input by `totalOrder` with the fully parallel merge sort and puts the NaNs
+    (in the order they had) before or after the numbers. threads may be `AUTO_THREADS`.
+*/
+pub fn sort_floats<T: TotalOrder>(
+    input: &mut [T],
+    threads: usize,
+    policy: NanPolicy,
+) -> Result<(), String> {
+    let (mut numbers, nans) = split_nans(input, policy)?;
+    fully_parallel_merge_sort_by(&mut numbers, threads, |a, b| {
+        a.total_order_key().cmp(&b.total_order_key())
+    })?;
+    place(input, &numbers, &nans, policy);
+    Ok(())
+}
+
+/**
+    Like `sort_floats`, but the numbers are transformed to their `total_order_key`s, sorted by the
+    given sorter on i64 (e.g. the radix sort) and transformed back.
+*/
+pub fn sort_floats_by_key<T: TotalOrder>(
+    input: &mut [T],
+    threads: usize,
+    policy: NanPolicy,
+    sorter: &dyn Sorter<i64>,
+) -> Result<(), String> {
+    let (numbers, nans) = split_nans(input, policy)?;
+    let mut keys: Vec<i64> = numbers.iter().map(|x| x.total_order_key()).collect();
+    if keys.len() > 1 {
+        sorter.sort(&mut keys, threads);
+    }
+    let numbers: Vec<T> = keys.into_iter().map(T::from_total_order_key).collect();
+    place(input, &numbers, &nans, policy);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::float_sort::{sort_floats, sort_floats_by_key, NanPolicy, TotalOrder};
+    use crate::input_generation::gen_input_with_seed;
+    use crate::sorter::registry;
+    use crate::NUM_PROCESSORS_TEST;
+    use benchmark::tuning::AUTO_THREADS;
+
+    // two NaNs with different payloads and signs, to check that they keep their order
+    fn nans() -> [f64; 2] {
+        [f64::from_bits(0x7ff8_0000_0000_0001), -f64::NAN]
+    }
+
+    fn input() -> Vec<f64> {
+        let special = [
+            0.0,
+            -0.0,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::MIN_POSITIVE,
+            -f64::MIN_POSITIVE / 4.0,
+            f64::MAX,
+            f64::MIN,
+            1.5,
+            -1.5,
+        ];
+        let mut input: Vec<f64> = gen_input_with_seed(3000, 1)
+            .iter()
+            .map(|x| *x as f64 / 7.0 - 1e9)
+            .collect();
+        for (i, x) in special.into_iter().chain(nans()).enumerate() {
+            input[i * 250] = x;
+        }
+        input
+    }
+
+    // the sorted input with the NaNs where the policy puts them, compared by bits
+    fn expected(input: &[f64], policy: NanPolicy) -> Vec<u64> {
+        let mut numbers: Vec<f64> = input.iter().copied().filter(|x| !x.is_nan()).collect();
+        numbers.sort_by(f64::total_cmp);
+        let nans = nans().to_vec();
+        let sorted = match policy {
+            NanPolicy::First => [nans, numbers].concat(),
+            _ => [numbers, nans].concat(),
+        };
+        sorted.iter().map(|x| x.to_bits()).collect()
+    }
+
+    fn bits(input: &[f64]) -> Vec<u64> {
+        input.iter().map(|x| x.to_bits()).collect()
+    }
+
+    #[test]
+    fn keys_follow_total_order() {
+        let values = [
+            f64::NEG_INFINITY,
+            -2.0,
+            -f64::MIN_POSITIVE,
+            -0.0,
+            0.0,
+            1e-300,
+            3.0,
+        ];
+        for pair in values.windows(2) {
+            assert!(pair[0].total_order_key() < pair[1].total_order_key());
+        }
+        for x in values.into_iter().chain(nans()) {
+            assert_eq!(
+                f64::from_total_order_key(x.total_order_key()).to_bits(),
+                x.to_bits()
+            );
+        }
+        let values = [
+            f32::NEG_INFINITY,
+            -1.0f32,
+            -0.0,
+            0.0,
+            f32::MIN_POSITIVE,
+            f32::MAX,
+        ];
+        for pair in values.windows(2) {
+            assert!(pair[0].total_order_key() < pair[1].total_order_key());
+        }
+        for x in values {
+            assert_eq!(
+                f32::from_total_order_key(x.total_order_key()).to_bits(),
+                x.to_bits()
+            );
+        }
+    }
+
+    #[test]
+    fn sorts_floats_with_nans_first_or_last() {
+        let input = input();
+        for policy in [NanPolicy::First, NanPolicy::Last] {
+            let expected = expected(&input, policy);
+            for threads in [1, 3, NUM_PROCESSORS_TEST, AUTO_THREADS] {
+                let mut output = input.clone();
+                assert_eq!(sort_floats(&mut output, threads, policy), Ok(()));
+                assert_eq!(bits(&output), expected, "{:?} with {}", policy, threads);
+            }
+            for sorter in registry() {
+                let mut output = input.clone();
+                let result = sort_floats_by_key(&mut output, 4, policy, sorter.as_ref());
+                assert_eq!(result, Ok(()));
+                assert_eq!(bits(&output), expected, "{:?} by {}", policy, sorter.name());
+            }
+        }
+
+        let mut small = vec![2.5f32, f32::NAN, -0.0, 0.0, -3.0];
+        sort_floats(&mut small, 2, NanPolicy::First).unwrap();
+        assert!(small[0].is_nan());
+        assert_eq!(&small[1..], &[-3.0, -0.0, 0.0, 2.5]);
+        assert!(small[2].is_sign_negative() && small[3].is_sign_positive());
+    }
+
+    #[test]
+    fn rejects_nans() {
+        let input = input();
+        let mut output = input.clone();
+        let result = sort_floats(&mut output, 2, NanPolicy::Reject);
+        assert_eq!(
+            result,
+            Err("NaN at position 2500 cannot be sorted".to_string())
+        );
+        assert_eq!(bits(&output), bits(&input));
+
+        let sorter = &registry()[0];
+        let result = sort_floats_by_key(&mut output, 2, NanPolicy::Reject, sorter.as_ref());
+        assert!(result.is_err());
+        assert_eq!(bits(&output), bits(&input));
+
+        let mut numbers = vec![1.0, -0.0, 0.0, -1.0];
+        assert_eq!(sort_floats(&mut numbers, 2, NanPolicy::Reject), Ok(()));
+        assert_eq!(bits(&numbers), bits(&[-1.0, -0.0, 0.0, 1.0]));
+    }
+}
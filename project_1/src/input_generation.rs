@@ -1,14 +1,14 @@
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{random, SeedableRng};
 use std::iter::repeat_with;
 use turborand::prelude::*;
 
-type LeftRightSplit = (Vec<i64>, Vec<i64>);
-pub type MergeFunction = fn(i64) -> LeftRightSplit;
+pub type LeftRightSplit = (Vec<i64>, Vec<i64>);
 
-#[allow(dead_code)]
-pub fn gen_input(input_size: usize) -> Vec<i64> {
-    let rand = Rng::new();
+// the random inputs can be reproduced by generating them with the same seed
+pub fn gen_input_with_seed(input_size: usize, seed: u64) -> Vec<i64> {
+    let rand = Rng::with_seed(seed);
 
     repeat_with(|| rand.i64(-(input_size as i64)..(input_size as i64)))
         .take(input_size)
@@ -16,39 +16,41 @@ pub fn gen_input(input_size: usize) -> Vec<i64> {
 }
 
 pub fn shuffled(input_size: i64) -> Vec<i64> {
+    shuffled_with_seed(input_size, random())
+}
+
+pub fn shuffled_with_seed(input_size: i64, seed: u64) -> Vec<i64> {
     let mut shuffled: Vec<i64> = (0..input_size).collect();
-    shuffled.shuffle(&mut thread_rng());
+    shuffled.shuffle(&mut StdRng::seed_from_u64(seed));
     shuffled
 }
 
-#[allow(dead_code)]
 pub fn reverse_sorted(input_size: i64) -> Vec<i64> {
     (0..input_size).rev().collect()
 }
 
-#[allow(dead_code)]
-pub fn all_left_smaller_than_right(input_size: i64) -> Vec<i64> {
+pub fn all_left_smaller_than_right_with_seed(input_size: i64, seed: u64) -> Vec<i64> {
+    let mut rng = StdRng::seed_from_u64(seed);
     let vec_length = input_size / 2;
     let mut left: Vec<i64> = (0..vec_length).collect();
     let mut right: Vec<i64> = (vec_length..input_size).collect();
-    left.shuffle(&mut thread_rng());
-    right.shuffle(&mut thread_rng());
+    left.shuffle(&mut rng);
+    right.shuffle(&mut rng);
     left.extend(right);
     left
 }
 
-#[allow(dead_code)]
-pub fn all_right_smaller_than_left(input_size: i64) -> Vec<i64> {
+pub fn all_right_smaller_than_left_with_seed(input_size: i64, seed: u64) -> Vec<i64> {
+    let mut rng = StdRng::seed_from_u64(seed);
     let vec_length = input_size / 2;
     let mut left: Vec<i64> = (vec_length..input_size).collect();
     let mut right: Vec<i64> = (0..vec_length).collect();
-    left.shuffle(&mut thread_rng());
-    right.shuffle(&mut thread_rng());
+    left.shuffle(&mut rng);
+    right.shuffle(&mut rng);
     left.extend(right);
     left
 }
 
-#[allow(dead_code)]
 pub fn right_fits_between_two_elements_in_left(input_size: i64) -> Vec<i64> {
     let mut left_first: Vec<i64> = (0..input_size / 4).collect();
     let left_second: Vec<i64> = ((3 * input_size / 4)..input_size).collect();
@@ -79,9 +81,16 @@ pub fn left_fits_between_last_two_elements_in_right(input_size: i64) -> LeftRigh
     (left, right)
 }
 
-pub fn random_sorted_halves(input_size: i64) -> LeftRightSplit {
+pub fn right_fits_between_two_elements_in_left_halves(input_size: i64) -> LeftRightSplit {
+    let mut left: Vec<i64> = (0..input_size / 4).collect();
+    left.extend((3 * input_size / 4)..input_size);
+    let right: Vec<i64> = ((input_size / 4)..(3 * input_size / 4)).collect();
+    (left, right)
+}
+
+pub fn random_sorted_halves_with_seed(input_size: i64, seed: u64) -> LeftRightSplit {
     let mut input_vector: Vec<i64> = (0..input_size).collect();
-    input_vector.shuffle(&mut thread_rng());
+    input_vector.shuffle(&mut StdRng::seed_from_u64(seed));
     let (left, right) = input_vector.split_at_mut((input_size / 2) as usize);
     left.sort();
     right.sort();
@@ -91,7 +100,7 @@ pub fn random_sorted_halves(input_size: i64) -> LeftRightSplit {
 
 #[cfg(test)]
 mod tests {
-    use crate::input_generation::alternating;
+    use crate::input_generation::{alternating, gen_input_with_seed, shuffled_with_seed};
 
     #[test]
     fn test_alternating() {
@@ -100,4 +109,11 @@ mod tests {
         left.extend(right);
         assert_eq!(left, expected);
     }
+
+    #[test]
+    fn same_seed_same_input() {
+        assert_eq!(shuffled_with_seed(1000, 42), shuffled_with_seed(1000, 42));
+        assert_ne!(shuffled_with_seed(1000, 42), shuffled_with_seed(1000, 43));
+        assert_eq!(gen_input_with_seed(1000, 42), gen_input_with_seed(1000, 42));
+    }
 }
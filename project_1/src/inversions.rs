@@ -0,0 +1,184 @@
+use crate::merge::{counting_merge, parallel_counting_merge};
+use benchmark::trace::span_with_size;
+use benchmark::tuning::{profile, AUTO_THREADS};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/**
+    The number of inversions of input, the pairs of positions i < j with input[i] > input[j].
+    They are counted while a copy of the input is merge sorted: the halves are sorted in parallel
+    and the inversions between them are counted by the threads of `parallel_counting_merge`.
+    threads may be `AUTO_THREADS`.
+*/
+pub fn count_inversions<T: Ord + Sync>(input: &[T], threads: usize) -> u64 {
+    // references compare like the values, and sorting them neither copies nor clones the values
+    let mut values: Vec<&T> = input.iter().collect();
+    let mut scratch = values.clone();
+    let (threads, cutoff) = profile().resolve("count_inversions", values.len(), threads, 1);
+    sort_counting_inversions(&mut values, &mut scratch, threads, cutoff)
+}
+
+// sorts input like fully_parallel_merge_sort_with_cutoff and returns the inversions it had
+fn sort_counting_inversions<T: Ord + Copy + Send + Sync>(
+    input: &mut [T],
+    scratch: &mut [T],
+    num_processors: usize,
+    cutoff: usize,
+) -> u64 {
+    let len = input.len();
+    if len < 2 {
+        return 0;
+    }
+    let mid = len / 2;
+
+    let (left_input, right_input) = input.split_at_mut(mid);
+    let (left_scratch, right_scratch) = scratch.split_at_mut(mid);
+
+    let inversions = if num_processors < 2 || len <= cutoff {
+        let left = sort_counting_inversions(left_input, left_scratch, 1, cutoff);
+        let right = sort_counting_inversions(right_input, right_scratch, 1, cutoff);
+        left + right + counting_merge(left_scratch, right_scratch, input)
+    } else {
+        let _span = span_with_size("sort", len);
+        let left_processors = num_processors / 2;
+        let right_processors = num_processors - left_processors;
+
+        let (left, right) = std::thread::scope(|scope| {
+            let left = scope.spawn(|| {
+                sort_counting_inversions(left_input, left_scratch, left_processors, cutoff)
+            });
+            let right =
+                sort_counting_inversions(right_input, right_scratch, right_processors, cutoff);
+            (left.join().unwrap(), right)
+        });
+        left + right + parallel_counting_merge(left_scratch, right_scratch, input, num_processors)
+    };
+    scratch.copy_from_slice(input);
+    inversions
+}
+
+/**
+    The Kendall tau distance of two rankings of the same items, the number of pairs of items that
+    are in a different order in a and b. None if the rankings are not permutations of each other
+    or an item appears twice.
+*/
+pub fn kendall_tau<T: Eq + Hash>(a: &[T], b: &[T]) -> Option<u64> {
+    if a.len() != b.len() {
+        return None;
+    }
+    let positions: HashMap<&T, usize> = b.iter().enumerate().map(|(i, item)| (item, i)).collect();
+    if positions.len() != b.len() {
+        return None;
+    }
+
+    // the positions in b of the items in the order of a, every discordant pair is an inversion
+    let mut seen = vec![false; b.len()];
+    let mut order = Vec::with_capacity(a.len());
+    for item in a {
+        let position = *positions.get(item)?;
+        if seen[position] {
+            return None;
+        }
+        seen[position] = true;
+        order.push(position);
+    }
+    Some(count_inversions(&order, AUTO_THREADS))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input_generation::{gen_input_with_seed, reverse_sorted, shuffled_with_seed};
+    use crate::inversions::{count_inversions, kendall_tau, sort_counting_inversions};
+    use crate::NUM_PROCESSORS_TEST;
+    use benchmark::tuning::AUTO_THREADS;
+
+    fn brute_force(input: &[i64]) -> u64 {
+        let mut inversions = 0;
+        for i in 0..input.len() {
+            for j in i + 1..input.len() {
+                if input[i] > input[j] {
+                    inversions += 1;
+                }
+            }
+        }
+        inversions
+    }
+
+    #[test]
+    fn inversions_match_brute_force() {
+        let inputs = vec![
+            vec![],
+            vec![1],
+            vec![2, 1],
+            vec![3, 3, 3],
+            reverse_sorted(500),
+            shuffled_with_seed(1500, 1),
+            gen_input_with_seed(1500, 4),
+            gen_input_with_seed(1500, 5).iter().map(|x| x % 5).collect(),
+        ];
+        for input in inputs {
+            let expected = brute_force(&input);
+            for threads in [1, 2, 3, NUM_PROCESSORS_TEST, AUTO_THREADS] {
+                assert_eq!(
+                    count_inversions(&input, threads),
+                    expected,
+                    "{} threads on {} elements",
+                    threads,
+                    input.len()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn inversions_with_cutoff_and_other_types() {
+        let input = shuffled_with_seed(3000, 2);
+        let expected = brute_force(&input);
+        for cutoff in [1, 100, 5000] {
+            let mut values = input.clone();
+            let mut scratch = input.clone();
+            let inversions = sort_counting_inversions(&mut values, &mut scratch, 4, cutoff);
+            assert_eq!(inversions, expected, "cutoff {}", cutoff);
+            assert!(values.windows(2).all(|w| w[0] <= w[1]));
+        }
+
+        let small: Vec<u8> = vec![3, 1, 2, 255, 0];
+        assert_eq!(count_inversions(&small, 2), 6);
+        let negative: Vec<i32> = vec![-1, -5, 4, -5];
+        assert_eq!(count_inversions(&negative, 2), 3);
+        let large: Vec<u64> = vec![u64::MAX, 0, u64::MAX - 1, 1];
+        assert_eq!(count_inversions(&large, 2), 4);
+        let indices: Vec<usize> = input.iter().map(|&x| x as usize).collect();
+        assert_eq!(count_inversions(&indices, 4), expected);
+        let words: Vec<String> = ["pear", "apple", "fig", "apple", "kiwi"]
+            .iter()
+            .map(|word| word.to_string())
+            .collect();
+        // pear is larger than the four words after it and fig than the second apple
+        assert_eq!(count_inversions(&words, 2), 5);
+    }
+
+    #[test]
+    fn kendall_tau_distance() {
+        let a = ["a", "b", "c", "d", "e"];
+        assert_eq!(kendall_tau(&a, &a), Some(0));
+        assert_eq!(kendall_tau(&a, &["e", "d", "c", "b", "a"]), Some(10));
+        // a moved behind b and c
+        assert_eq!(kendall_tau(&a, &["b", "c", "a", "d", "e"]), Some(2));
+
+        let ranking: Vec<usize> = (0..1000).collect();
+        let shuffled: Vec<usize> = shuffled_with_seed(1000, 3)
+            .iter()
+            .map(|&x| x as usize)
+            .collect();
+        assert_eq!(
+            kendall_tau(&ranking, &shuffled),
+            Some(brute_force(&shuffled_with_seed(1000, 3)))
+        );
+
+        assert_eq!(kendall_tau(&a, &["a", "b", "c", "d"]), None);
+        assert_eq!(kendall_tau(&a, &["a", "b", "c", "d", "x"]), None);
+        assert_eq!(kendall_tau(&["a", "a"], &["a", "b"]), None);
+        assert_eq!(kendall_tau(&["a", "b"], &["a", "a"]), None);
+    }
+}
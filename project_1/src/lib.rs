@@ -0,0 +1,19 @@
+pub mod animation;
+pub mod argsort;
+pub mod bitonic_merge;
+pub mod comparator_sort;
+pub mod float_sort;
+pub mod input_generation;
+pub mod inversions;
+pub mod merge;
+pub mod merge_sort;
+pub mod natural_merge_sort;
+pub mod radix_sort;
+pub mod sample_sort;
+pub mod selection;
+pub mod set_operations;
+pub mod sorter;
+pub mod utils;
+pub mod workload;
+
+pub const NUM_PROCESSORS_TEST: usize = 8;
@@ -1,58 +1,285 @@
-mod input_generation;
-mod merge;
-mod merge_sort;
-mod plotting;
-mod utils;
-
-use crate::input_generation::shuffled;
-use crate::merge_sort::{fully_parallel_merge_sort, parallel_merge_sort, sequential_merge_sort};
-use crate::plotting::{
-    plot_runtime_depending_on_input_generation, plot_runtime_depending_on_threads,
+use benchmark::compare::compare_command;
+use benchmark::runner::{plot_results, run_spec};
+use benchmark::spec::BenchmarkSpec;
+use benchmark::trace::{record, write_chrome_trace};
+use benchmark::tuning::{
+    calibrate, candidate_threads, load_profile, parse_threads, seconds, TuningProfile,
 };
-use crate::utils::is_sorted;
+use benchmark::workspan::{simulation_table, WorkSpan};
+use project_1::animation::animate;
+use project_1::input_generation::{random_sorted_halves_with_seed, shuffled, shuffled_with_seed};
+use project_1::merge::{
+    merge_threads, parallel_merge, parallel_merge_observed, sequential_merge,
+    simulate_parallel_merge, MergeKernel,
+};
+use project_1::merge_sort::{simulate_fully_parallel_merge_sort, simulate_parallel_merge_sort};
+use project_1::sorter::{find_sorter, registry};
+use project_1::utils::is_sorted;
+use project_1::workload::SortWorkload;
 use std::env;
+use std::error::Error;
+use std::process::exit;
+use std::thread::available_parallelism;
 use std::time::Instant;
 
-pub const NUM_PROCESSORS_TEST: usize = 8;
+// the thread counts and cutoffs chosen by `tune`, used wherever the thread count is "auto"
+const TUNING_PROFILE: &str = "project_1/tuning.toml";
 
 fn main() {
-    //let _ = plot_runtime_depending_on_threads();
-    //let _ = plot_runtime_depending_on_input_generation();
+    let args: Vec<String> = env::args().collect();
+    // a broken profile must not stop `tune` from writing a new one, "auto" then uses all cpus
+    if let Err(error) = load_profile(TUNING_PROFILE) {
+        println!("ignoring {}: {}", TUNING_PROFILE, error);
+    }
+
+    // run the experiments and plots of a benchmark spec, or only redraw the plots from its results
+    if args.len() >= 2 && (args[1] == "bench" || args[1] == "plot") {
+        let path = args
+            .get(2)
+            .map(String::as_str)
+            .unwrap_or("project_1/benchmarks.toml");
+        let result = BenchmarkSpec::load(path).and_then(|spec| {
+            if args[1] == "plot" {
+                plot_results(&SortWorkload(), &spec)
+            } else {
+                run_spec(&SortWorkload(), &spec).map(|_| ())
+            }
+        });
+        if let Err(error) = result {
+            println!("{}", error);
+        }
+        return;
+    }
+
+    // compare two results files and fail if the candidate has regressions
+    if args.len() >= 2 && args[1] == "compare" {
+        match compare_command(&SortWorkload(), &args[2..]) {
+            Ok(false) => {}
+            Ok(true) => exit(1),
+            Err(error) => {
+                println!("{}", error);
+                exit(2);
+            }
+        }
+        return;
+    }
+
+    // simulate the parallel sorts and the parallel merge on a virtual machine
+    if args.len() >= 2 && args[1] == "simulate" {
+        match simulate(&args[2..]) {
+            Ok(table) => print!("{}", table),
+            Err(error) => {
+                println!("{}", error);
+                exit(2);
+            }
+        }
+        return;
+    }
+
+    // "animate" renders the ranks, chunks and leaf merges of one parallel merge
+    if args.len() >= 2 && args[1] == "animate" {
+        if let Err(error) = animate_merge(&args[2..]) {
+            println!("{}", error);
+            exit(2);
+        }
+        return;
+    }
+
+    // calibrate the thread count and cutoff per input size for "auto" threads
+    if args.len() >= 2 && args[1] == "tune" {
+        if let Err(error) = tune(&args[2..]) {
+            println!("{}", error);
+            exit(2);
+        }
+        return;
+    }
 
     // this can be used to run the merge sort on individual input sizes and thread counts
-    // and measure the time of the specific strategy
-    let args: Vec<String> = env::args().collect();
+    // (or "auto") and measure the time of the specific strategy
+    // with a fifth argument the spans of all threads are written there as a Chrome trace
     if args.len() >= 4 {
         let input_size: usize = args[1].parse().unwrap();
-        let num_processors: usize = args[2].parse().unwrap();
+        let num_processors = match parse_threads(&args[2]) {
+            Ok(threads) => threads,
+            Err(error) => {
+                println!("{}", error);
+                exit(2);
+            }
+        };
         let strategy = args[3].clone();
 
+        let Some(sorter) = find_sorter(&strategy) else {
+            println!("Unknown strategy: {}", strategy);
+            println!("Known strategies are:");
+            for sorter in registry() {
+                println!("  {} ({})", sorter.name(), sorter.description());
+            }
+            return;
+        };
+
         let mut input: Vec<i64> = shuffled(input_size as i64);
         let mut scratch: Vec<i64> = input.clone();
 
         let now = Instant::now();
-        match strategy.as_str() {
-            "SSSM" => {
-                sequential_merge_sort(&mut input, &mut scratch);
-            }
-            "PSSM" => {
-                parallel_merge_sort(&mut input, &mut scratch, num_processors);
-            }
-            "PSPM" => {
-                fully_parallel_merge_sort(&mut input, &mut scratch, num_processors);
-            }
-            _ => {
-                println!("Unknown strategy: {}", strategy);
-                println!(
-                    "Known strategies are: SSSM (sequential sort sequential merge),\
-                 PSSM (parallel sort sequential merge), PSPM (parallel sort parallel merge)"
-                );
+        if let Some(path) = args.get(4) {
+            let (_, spans) =
+                record(|| sorter.sort_with_scratch(&mut input, &mut scratch, num_processors));
+            if let Err(error) = write_chrome_trace(path, "project_1", &spans) {
+                println!("{}", error);
+                exit(2);
             }
+            println!("{} spans written to {}", spans.len(), path);
+        } else {
+            sorter.sort_with_scratch(&mut input, &mut scratch, num_processors);
         }
-
         let elapsed = now.elapsed();
 
         println!("Time: {:.3?}", elapsed);
-        println!("correct: {:?}", is_sorted(scratch));
+        println!("correct: {:?}", is_sorted(input));
+    }
+}
+
+/**
+    `animate <n> <threads|auto> <animation.gif|frame directory>` merges two random sorted halves
+    of 0..n with the parallel merge and renders one frame per rank, chunk and leaf merge.
+*/
+fn animate_merge(args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args.len() < 3 {
+        return Err("usage: animate <n> <threads|auto> <animation.gif|frame directory>".into());
+    }
+    let size: usize = args[0]
+        .parse()
+        .map_err(|_| format!("invalid input size {}", args[0]))?;
+    let threads = merge_threads(size / 2, parse_threads(&args[1])?);
+    let (left, right) = random_sorted_halves_with_seed(size as i64, 0);
+
+    let mut output = vec![0i64; size];
+    let mut events = vec![];
+    parallel_merge_observed(
+        &left,
+        &right,
+        &mut output,
+        threads,
+        MergeKernel::Standard,
+        &mut |event| events.push(event),
+    );
+
+    let title = format!("parallel merge, {} threads", threads);
+    animate(&args[2], (960, 360), &title, &left, &right, &events, 800)?;
+    println!("{} frames written to {}", events.len() + 1, args[2]);
+    Ok(())
+}
+
+/**
+    `simulate <PSSM|PSPM|parallel_merge> <n> [threads ...]` prints the work, span and Brent bound
+    of the algorithm on a virtual machine with each thread count (1 to 64 by default). The input
+    is generated with a fixed seed and the cost is counted in operations, so the table is the same
+    on every machine.
+*/
+fn simulate(args: &[String]) -> Result<String, String> {
+    let usage = "usage: simulate <PSSM|PSPM|parallel_merge> <n> [threads ...]";
+    if args.len() < 2 {
+        return Err(usage.to_string());
+    }
+    let size: usize = args[1]
+        .parse()
+        .map_err(|_| format!("invalid input size {}", args[1]))?;
+    let threads = if args.len() > 2 {
+        args[2..]
+            .iter()
+            .map(|p| p.parse().map_err(|_| format!("invalid thread count {}", p)))
+            .collect::<Result<Vec<usize>, String>>()?
+    } else {
+        vec![1, 2, 4, 8, 16, 32, 64]
+    };
+
+    let run = |processors: usize| -> Result<WorkSpan, String> {
+        if args[0] == "parallel_merge" {
+            let (left, right) = random_sorted_halves_with_seed(size as i64, 0);
+            let mut output = vec![0i64; size];
+            return Ok(simulate_parallel_merge(
+                &left,
+                &right,
+                &mut output,
+                processors,
+            ));
+        }
+        let mut input = shuffled_with_seed(size as i64, 0);
+        let mut scratch = input.clone();
+        match args[0].as_str() {
+            "PSSM" => Ok(simulate_parallel_merge_sort(
+                &mut input,
+                &mut scratch,
+                processors,
+            )),
+            "PSPM" => Ok(simulate_fully_parallel_merge_sort(
+                &mut input,
+                &mut scratch,
+                processors,
+            )),
+            _ => Err(usage.to_string()),
+        }
+    };
+    let runs = threads
+        .iter()
+        .map(|&p| run(p).map(|work_span| (p, work_span)))
+        .collect::<Result<Vec<(usize, WorkSpan)>, String>>()?;
+    Ok(simulation_table(&format!("{}, n={}", args[0], size), &runs))
+}
+
+/**
+    `tune [algorithm ...]` measures the sorters with a sequential cutoff and the parallel merge (or
+    only the given algorithms) on inputs of 10^3 to 10^6 elements with every candidate thread count
+    and cutoff. The fastest setting per size replaces the one of the algorithm in the tuning
+    profile.
+*/
+fn tune(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let algorithms: Vec<String> = if args.is_empty() {
+        registry()
+            .iter()
+            .filter(|sorter| sorter.has_cutoff())
+            .map(|sorter| sorter.name().to_string())
+            .chain(["parallel_merge".to_string()])
+            .collect()
+    } else {
+        args.to_vec()
+    };
+    let sizes = [1000, 10000, 100000, 1000000];
+    let cutoffs = [1, 64, 512, 4096, 32768];
+
+    let mut profile = TuningProfile::load(TUNING_PROFILE).unwrap_or_default();
+    for algorithm in algorithms {
+        let tuning = if algorithm == "parallel_merge" {
+            // like the merge workload, a single thread merges sequentially
+            let run = |size: usize, threads: usize, cutoff: usize| {
+                let (left, right) = random_sorted_halves_with_seed(size as i64, 0);
+                let mut output = vec![0; size];
+                let threads = threads.min((right.len() / cutoff).max(1));
+                seconds(|| {
+                    if threads > 1 {
+                        parallel_merge(&left, &right, &mut output, threads);
+                    } else {
+                        sequential_merge(&left, &right, &mut output);
+                    }
+                })
+            };
+            calibrate(&algorithm, &sizes, &candidate_threads(), &cutoffs, 3, &run)
+        } else {
+            let sorter =
+                find_sorter(&algorithm).ok_or(format!("unknown algorithm {}", algorithm))?;
+            let run = |size: usize, threads: usize, cutoff: usize| {
+                let mut input = shuffled_with_seed(size as i64, 0);
+                let mut scratch = input.clone();
+                seconds(|| sorter.sort_with_cutoff(&mut input, &mut scratch, threads, cutoff))
+            };
+            let cutoffs: &[usize] = if sorter.has_cutoff() { &cutoffs } else { &[1] };
+            calibrate(&algorithm, &sizes, &candidate_threads(), cutoffs, 3, &run)
+        };
+        profile.tuning.retain(|t| t.algorithm != algorithm);
+        profile.tuning.extend(tuning);
     }
+    profile.cpus = available_parallelism().map_or(1, |n| n.get());
+    profile.save(TUNING_PROFILE)?;
+    println!("profile written to {}", TUNING_PROFILE);
+    Ok(())
 }
@@ -1,6 +1,15 @@
-use crate::utils::binary_search;
+use crate::bitonic_merge::bitonic_merge;
+use crate::utils::{
+    batch_ranks_by, binary_search, exponential_search, exponential_search_upper, Bound,
+};
+use benchmark::counters::{count, Counter};
+use benchmark::trace::span_with_size;
+use benchmark::tuning::profile;
+use benchmark::workspan::{measure, WorkSpan};
 use std::cmp::min;
 use std::iter::zip;
+use std::ops::Range;
+use std::sync::mpsc::channel;
 
 pub fn sequential_merge(left: &[i64], right: &[i64], output: &mut [i64]) {
     let (mut i, mut j, mut k) = (0, 0, 0);
@@ -15,6 +24,9 @@ pub fn sequential_merge(left: &[i64], right: &[i64], output: &mut [i64]) {
         }
         k += 1;
     }
+    // one comparison per element written so far
+    count(Counter::Comparisons, k as u64);
+    count(Counter::Writes, output.len() as u64);
 
     while i < left.len() {
         output[k] = left[i];
@@ -29,35 +41,252 @@ pub fn sequential_merge(left: &[i64], right: &[i64], output: &mut [i64]) {
     }
 }
 
-/** TASK 2 */
-pub fn parallel_merge(left: &[i64], right: &[i64], output: &mut [i64], num_processors: usize) {
-    let n = right.len();
+/**
+    Same merge as `sequential_merge`, but the choice of the next element is turned into arithmetic
+    on the comparison result, so the compiler can emit conditional moves instead of a branch that
+    is mispredicted about half the time on random inputs.
+*/
+pub fn branchless_merge(left: &[i64], right: &[i64], output: &mut [i64]) {
+    let (mut i, mut j, mut k) = (0, 0, 0);
 
-    // if there are fewer elements in right array than the number of processors available, use only right.len() processors
-    let threads = min(num_processors, n);
+    while i < left.len() && j < right.len() {
+        let (l, r) = (left[i], right[j]);
+        let take_left = l < r;
+        output[k] = if take_left { l } else { r };
+        i += take_left as usize;
+        j += !take_left as usize;
+        k += 1;
+    }
+    count(Counter::Comparisons, k as u64);
+    count(Counter::Writes, output.len() as u64);
 
-    // allocate array R[0,...,p] and R[0] = 0
-    let mut rank_vector = vec![0usize; threads + 1];
-    let rank_chunks = rank_vector.chunks_mut(1);
-    let chunk_size = (n as f64 / (threads as f64)).ceil() as usize;
+    output[k..k + left.len() - i].copy_from_slice(&left[i..]);
+    k += left.len() - i;
+    output[k..].copy_from_slice(&right[j..]);
+}
 
-    // binary search for upper bound of each piece - if looking at right[i] place in output array on spot i + rank(right[i], left)
-    // get rank
-    std::thread::scope(|scope| {
-        for (i, rank) in zip(0..threads + 1, rank_chunks) {
-            if i == 0 {
-                // R[0] = 0
-                continue;
-            } else if i == threads {
-                // find rank of last element in right - this is the last upper bound
-                rank[0] = binary_search(left, right[n - 1]);
-            } else {
-                scope.spawn(move || {
-                    rank[0] = binary_search(left, right[i * chunk_size]);
-                });
+// number of consecutive wins of one side after which galloping_merge switches to exponential search
+const MIN_GALLOP: usize = 7;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MergeKernel {
+    Standard,
+    Galloping,
+    Branchless,
+    Bitonic,
+}
+
+impl MergeKernel {
+    pub const ALL: [MergeKernel; 4] = [
+        MergeKernel::Standard,
+        MergeKernel::Galloping,
+        MergeKernel::Branchless,
+        MergeKernel::Bitonic,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MergeKernel::Standard => "Sequential",
+            MergeKernel::Galloping => "Galloping",
+            MergeKernel::Branchless => "Branchless",
+            MergeKernel::Bitonic => "Bitonic",
+        }
+    }
+
+    pub fn merge(&self, left: &[i64], right: &[i64], output: &mut [i64]) {
+        match self {
+            MergeKernel::Standard => sequential_merge(left, right, output),
+            MergeKernel::Galloping => galloping_merge(left, right, output),
+            MergeKernel::Branchless => branchless_merge(left, right, output),
+            MergeKernel::Bitonic => bitonic_merge(left, right, output),
+        }
+    }
+}
+
+/**
+    Merges like `sequential_merge` (ties are taken from right first) but once one side has won
+    MIN_GALLOP comparisons in a row, the length of its winning streak is found with exponential
+    search and the whole block is copied at once. Galloping continues as long as the blocks found
+    are at least MIN_GALLOP long.
+*/
+pub fn galloping_merge(left: &[i64], right: &[i64], output: &mut [i64]) {
+    let (mut i, mut j, mut k) = (0, 0, 0);
+    let (mut left_wins, mut right_wins) = (0, 0);
+    // the comparisons one element at a time, the exponential searches count their own
+    let mut comparisons = 0;
+
+    while i < left.len() && j < right.len() {
+        if left_wins >= MIN_GALLOP || right_wins >= MIN_GALLOP {
+            // galloping mode: copy the block of left elements smaller than right[j]
+            let left_block = exponential_search(&left[i..], right[j]);
+            output[k..k + left_block].copy_from_slice(&left[i..i + left_block]);
+            i += left_block;
+            k += left_block;
+            if i == left.len() {
+                break;
             }
+
+            // and the block of right elements smaller or equal to left[i]
+            let right_block = exponential_search_upper(&right[j..], left[i]);
+            output[k..k + right_block].copy_from_slice(&right[j..j + right_block]);
+            j += right_block;
+            k += right_block;
+
+            if left_block < MIN_GALLOP && right_block < MIN_GALLOP {
+                // blocks got short, go back to comparing one element at a time
+                left_wins = 0;
+                right_wins = 0;
+            }
+            continue;
         }
-    });
+
+        comparisons += 1;
+        if left[i] < right[j] {
+            output[k] = left[i];
+            i += 1;
+            left_wins += 1;
+            right_wins = 0;
+        } else {
+            output[k] = right[j];
+            j += 1;
+            right_wins += 1;
+            left_wins = 0;
+        }
+        k += 1;
+    }
+    count(Counter::Comparisons, comparisons);
+    count(Counter::Writes, output.len() as u64);
+
+    output[k..k + left.len() - i].copy_from_slice(&left[i..]);
+    k += left.len() - i;
+    output[k..].copy_from_slice(&right[j..]);
+}
+
+// the number of threads and the number of elements of right each of them merges
+pub fn chunks(n: usize, num_processors: usize) -> (usize, usize) {
+    // if there are fewer elements in right array than the number of processors available, use only right.len() processors
+    let threads = min(num_processors.max(1), n);
+    let chunk_size = n.div_ceil(threads);
+    // rounding the chunk size up can leave fewer chunks than threads (e.g. 10 elements on 8 threads)
+    (n.div_ceil(chunk_size), chunk_size)
+}
+
+/**
+    The number of threads to merge n elements with when asked for the given number, which may be
+    `AUTO_THREADS`. Every thread gets at least cutoff elements of the right array, the cutoff of a
+    fixed thread count is 1.
+*/
+pub fn merge_threads(n: usize, threads: usize) -> usize {
+    let (threads, cutoff) = profile().resolve("parallel_merge", n, threads, 1);
+    min(threads, (n / cutoff).max(1))
+}
+
+/**
+    The ranks R[0..=threads] in left of the first element of each chunk of right (and of the last
+    element of right for R[threads]), searched for in parallel by `batch_ranks_by`.
+*/
+pub fn ranks<T: Ord + Copy + Sync>(
+    left: &[T],
+    right: &[T],
+    threads: usize,
+    chunk_size: usize,
+    bound: Bound,
+) -> Vec<usize> {
+    let n = right.len();
+    // if looking at right[i] place in output array on spot i + rank(right[i], left)
+    let queries: Vec<T> = (1..threads)
+        .map(|i| right[i * chunk_size])
+        .chain([right[n - 1]])
+        .collect();
+    // R[0] = 0
+    [0].into_iter()
+        .chain(batch_ranks_by(left, &queries, bound, threads, &T::cmp))
+        .collect()
+}
+
+/** TASK 2 */
+// num_processors may be `AUTO_THREADS` like for all the parallel merges
+pub fn parallel_merge(left: &[i64], right: &[i64], output: &mut [i64], num_processors: usize) {
+    parallel_merge_with_kernel(left, right, output, num_processors, MergeKernel::Standard);
+}
+
+/**
+    A step of the parallel merge, used to animate it. Ranges index into left, right and output of
+    the merge, threads are numbered by the chunk of right they merge.
+*/
+#[derive(Debug, PartialEq, Clone)]
+pub enum MergeEvent {
+    // right[right], the first element of the chunk of a thread, belongs before left[rank]
+    Rank {
+        thread: usize,
+        right: usize,
+        rank: usize,
+    },
+    // the parts of left and right a thread merges into the given part of output
+    Chunk {
+        thread: usize,
+        left: Range<usize>,
+        right: Range<usize>,
+        output: Range<usize>,
+    },
+    // a thread has merged its chunk
+    LeafMerge {
+        thread: usize,
+    },
+}
+
+/**
+    Parallel merge that uses the given merge kernel for the sequential merges of the chunks of
+    each thread.
+*/
+pub fn parallel_merge_with_kernel(
+    left: &[i64],
+    right: &[i64],
+    output: &mut [i64],
+    num_processors: usize,
+    kernel: MergeKernel,
+) {
+    parallel_merge_observed(left, right, output, num_processors, kernel, &mut |_| {});
+}
+
+/**
+    Like `parallel_merge_with_kernel`, but reports the ranks, the chunks and the finished leaf
+    merges to observe. observe is only called from the calling thread, the threads send it their
+    leaf merges as they finish them, so these are reported in the order they completed in.
+*/
+pub fn parallel_merge_observed(
+    left: &[i64],
+    right: &[i64],
+    output: &mut [i64],
+    num_processors: usize,
+    kernel: MergeKernel,
+    observe: &mut impl FnMut(MergeEvent),
+) {
+    let _span = span_with_size("parallel merge", output.len());
+    let num_processors = merge_threads(right.len(), num_processors);
+    let n = right.len();
+    if n == 0 {
+        output.copy_from_slice(left);
+        observe(MergeEvent::Chunk {
+            thread: 0,
+            left: 0..left.len(),
+            right: 0..0,
+            output: 0..output.len(),
+        });
+        observe(MergeEvent::LeafMerge { thread: 0 });
+        return;
+    }
+
+    let (threads, chunk_size) = chunks(n, num_processors);
+
+    let rank_vector = ranks(left, right, threads, chunk_size, Bound::Lower);
+    for (thread, rank) in rank_vector[..threads].iter().enumerate() {
+        observe(MergeEvent::Rank {
+            thread,
+            right: thread * chunk_size,
+            rank: *rank,
+        });
+    }
 
     // if any elements remain in left, that are larger than all elements in right, put them in output
     let last_rank = rank_vector[rank_vector.len() - 1];
@@ -66,7 +295,24 @@ pub fn parallel_merge(left: &[i64], right: &[i64], output: &mut [i64], num_proce
         output[output_len - left[last_rank..].len()..].copy_from_slice(&left[last_rank..]);
     }
 
+    for thread in 0..threads {
+        // the last thread also takes the elements of left beyond the last rank
+        let left_end = if thread == threads - 1 {
+            left.len()
+        } else {
+            rank_vector[thread + 1]
+        };
+        let right_start = thread * chunk_size;
+        observe(MergeEvent::Chunk {
+            thread,
+            left: rank_vector[thread]..left_end,
+            right: right_start..min(right_start + chunk_size, n),
+            output: rank_vector[thread] + right_start..left_end + min(right_start + chunk_size, n),
+        });
+    }
+
     let right_chunks = right.chunks(chunk_size);
+    let (finished, leaf_merges) = channel();
     // merge each chunk in sequentially ()
     std::thread::scope(|scope| {
         let mut current_chunk;
@@ -78,24 +324,230 @@ pub fn parallel_merge(left: &[i64], right: &[i64], output: &mut [i64], num_proce
 
             if i == threads - 1 {
                 let left_slice = &left[rank_vector[i]..];
-                sequential_merge(left_slice, right_chunk, rest);
+                let _span = span_with_size("leaf merge", rest.len());
+                kernel.merge(left_slice, right_chunk, rest);
+                let _ = finished.send(MergeEvent::LeafMerge { thread: i });
             } else {
                 (current_chunk, rest) =
                     rest.split_at_mut(chunk_size + rank_vector[i + 1] - rank_vector[i]);
                 let left_slice = &left[rank_vector[i]..rank_vector[i + 1]];
+                let finished = finished.clone();
                 // let output_slice = &mut full_chunks[i * chunk_size + rank_vector[i]..  (i+1) * chunk_size + rank_vector[i+1]];
                 scope.spawn(move || {
-                    sequential_merge(left_slice, right_chunk, current_chunk);
+                    let _span = span_with_size("leaf merge", current_chunk.len());
+                    kernel.merge(left_slice, right_chunk, current_chunk);
+                    let _ = finished.send(MergeEvent::LeafMerge { thread: i });
                 });
             }
         }
+        // the events of the threads still merging arrive until the last of them is done
+        drop(finished);
+        for event in leaf_merges {
+            observe(event);
+        }
+    });
+}
+
+/**
+    Merges like `sequential_merge`, but takes ties from left and returns the number of inversions
+    between the two sides, the pairs of an element of left and a smaller element of right.
+*/
+pub fn counting_merge<T: Ord + Copy>(left: &[T], right: &[T], output: &mut [T]) -> u64 {
+    let (mut i, mut j, mut k) = (0, 0, 0);
+    let mut inversions = 0;
+
+    while i < left.len() && j < right.len() {
+        if left[i] <= right[j] {
+            output[k] = left[i];
+            i += 1;
+        } else {
+            // right[j] is smaller than all remaining elements of left
+            output[k] = right[j];
+            j += 1;
+            inversions += (left.len() - i) as u64;
+        }
+        k += 1;
+    }
+    count(Counter::Comparisons, k as u64);
+    count(Counter::Writes, output.len() as u64);
+
+    output[k..k + left.len() - i].copy_from_slice(&left[i..]);
+    k += left.len() - i;
+    output[k..].copy_from_slice(&right[j..]);
+    inversions
+}
+
+/**
+    Parallel merge like `parallel_merge` that returns the inversions between left and right, each
+    thread counts those of its chunk of right. The ranks are upper bounds, so everything in left
+    before the slice of a chunk is at most its first element, and everything after the slice is
+    larger than the whole chunk.
+*/
+pub fn parallel_counting_merge<T: Ord + Copy + Send + Sync>(
+    left: &[T],
+    right: &[T],
+    output: &mut [T],
+    num_processors: usize,
+) -> u64 {
+    let _span = span_with_size("parallel merge", output.len());
+    let num_processors = merge_threads(right.len(), num_processors);
+    let n = right.len();
+    if n == 0 {
+        output.copy_from_slice(left);
+        return 0;
+    }
+
+    let (threads, chunk_size) = chunks(n, num_processors);
+    let rank_vector = ranks(left, right, threads, chunk_size, Bound::Upper);
+
+    std::thread::scope(|scope| {
+        let mut current_chunk;
+        let mut rest = output;
+        let mut inversions = 0;
+        let mut handles = vec![];
+
+        for (i, right_chunk) in zip(0..threads, right.chunks(chunk_size)) {
+            if i == threads - 1 {
+                // the last slice of left reaches to its end, the merge counts all its inversions
+                let left_slice = &left[rank_vector[i]..];
+                let _span = span_with_size("leaf merge", rest.len());
+                inversions += counting_merge(left_slice, right_chunk, rest);
+            } else {
+                (current_chunk, rest) =
+                    rest.split_at_mut(chunk_size + rank_vector[i + 1] - rank_vector[i]);
+                let left_slice = &left[rank_vector[i]..rank_vector[i + 1]];
+                inversions += ((left.len() - rank_vector[i + 1]) * right_chunk.len()) as u64;
+                handles.push(scope.spawn(move || {
+                    let _span = span_with_size("leaf merge", current_chunk.len());
+                    counting_merge(left_slice, right_chunk, current_chunk)
+                }));
+            }
+        }
+        inversions + handles.into_iter().map(|h| h.join().unwrap()).sum::<u64>()
+    })
+}
+
+/**
+    Runs `parallel_merge` on a virtual machine with the given number of processors: first all rank
+    searches in parallel, then all chunk merges in parallel, every task on the calling thread.
+*/
+pub fn simulate_parallel_merge(
+    left: &[i64],
+    right: &[i64],
+    output: &mut [i64],
+    num_processors: usize,
+) -> WorkSpan {
+    let n = right.len();
+    if n == 0 {
+        return measure(|| output.copy_from_slice(left)).1;
+    }
+    let (threads, chunk_size) = chunks(n, num_processors);
+
+    let mut rank_vector = vec![0usize; threads + 1];
+    let searches = WorkSpan::parallel((1..threads + 1).map(|i| {
+        let key = if i == threads {
+            right[n - 1]
+        } else {
+            right[i * chunk_size]
+        };
+        let (rank, search) = measure(|| binary_search(left, key));
+        rank_vector[i] = rank;
+        search
+    }));
+
+    let last_rank = rank_vector[threads];
+    let (_, rest_of_left) = measure(|| {
+        if last_rank < left.len() {
+            let output_len = output.len();
+            output[output_len - left[last_rank..].len()..].copy_from_slice(&left[last_rank..]);
+        }
     });
+
+    let mut merges = vec![];
+    let mut current_chunk;
+    let mut rest = output;
+    for (i, right_chunk) in zip(0..threads, right.chunks(chunk_size)) {
+        if i == threads - 1 {
+            let left_slice = &left[rank_vector[i]..];
+            merges.push(measure(|| sequential_merge(left_slice, right_chunk, rest)).1);
+            break;
+        }
+        (current_chunk, rest) = rest.split_at_mut(chunk_size + rank_vector[i + 1] - rank_vector[i]);
+        let left_slice = &left[rank_vector[i]..rank_vector[i + 1]];
+        merges.push(measure(|| sequential_merge(left_slice, right_chunk, current_chunk)).1);
+    }
+
+    searches.then(rest_of_left).then(WorkSpan::parallel(merges))
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::merge::{parallel_merge, sequential_merge};
+    use crate::input_generation::{
+        alternating, left_fits_between_last_two_elements_in_right, random_sorted_halves_with_seed,
+        right_fits_between_two_elements_in_left_halves, sorted, LeftRightSplit,
+    };
+    use crate::merge::simulate_parallel_merge;
+    use crate::merge::{
+        branchless_merge, counting_merge, galloping_merge, parallel_counting_merge, parallel_merge,
+        parallel_merge_with_kernel, sequential_merge, MergeKernel,
+    };
     use crate::NUM_PROCESSORS_TEST;
+    use benchmark::counters::{counted_locally, Operations};
+    use benchmark::tuning::AUTO_THREADS;
+
+    #[test]
+    fn counted_comparisons_and_writes() {
+        let left: Vec<i64> = vec![1, 3, 5, 7];
+        let right: Vec<i64> = vec![2, 4, 6, 8, 9, 10];
+        let mut output = vec![0i64; 10];
+        let (_, operations) = counted_locally(|| sequential_merge(&left, &right, &mut output));
+        // left runs out after 7 comparisons, the rest of right is copied without comparing
+        assert_eq!(
+            operations,
+            Operations {
+                comparisons: 7,
+                writes: 10,
+                ..Operations::default()
+            }
+        );
+        assert_eq!(output, (1..=10).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn galloping_saves_comparisons_on_lopsided_inputs() {
+        // a few elements of right between long runs of left
+        let left: Vec<i64> = (0..1000).filter(|x| x % 100 != 50).collect();
+        let right: Vec<i64> = (0..1000).filter(|x| x % 100 == 50).collect();
+        let expected: Vec<i64> = (0..1000).collect();
+
+        let mut output = vec![0i64; 1000];
+        let (_, sequential) = counted_locally(|| sequential_merge(&left, &right, &mut output));
+        assert_eq!(output, expected);
+        let mut output = vec![0i64; 1000];
+        let (_, galloping) = counted_locally(|| galloping_merge(&left, &right, &mut output));
+        assert_eq!(output, expected);
+
+        assert_eq!(galloping.writes, sequential.writes);
+        assert!(galloping.comparisons * 4 < sequential.comparisons);
+    }
+
+    #[test]
+    fn simulated_merge_matches_the_parallel_merge() {
+        let (left, right) = random_sorted_halves_with_seed(1000, 2);
+        let mut expected = vec![0i64; 1000];
+        parallel_merge(&left, &right, &mut expected, 4);
+
+        let mut output = vec![0i64; 1000];
+        let sequential = simulate_parallel_merge(&left, &right, &mut output, 1);
+        assert_eq!(output, expected);
+        let mut output = vec![0i64; 1000];
+        let parallel = simulate_parallel_merge(&left, &right, &mut output, 4);
+        assert_eq!(output, expected);
+
+        // the merges are split evenly, only the rank searches are additional work
+        assert!(parallel.work >= sequential.work);
+        assert!(parallel.span < sequential.span / 2);
+    }
 
     // Test sequential merge
     #[test]
@@ -138,6 +590,58 @@ mod tests {
         assert_eq!(output, solution);
     }
 
+    // Test galloping merge
+    #[test]
+    fn galloping_merge_matches_sequential_merge() {
+        let generators: [fn(i64) -> LeftRightSplit; 5] = [
+            alternating,
+            sorted,
+            |n| random_sorted_halves_with_seed(n, 1),
+            left_fits_between_last_two_elements_in_right,
+            right_fits_between_two_elements_in_left_halves,
+        ];
+        for generator in generators {
+            let (left, right) = generator(1000);
+            let mut expected: Vec<i64> = vec![0i64; left.len() + right.len()];
+            let mut output: Vec<i64> = vec![0i64; left.len() + right.len()];
+            sequential_merge(&left, &right, &mut expected);
+            galloping_merge(&left, &right, &mut output);
+            assert_eq!(output, expected);
+        }
+    }
+
+    #[test]
+    fn merge_kernels_lopsided() {
+        let (left, right) = right_fits_between_two_elements_in_left_halves(1000);
+        let solution: Vec<i64> = (0..1000).collect();
+        for kernel in MergeKernel::ALL {
+            let mut output: Vec<i64> = vec![0i64; 1000];
+            kernel.merge(&left, &right, &mut output);
+            assert_eq!(output, solution, "{}", kernel.name());
+        }
+    }
+
+    #[test]
+    fn branchless_merge_random_sorted_halves() {
+        let (left, right) = random_sorted_halves_with_seed(1001, 3);
+        let mut expected: Vec<i64> = vec![0i64; 1001];
+        let mut output: Vec<i64> = vec![0i64; 1001];
+        sequential_merge(&left, &right, &mut expected);
+        branchless_merge(&left, &right, &mut output);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn galloping_merge_duplicates() {
+        let left: Vec<i64> = vec![1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3];
+        let right: Vec<i64> = vec![1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3];
+        let mut expected: Vec<i64> = [left.clone(), right.clone()].concat();
+        expected.sort();
+        let mut output: Vec<i64> = vec![0i64; left.len() + right.len()];
+        galloping_merge(&left, &right, &mut output);
+        assert_eq!(output, expected);
+    }
+
     // Test parallel merge
     #[test]
     fn parallel_merge_left_smaller() {
@@ -178,4 +682,63 @@ mod tests {
         parallel_merge(&left, &right, &mut output, NUM_PROCESSORS_TEST);
         assert_eq!(output, solution);
     }
+
+    #[test]
+    fn parallel_merge_fewer_chunks_than_threads() {
+        let left: Vec<i64> = (0..20).step_by(2).collect();
+        let right: Vec<i64> = (1..20).step_by(2).collect();
+        let solution: Vec<i64> = (0..20).collect();
+        let mut output: Vec<i64> = vec![0i64; 20];
+        parallel_merge(&left, &right, &mut output, NUM_PROCESSORS_TEST);
+        assert_eq!(output, solution);
+    }
+
+    #[test]
+    fn parallel_merge_right_empty() {
+        let left: Vec<i64> = vec![1, 2, 3];
+        let right: Vec<i64> = vec![];
+        let mut output: Vec<i64> = vec![0i64; 3];
+        parallel_merge(&left, &right, &mut output, NUM_PROCESSORS_TEST);
+        assert_eq!(output, left);
+    }
+
+    #[test]
+    fn parallel_merge_all_kernels() {
+        let (left, right) = random_sorted_halves_with_seed(2000, 4);
+        let solution: Vec<i64> = (0..2000).collect();
+        for kernel in MergeKernel::ALL {
+            for threads in [NUM_PROCESSORS_TEST, AUTO_THREADS] {
+                let mut output: Vec<i64> = vec![0i64; 2000];
+                parallel_merge_with_kernel(&left, &right, &mut output, threads, kernel);
+                assert_eq!(output, solution, "{} with {}", kernel.name(), threads);
+            }
+        }
+    }
+
+    #[test]
+    fn counting_merges_count_cross_inversions() {
+        // many duplicates, also across the halves and at the chunk boundaries
+        let (left, right) = random_sorted_halves_with_seed(1001, 5);
+        let left: Vec<i64> = left.iter().map(|x| x / 7).collect();
+        let right: Vec<i64> = right.iter().map(|x| x / 7).collect();
+        let inversions = left
+            .iter()
+            .map(|l| right.iter().filter(|r| *r < l).count() as u64)
+            .sum::<u64>();
+        let mut solution = [left.clone(), right.clone()].concat();
+        solution.sort();
+
+        let mut output = vec![0i64; 1001];
+        assert_eq!(counting_merge(&left, &right, &mut output), inversions);
+        assert_eq!(output, solution);
+        for threads in [1, 2, 3, NUM_PROCESSORS_TEST, 600] {
+            let mut output = vec![0i64; 1001];
+            let counted = parallel_counting_merge(&left, &right, &mut output, threads);
+            assert_eq!(counted, inversions, "{} threads", threads);
+            assert_eq!(output, solution, "{} threads", threads);
+        }
+        let mut output = vec![0i64; right.len()];
+        assert_eq!(parallel_counting_merge(&right, &[], &mut output, 4), 0);
+        assert_eq!(output, right);
+    }
 }
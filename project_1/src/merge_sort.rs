@@ -1,4 +1,19 @@
-use crate::merge::{parallel_merge, sequential_merge};
+use crate::merge::{parallel_merge, sequential_merge, simulate_parallel_merge};
+use benchmark::counters::{count, Counter};
+use benchmark::trace::span_with_size;
+use benchmark::tuning::profile;
+use benchmark::workspan::{measure, WorkSpan};
+
+// sorts input using scratch as additional memory and the given number of processors
+pub type SortFunction = fn(&mut [i64], &mut [i64], usize);
+// like SortFunction, with the size up to which parts of the input are sorted sequentially
+pub type CutoffSortFunction = fn(&mut [i64], &mut [i64], usize, usize);
+
+// after every merge the merged elements are copied back, so that scratch holds them as well
+fn copy_back(input: &[i64], scratch: &mut [i64]) {
+    scratch.copy_from_slice(input);
+    count(Counter::Writes, input.len() as u64);
+}
 
 pub fn sequential_merge_sort(input: &mut [i64], scratch: &mut [i64]) {
     let len = input.len();
@@ -14,76 +29,264 @@ pub fn sequential_merge_sort(input: &mut [i64], scratch: &mut [i64]) {
     sequential_merge_sort(right_input, right_scratch);
 
     sequential_merge(left_scratch, right_scratch, input);
-    scratch.copy_from_slice(input);
+    copy_back(input, scratch);
 }
 
 /** TASK 1 */
+// num_processors may be `AUTO_THREADS` to take the thread count and cutoff from the profile
 pub fn parallel_merge_sort<'input>(
     input: &'input mut [i64],
     scratch: &'input mut [i64],
     num_processors: usize,
+) {
+    let (num_processors, cutoff) = profile().resolve("PSSM", input.len(), num_processors, 1);
+    parallel_merge_sort_with_cutoff(input, scratch, num_processors, cutoff);
+}
+
+// like parallel_merge_sort, but parts of at most cutoff elements are sorted sequentially
+pub fn parallel_merge_sort_with_cutoff<'input>(
+    input: &'input mut [i64],
+    scratch: &'input mut [i64],
+    num_processors: usize,
+    cutoff: usize,
 ) {
     let len = input.len();
     if len == 1 {
         return;
     }
-    if num_processors < 2 {
+    let _span = span_with_size("sort", len);
+    if num_processors < 2 || len <= cutoff {
         sequential_merge_sort(input, scratch);
     } else {
         let mid = len / 2;
         let left_processors = num_processors / 2;
+        let right_processors = num_processors - left_processors;
 
         let (left_input, right_input) = input.split_at_mut(mid);
         let (left_scratch, right_scratch) = scratch.split_at_mut(mid);
 
         std::thread::scope(|scope| {
-            scope.spawn(|| parallel_merge_sort(left_input, left_scratch, left_processors));
-            parallel_merge_sort(right_input, right_scratch, num_processors - left_processors);
+            scope.spawn(|| {
+                parallel_merge_sort_with_cutoff(left_input, left_scratch, left_processors, cutoff)
+            });
+            parallel_merge_sort_with_cutoff(right_input, right_scratch, right_processors, cutoff);
         });
 
         // merge in sequence
+        let _merge = span_with_size("merge", len);
         sequential_merge(left_scratch, right_scratch, input);
 
-        scratch.copy_from_slice(input);
+        copy_back(input, scratch);
     }
 }
 
 /** TASK 3 */
+// num_processors may be `AUTO_THREADS` to take the thread count and cutoff from the profile
 pub fn fully_parallel_merge_sort<'input>(
     input: &'input mut [i64],
     scratch: &'input mut [i64],
     num_processors: usize,
+) {
+    let (num_processors, cutoff) = profile().resolve("PSPM", input.len(), num_processors, 1);
+    fully_parallel_merge_sort_with_cutoff(input, scratch, num_processors, cutoff);
+}
+
+// like fully_parallel_merge_sort, but parts of at most cutoff elements are sorted sequentially
+pub fn fully_parallel_merge_sort_with_cutoff<'input>(
+    input: &'input mut [i64],
+    scratch: &'input mut [i64],
+    num_processors: usize,
+    cutoff: usize,
 ) {
     let len = input.len();
     if len == 1 {
         return;
     }
-    if num_processors < 2 {
+    let _span = span_with_size("sort", len);
+    if num_processors < 2 || len <= cutoff {
         sequential_merge_sort(input, scratch);
     } else {
         let mid = len / 2;
         let left_processors = num_processors / 2;
+        let right_processors = num_processors - left_processors;
 
         let (left_input, right_input) = input.split_at_mut(mid);
         let (left_scratch, right_scratch) = scratch.split_at_mut(mid);
 
         std::thread::scope(|scope| {
-            scope.spawn(|| parallel_merge_sort(left_input, left_scratch, left_processors));
-            parallel_merge_sort(right_input, right_scratch, num_processors - left_processors);
+            scope.spawn(|| {
+                parallel_merge_sort_with_cutoff(left_input, left_scratch, left_processors, cutoff)
+            });
+            parallel_merge_sort_with_cutoff(right_input, right_scratch, right_processors, cutoff);
         });
 
         // merge in parallel
         parallel_merge(left_scratch, right_scratch, input, num_processors);
 
-        scratch.copy_from_slice(input);
+        copy_back(input, scratch);
     }
 }
 
+/**
+    Runs `parallel_merge_sort` on a virtual machine with the given number of processors. The
+    recursive calls are forked like in the real sort, but every task runs on the calling thread,
+    so the work and span only depend on the input. The input is sorted like by the real sort.
+*/
+pub fn simulate_parallel_merge_sort(
+    input: &mut [i64],
+    scratch: &mut [i64],
+    num_processors: usize,
+) -> WorkSpan {
+    let len = input.len();
+    if len == 1 {
+        return WorkSpan::default();
+    }
+    if num_processors < 2 {
+        return measure(|| sequential_merge_sort(input, scratch)).1;
+    }
+    let mid = len / 2;
+    let left_processors = num_processors / 2;
+
+    let (left_input, right_input) = input.split_at_mut(mid);
+    let (left_scratch, right_scratch) = scratch.split_at_mut(mid);
+
+    let halves = WorkSpan::parallel([
+        simulate_parallel_merge_sort(left_input, left_scratch, left_processors),
+        simulate_parallel_merge_sort(right_input, right_scratch, num_processors - left_processors),
+    ]);
+    let (_, merge) = measure(|| sequential_merge(left_scratch, right_scratch, input));
+    let (_, copy) = measure(|| copy_back(input, scratch));
+    halves.then(merge).then(copy)
+}
+
+// like `simulate_parallel_merge_sort`, but for `fully_parallel_merge_sort`
+pub fn simulate_fully_parallel_merge_sort(
+    input: &mut [i64],
+    scratch: &mut [i64],
+    num_processors: usize,
+) -> WorkSpan {
+    let len = input.len();
+    if len == 1 {
+        return WorkSpan::default();
+    }
+    if num_processors < 2 {
+        return measure(|| sequential_merge_sort(input, scratch)).1;
+    }
+    let mid = len / 2;
+    let left_processors = num_processors / 2;
+
+    let (left_input, right_input) = input.split_at_mut(mid);
+    let (left_scratch, right_scratch) = scratch.split_at_mut(mid);
+
+    let halves = WorkSpan::parallel([
+        simulate_parallel_merge_sort(left_input, left_scratch, left_processors),
+        simulate_parallel_merge_sort(right_input, right_scratch, num_processors - left_processors),
+    ]);
+    let merge = simulate_parallel_merge(left_scratch, right_scratch, input, num_processors);
+    let (_, copy) = measure(|| copy_back(input, scratch));
+    halves.then(merge).then(copy)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::merge_sort::{fully_parallel_merge_sort, parallel_merge_sort};
+    use crate::input_generation::shuffled_with_seed;
+    use crate::merge_sort::{
+        fully_parallel_merge_sort, fully_parallel_merge_sort_with_cutoff, parallel_merge_sort,
+        parallel_merge_sort_with_cutoff, simulate_fully_parallel_merge_sort,
+        simulate_parallel_merge_sort,
+    };
     use crate::utils::is_sorted;
     use crate::NUM_PROCESSORS_TEST;
+    use benchmark::trace::record_locally;
+    use benchmark::tuning::AUTO_THREADS;
+
+    #[test]
+    fn simulated_sorts_have_the_same_work_and_less_span() {
+        let simulate = |p| {
+            let mut input = shuffled_with_seed(10000, 1);
+            let mut scratch = input.clone();
+            let run = simulate_parallel_merge_sort(&mut input, &mut scratch, p);
+            assert!(is_sorted(input));
+            run
+        };
+        let sequential = simulate(1);
+        assert_eq!(sequential.work, sequential.span);
+        // the recursion splits the same way for every processor count
+        let parallel = simulate(8);
+        assert_eq!(parallel.work, sequential.work);
+        assert!(parallel.span < sequential.span / 3);
+
+        let mut input = shuffled_with_seed(10000, 1);
+        let mut scratch = input.clone();
+        let fully = simulate_fully_parallel_merge_sort(&mut input, &mut scratch, 8);
+        assert!(is_sorted(input));
+        // the parallel merge of the top level shortens the critical path further
+        assert!(fully.span < parallel.span);
+        assert!(fully.brent(8) < parallel.brent(8));
+    }
+
+    #[test]
+    fn traced_recursion_and_merges() {
+        let mut input: Vec<i64> = (0..1000).rev().collect();
+        let mut scratch: Vec<i64> = vec![0i64; 1000];
+        let (_, spans) = record_locally(|| fully_parallel_merge_sort(&mut input, &mut scratch, 4));
+        assert!(is_sorted(input));
+
+        let sizes = |name: &str| {
+            spans
+                .iter()
+                .filter(|s| s.name == name)
+                .map(|s| s.size.unwrap())
+                .collect::<Vec<usize>>()
+        };
+        // the calling thread sorts the right halves itself and spawns a thread for each left one,
+        // only the top level merges in parallel
+        assert_eq!(sizes("sort"), vec![1000, 500, 250]);
+        assert_eq!(sizes("merge"), vec![500]);
+        assert_eq!(sizes("parallel merge"), vec![1000]);
+        // and merges the last chunk of each parallel merge
+        let top = spans
+            .iter()
+            .find(|s| s.name == "parallel merge" && s.size == Some(1000))
+            .unwrap();
+        let inside = spans
+            .iter()
+            .filter(|s| s.name == "leaf merge" && s.start >= top.start)
+            .filter(|s| s.start + s.duration <= top.start + top.duration)
+            .count();
+        assert_eq!(inside, 1);
+    }
+
+    #[test]
+    fn sequential_below_cutoff() {
+        for sort in [
+            parallel_merge_sort_with_cutoff,
+            fully_parallel_merge_sort_with_cutoff,
+        ] {
+            for cutoff in [1, 100, 1000] {
+                let mut input = shuffled_with_seed(1000, 2);
+                let mut scratch = input.clone();
+                let (_, spans) = record_locally(|| sort(&mut input, &mut scratch, 4, cutoff));
+                assert!(is_sorted(input));
+                // the halves are only sorted in parallel above the cutoff
+                let halves = spans
+                    .iter()
+                    .filter(|s| s.name == "sort" && s.size == Some(500))
+                    .count();
+                assert_eq!(halves, if cutoff < 1000 { 1 } else { 0 });
+            }
+        }
+    }
+
+    #[test]
+    fn auto_threads() {
+        for sort in [parallel_merge_sort, fully_parallel_merge_sort] {
+            let mut input = shuffled_with_seed(3000, 3);
+            let mut scratch = input.clone();
+            sort(&mut input, &mut scratch, AUTO_THREADS);
+            assert!(is_sorted(input));
+        }
+    }
 
     // Test parallel merge sort (using sequential merge)
     #[test]
@@ -91,7 +294,7 @@ mod tests {
         let mut input: Vec<i64> = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
         let mut scratch: Vec<i64> = vec![0i64; 9];
         parallel_merge_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
-        assert_eq!(is_sorted(scratch), true);
+        assert!(is_sorted(scratch));
     }
 
     #[test]
@@ -99,7 +302,7 @@ mod tests {
         let mut input: Vec<i64> = vec![9, 8, 7, 6, 5, 4, 3, 2, 1];
         let mut scratch: Vec<i64> = vec![0i64; 9];
         parallel_merge_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
-        assert_eq!(is_sorted(scratch), true);
+        assert!(is_sorted(scratch));
     }
 
     #[test]
@@ -107,7 +310,7 @@ mod tests {
         let mut input: Vec<i64> = vec![5, 7, 2, 9, 1, 3, 8, 4, 6];
         let mut scratch: Vec<i64> = vec![0i64; 9];
         parallel_merge_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
-        assert_eq!(is_sorted(scratch), true);
+        assert!(is_sorted(scratch));
     }
 
     // Test parallel merge sort (using parallel merge)
@@ -116,7 +319,7 @@ mod tests {
         let mut input: Vec<i64> = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
         let mut scratch: Vec<i64> = vec![0i64; 9];
         fully_parallel_merge_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
-        assert_eq!(is_sorted(scratch), true);
+        assert!(is_sorted(scratch));
     }
 
     #[test]
@@ -124,7 +327,7 @@ mod tests {
         let mut input: Vec<i64> = vec![9, 8, 7, 6, 5, 4, 3, 2, 1];
         let mut scratch: Vec<i64> = vec![0i64; 9];
         fully_parallel_merge_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
-        assert_eq!(is_sorted(scratch), true);
+        assert!(is_sorted(scratch));
     }
 
     #[test]
@@ -132,6 +335,6 @@ mod tests {
         let mut input: Vec<i64> = vec![5, 7, 2, 9, 1, 3, 8, 4, 6];
         let mut scratch: Vec<i64> = vec![0i64; 9];
         fully_parallel_merge_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
-        assert_eq!(is_sorted(scratch), true);
+        assert!(is_sorted(scratch));
     }
 }
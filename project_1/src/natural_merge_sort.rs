@@ -0,0 +1,360 @@
+use crate::merge::{galloping_merge, parallel_merge};
+use crate::utils::exponential_search;
+use benchmark::tuning::profile;
+use std::cmp::min;
+
+// runs shorter than this are extended with insertion sort before any merging happens
+const MIN_RUN: usize = 32;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Run {
+    pub start: usize,
+    pub len: usize,
+}
+
+impl Run {
+    fn end(&self) -> usize {
+        self.start + self.len
+    }
+}
+
+/**
+    Returns the end of the run starting at `start`. A run is either non-decreasing or strictly
+    decreasing, strictly decreasing runs are reversed in place (strict so reversing keeps equal
+    elements in order).
+*/
+fn find_run(input: &mut [i64], start: usize) -> usize {
+    let mut end = start + 1;
+    if end == input.len() {
+        return end;
+    }
+
+    if input[end] < input[start] {
+        while end < input.len() && input[end] < input[end - 1] {
+            end += 1;
+        }
+        input[start..end].reverse();
+    } else {
+        while end < input.len() && input[end] >= input[end - 1] {
+            end += 1;
+        }
+    }
+    end
+}
+
+// sorts input assuming that input[..sorted] is already sorted
+fn insertion_sort(input: &mut [i64], sorted: usize) {
+    for i in sorted.max(1)..input.len() {
+        let key = input[i];
+        let mut j = i;
+        while j > 0 && input[j - 1] > key {
+            input[j] = input[j - 1];
+            j -= 1;
+        }
+        input[j] = key;
+    }
+}
+
+/**
+    Splits the input into ascending runs of at least MIN_RUN elements (except for the last one).
+    Descending runs are reversed and short runs are extended with insertion sort.
+*/
+pub fn collect_runs(input: &mut [i64]) -> Vec<Run> {
+    let mut runs = vec![];
+    let mut start = 0;
+
+    while start < input.len() {
+        let mut end = find_run(input, start);
+        if end - start < MIN_RUN {
+            let forced_end = min(start + MIN_RUN, input.len());
+            insertion_sort(&mut input[start..forced_end], end - start);
+            end = forced_end;
+        }
+        runs.push(Run {
+            start,
+            len: end - start,
+        });
+        start = end;
+    }
+
+    runs
+}
+
+/**
+    Powersort node power of the boundary between two adjacent runs in an input of length n: the
+    depth at which the boundary would be placed in a perfectly balanced merge tree.
+*/
+fn node_power(n: usize, left: Run, right: Run) -> u32 {
+    // twice the midpoints of the runs, relative to 2n
+    let two_n = 2 * n as u64;
+    let mut a = (2 * left.start + left.len) as u64;
+    let mut b = (2 * right.start + right.len) as u64;
+
+    let mut power = 0;
+    loop {
+        power += 1;
+        a *= 2;
+        b *= 2;
+        let (a_bit, b_bit) = (a >= two_n, b >= two_n);
+        if a_bit != b_bit {
+            return power;
+        }
+        if a_bit {
+            a -= two_n;
+            b -= two_n;
+        }
+    }
+}
+
+/**
+    Merges the two adjacent sorted runs input[..mid] and input[mid..]. Elements of the left run
+    smaller than the first element of the right run, and elements of the right run not smaller
+    than the last element of the left run, are already in place and are skipped using exponential
+    search. The rest is merged with the galloping merge, which makes lopsided merges cheap.
+*/
+pub fn merge_adjacent_runs(input: &mut [i64], scratch: &mut [i64], mid: usize) {
+    let (left, right) = input.split_at(mid);
+    if left.is_empty() || right.is_empty() {
+        return;
+    }
+
+    let skip = exponential_search(left, right[0]);
+    if skip == left.len() {
+        // the runs are already in order
+        return;
+    }
+    let keep = exponential_search(right, left[left.len() - 1]);
+
+    let merge_len = mid - skip + keep;
+    let output = &mut scratch[..merge_len];
+    galloping_merge(&left[skip..], &right[..keep], output);
+    input[skip..skip + merge_len].copy_from_slice(output);
+}
+
+// merges the given runs (with start positions relative to input) using the powersort merge policy
+fn powersort_merge(input: &mut [i64], scratch: &mut [i64], runs: &[Run]) {
+    let n = input.len();
+    let mut stack: Vec<(Run, u32)> = Vec::new();
+    let mut current = runs[0];
+
+    for &next in &runs[1..] {
+        let power = node_power(n, current, next);
+        while let Some(&(top, top_power)) = stack.last() {
+            if top_power <= power {
+                break;
+            }
+            stack.pop();
+            merge_adjacent_runs(&mut input[top.start..current.end()], scratch, top.len);
+            current = Run {
+                start: top.start,
+                len: top.len + current.len,
+            };
+        }
+        stack.push((current, power));
+        current = next;
+    }
+
+    while let Some((top, _)) = stack.pop() {
+        merge_adjacent_runs(&mut input[top.start..current.end()], scratch, top.len);
+        current = Run {
+            start: top.start,
+            len: top.len + current.len,
+        };
+    }
+}
+
+pub fn natural_merge_sort(input: &mut [i64], scratch: &mut [i64]) {
+    if input.len() < 2 {
+        scratch.copy_from_slice(input);
+        return;
+    }
+
+    let runs = collect_runs(input);
+    powersort_merge(input, scratch, &runs);
+
+    scratch.copy_from_slice(input);
+}
+
+/**
+    Recursively splits the runs at the run boundary closest to the middle, sorts both halves in
+    parallel and merges them with the parallel merge. Run start positions are absolute, `offset`
+    is the position of input[0] in the full input.
+*/
+fn parallel_merge_runs(
+    input: &mut [i64],
+    scratch: &mut [i64],
+    runs: &[Run],
+    offset: usize,
+    num_processors: usize,
+) {
+    if runs.len() < 2 {
+        return;
+    }
+    if num_processors < 2 {
+        let relative_runs: Vec<Run> = runs
+            .iter()
+            .map(|run| Run {
+                start: run.start - offset,
+                len: run.len,
+            })
+            .collect();
+        powersort_merge(input, scratch, &relative_runs);
+        return;
+    }
+
+    // pick the run boundary closest to the middle of the input
+    let half = offset + input.len() / 2;
+    let split = (1..runs.len())
+        .min_by_key(|&i| runs[i].start.abs_diff(half))
+        .unwrap();
+    let mid = runs[split].start - offset;
+    let left_processors = num_processors / 2;
+
+    let (left_input, right_input) = input.split_at_mut(mid);
+    let (left_scratch, right_scratch) = scratch.split_at_mut(mid);
+    let (left_runs, right_runs) = runs.split_at(split);
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            parallel_merge_runs(left_input, left_scratch, left_runs, offset, left_processors)
+        });
+        parallel_merge_runs(
+            right_input,
+            right_scratch,
+            right_runs,
+            offset + mid,
+            num_processors - left_processors,
+        );
+    });
+
+    let (left, right) = input.split_at(mid);
+    parallel_merge(left, right, scratch, num_processors);
+    input.copy_from_slice(scratch);
+}
+
+// num_processors may be `AUTO_THREADS`
+pub fn parallel_natural_merge_sort(input: &mut [i64], scratch: &mut [i64], num_processors: usize) {
+    let (num_processors, _) = profile().resolve("NPPM", input.len(), num_processors, 1);
+    if input.len() < 2 {
+        scratch.copy_from_slice(input);
+        return;
+    }
+
+    // run detection is a single linear scan, the merging of the runs is done in parallel
+    let runs = collect_runs(input);
+    parallel_merge_runs(input, scratch, &runs, 0, num_processors);
+
+    scratch.copy_from_slice(input);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input_generation::{
+        left_fits_between_last_two_elements_in_right, reverse_sorted, shuffled,
+    };
+    use crate::natural_merge_sort::{
+        collect_runs, merge_adjacent_runs, natural_merge_sort, parallel_natural_merge_sort, Run,
+    };
+    use crate::NUM_PROCESSORS_TEST;
+
+    fn sorted_copy(input: &[i64]) -> Vec<i64> {
+        let mut expected = input.to_vec();
+        expected.sort();
+        expected
+    }
+
+    #[test]
+    fn collect_runs_reverses_descending_runs() {
+        let mut input: Vec<i64> = (0..100).rev().collect();
+        let runs = collect_runs(&mut input);
+        assert_eq!(runs, vec![Run { start: 0, len: 100 }]);
+        assert_eq!(input, (0..100).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn collect_runs_extends_short_runs() {
+        let mut input: Vec<i64> = shuffled(100);
+        let runs = collect_runs(&mut input);
+        assert!(runs
+            .iter()
+            .all(|run| run.len >= 32 || run.start + run.len == 100));
+        for run in runs {
+            assert!(input[run.start..run.start + run.len].is_sorted());
+        }
+    }
+
+    #[test]
+    fn merge_adjacent_runs_lopsided() {
+        let (left, right) = left_fits_between_last_two_elements_in_right(101);
+        let mid = left.len();
+        let mut input = left;
+        input.extend(right);
+        let mut scratch = vec![0i64; input.len()];
+        let expected = sorted_copy(&input);
+        merge_adjacent_runs(&mut input, &mut scratch, mid);
+        assert_eq!(input, expected);
+    }
+
+    #[test]
+    fn natural_merge_sort_sorted() {
+        let mut input: Vec<i64> = (0..1000).collect();
+        let mut scratch: Vec<i64> = vec![0i64; 1000];
+        natural_merge_sort(&mut input, &mut scratch);
+        assert_eq!(scratch, (0..1000).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn natural_merge_sort_reversed() {
+        let mut input: Vec<i64> = reverse_sorted(1000);
+        let mut scratch: Vec<i64> = vec![0i64; 1000];
+        natural_merge_sort(&mut input, &mut scratch);
+        assert_eq!(scratch, (0..1000).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn natural_merge_sort_random_with_duplicates() {
+        let mut input: Vec<i64> = shuffled(5000).iter().map(|x| x % 97).collect();
+        let mut scratch: Vec<i64> = vec![0i64; 5000];
+        let expected = sorted_copy(&input);
+        natural_merge_sort(&mut input, &mut scratch);
+        assert_eq!(scratch, expected);
+    }
+
+    #[test]
+    fn natural_merge_sort_sawtooth() {
+        // alternating ascending and descending runs of different lengths
+        let mut input: Vec<i64> = vec![];
+        for i in 0..20 {
+            let run = (0..(i * 13 % 70)).map(|x| x * (i - 10));
+            input.extend(run);
+        }
+        let mut scratch: Vec<i64> = vec![0i64; input.len()];
+        let expected = sorted_copy(&input);
+        natural_merge_sort(&mut input, &mut scratch);
+        assert_eq!(scratch, expected);
+    }
+
+    #[test]
+    fn parallel_natural_merge_sort_random() {
+        let mut input: Vec<i64> = shuffled(5000);
+        let mut scratch: Vec<i64> = vec![0i64; 5000];
+        parallel_natural_merge_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
+        assert_eq!(scratch, (0..5000).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn parallel_natural_merge_sort_reversed() {
+        let mut input: Vec<i64> = reverse_sorted(5000);
+        let mut scratch: Vec<i64> = vec![0i64; 5000];
+        parallel_natural_merge_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
+        assert_eq!(scratch, (0..5000).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn parallel_natural_merge_sort_single_element() {
+        let mut input: Vec<i64> = vec![7];
+        let mut scratch: Vec<i64> = vec![0];
+        parallel_natural_merge_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
+        assert_eq!(scratch, vec![7]);
+    }
+}
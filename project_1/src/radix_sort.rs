@@ -0,0 +1,152 @@
+use benchmark::tuning::profile;
+use std::iter::zip;
+
+const RADIX_BITS: usize = 8;
+const RADIX: usize = 1 << RADIX_BITS;
+const PASSES: usize = 64 / RADIX_BITS;
+
+// flipping the sign bit maps i64 to u64 such that the unsigned order equals the signed order
+fn digit(x: i64, pass: usize) -> usize {
+    let key = (x as u64) ^ (1 << 63);
+    ((key >> (pass * RADIX_BITS)) as usize) & (RADIX - 1)
+}
+
+/**
+    One stable counting sort pass over `source` into `destination` on the digit of the given pass.
+    Every thread counts the digits of its block, the destination is then split into one slice per
+    (digit, thread) pair so that every thread can scatter its block without synchronisation.
+    Returns false (and writes nothing) if all elements have the same digit.
+*/
+fn radix_pass(source: &[i64], destination: &mut [i64], pass: usize, num_processors: usize) -> bool {
+    let block_size = source.len().div_ceil(num_processors);
+    let blocks: Vec<&[i64]> = source.chunks(block_size).collect();
+
+    let histograms: Vec<[usize; RADIX]> = std::thread::scope(|scope| {
+        let handles: Vec<_> = blocks
+            .iter()
+            .map(|block| {
+                scope.spawn(move || {
+                    let mut histogram = [0usize; RADIX];
+                    for &x in block.iter() {
+                        histogram[digit(x, pass)] += 1;
+                    }
+                    histogram
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    // skip passes that would not move anything
+    let first_digit = digit(source[0], pass);
+    if histograms
+        .iter()
+        .map(|histogram| histogram[first_digit])
+        .sum::<usize>()
+        == source.len()
+    {
+        return false;
+    }
+
+    // slices ordered by digit first and thread second keep the pass stable
+    let mut thread_slices: Vec<Vec<&mut [i64]>> = (0..blocks.len())
+        .map(|_| Vec::with_capacity(RADIX))
+        .collect();
+    let mut rest = destination;
+    for d in 0..RADIX {
+        for (t, histogram) in histograms.iter().enumerate() {
+            let (slice, remaining) = rest.split_at_mut(histogram[d]);
+            rest = remaining;
+            thread_slices[t].push(slice);
+        }
+    }
+
+    std::thread::scope(|scope| {
+        for (block, mut slices) in zip(blocks, thread_slices) {
+            scope.spawn(move || {
+                let mut cursors = [0usize; RADIX];
+                for &x in block {
+                    let d = digit(x, pass);
+                    slices[d][cursors[d]] = x;
+                    cursors[d] += 1;
+                }
+            });
+        }
+    });
+
+    true
+}
+
+/**
+    Parallel least significant digit radix sort on 8 bit digits. Passes in which all keys share the
+    same digit are skipped, so small key ranges need fewer than 8 passes. The result is in both
+    input and scratch like for the merge sorts. num_processors may be `AUTO_THREADS`.
+*/
+pub fn parallel_radix_sort(input: &mut [i64], scratch: &mut [i64], num_processors: usize) {
+    if input.is_empty() {
+        return;
+    }
+    let (num_processors, _) = profile().resolve("PLSD", input.len(), num_processors, 1);
+    let num_processors = num_processors.max(1);
+
+    // the sorted data alternates between input and scratch
+    let mut in_scratch = false;
+    for pass in 0..PASSES {
+        let moved = if in_scratch {
+            radix_pass(scratch, input, pass, num_processors)
+        } else {
+            radix_pass(input, scratch, pass, num_processors)
+        };
+        in_scratch ^= moved;
+    }
+
+    if in_scratch {
+        input.copy_from_slice(scratch);
+    } else {
+        scratch.copy_from_slice(input);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input_generation::{gen_input_with_seed, reverse_sorted, shuffled};
+    use crate::radix_sort::parallel_radix_sort;
+    use crate::NUM_PROCESSORS_TEST;
+
+    #[test]
+    fn radix_sort_random() {
+        let mut input: Vec<i64> = shuffled(10000);
+        let mut scratch: Vec<i64> = vec![0i64; 10000];
+        parallel_radix_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
+        assert_eq!(input, (0..10000).collect::<Vec<i64>>());
+        assert_eq!(scratch, input);
+    }
+
+    #[test]
+    fn radix_sort_reversed() {
+        let mut input: Vec<i64> = reverse_sorted(10000);
+        let mut scratch: Vec<i64> = vec![0i64; 10000];
+        parallel_radix_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
+        assert_eq!(input, (0..10000).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn radix_sort_negative_and_extreme_values() {
+        let mut input: Vec<i64> = gen_input_with_seed(5000, 1);
+        input.extend([i64::MIN, i64::MAX, 0, -1, i64::MIN, i64::MAX]);
+        let mut expected = input.clone();
+        expected.sort();
+        let mut scratch: Vec<i64> = vec![0i64; input.len()];
+        parallel_radix_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
+        assert_eq!(input, expected);
+    }
+
+    #[test]
+    fn radix_sort_all_equal() {
+        let mut input: Vec<i64> = vec![42; 100];
+        let mut scratch: Vec<i64> = vec![0i64; 100];
+        parallel_radix_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
+        assert_eq!(input, vec![42; 100]);
+        assert_eq!(scratch, vec![42; 100]);
+    }
+}
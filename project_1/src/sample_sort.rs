@@ -0,0 +1,146 @@
+use crate::merge_sort::sequential_merge_sort;
+use benchmark::tuning::profile;
+use std::iter::zip;
+use turborand::prelude::*;
+
+// number of samples drawn per bucket to choose the splitters
+const OVERSAMPLING: usize = 16;
+
+// below this many elements per processor the sample sort falls back to sequential merge sort
+const MIN_ELEMENTS_PER_PROCESSOR: usize = 4 * OVERSAMPLING;
+
+// sorts input, the result is in both input and scratch like for the merge sorts
+fn sequential_sort(input: &mut [i64], scratch: &mut [i64]) {
+    // the merge sort expects scratch to start out as a copy of the input
+    scratch.copy_from_slice(input);
+    if input.len() > 1 {
+        sequential_merge_sort(input, scratch);
+    }
+}
+
+/**
+    Draws num_buckets * OVERSAMPLING random elements, sorts them and returns every OVERSAMPLING-th
+    one as the num_buckets - 1 splitters between the buckets.
+*/
+fn choose_splitters(input: &[i64], num_buckets: usize) -> Vec<i64> {
+    let rand = Rng::new();
+    let mut sample: Vec<i64> = (0..num_buckets * OVERSAMPLING)
+        .map(|_| input[rand.usize(0..input.len())])
+        .collect();
+    sample.sort_unstable();
+
+    (1..num_buckets).map(|i| sample[i * OVERSAMPLING]).collect()
+}
+
+/**
+    Parallel sample sort: every thread distributes its block of the input into buckets given by
+    randomly sampled splitters, then every bucket is gathered from all threads and sorted by its
+    own thread. num_processors may be `AUTO_THREADS`.
+*/
+pub fn parallel_sample_sort(input: &mut [i64], scratch: &mut [i64], num_processors: usize) {
+    let n = input.len();
+    let (num_processors, _) = profile().resolve("PSAS", n, num_processors, 1);
+    if num_processors < 2 || n < num_processors * MIN_ELEMENTS_PER_PROCESSOR {
+        sequential_sort(input, scratch);
+        return;
+    }
+
+    let splitters = choose_splitters(input, num_processors);
+    let block_size = n.div_ceil(num_processors);
+
+    // each thread puts the elements of its block into num_processors local buckets
+    let local_buckets: Vec<Vec<Vec<i64>>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = input
+            .chunks(block_size)
+            .map(|block| {
+                let splitters = &splitters;
+                scope.spawn(move || {
+                    let mut buckets = vec![Vec::new(); splitters.len() + 1];
+                    for &x in block {
+                        // elements equal to a splitter go into the bucket left of it
+                        buckets[splitters.partition_point(|s| *s < x)].push(x);
+                    }
+                    buckets
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    // split input and scratch into the regions of the buckets
+    let bucket_sizes: Vec<usize> = (0..num_processors)
+        .map(|b| local_buckets.iter().map(|buckets| buckets[b].len()).sum())
+        .collect();
+    let mut input_regions = Vec::with_capacity(num_processors);
+    let mut scratch_regions = Vec::with_capacity(num_processors);
+    let (mut input_rest, mut scratch_rest) = (input, scratch);
+    for size in bucket_sizes {
+        let (input_region, rest) = input_rest.split_at_mut(size);
+        input_rest = rest;
+        input_regions.push(input_region);
+        let (scratch_region, rest) = scratch_rest.split_at_mut(size);
+        scratch_rest = rest;
+        scratch_regions.push(scratch_region);
+    }
+
+    // gather every bucket from the threads in order and sort it
+    std::thread::scope(|scope| {
+        for (b, (input_region, scratch_region)) in zip(input_regions, scratch_regions).enumerate() {
+            let local_buckets = &local_buckets;
+            scope.spawn(move || {
+                let mut position = 0;
+                for buckets in local_buckets {
+                    let bucket = &buckets[b];
+                    input_region[position..position + bucket.len()].copy_from_slice(bucket);
+                    position += bucket.len();
+                }
+                sequential_sort(input_region, scratch_region);
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input_generation::{gen_input_with_seed, reverse_sorted, shuffled};
+    use crate::sample_sort::parallel_sample_sort;
+    use crate::NUM_PROCESSORS_TEST;
+
+    #[test]
+    fn sample_sort_random() {
+        let mut input: Vec<i64> = shuffled(10000);
+        let mut scratch: Vec<i64> = vec![0i64; 10000];
+        parallel_sample_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
+        assert_eq!(input, (0..10000).collect::<Vec<i64>>());
+        assert_eq!(scratch, input);
+    }
+
+    #[test]
+    fn sample_sort_reversed() {
+        let mut input: Vec<i64> = reverse_sorted(10000);
+        let mut scratch: Vec<i64> = vec![0i64; 10000];
+        parallel_sample_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
+        assert_eq!(input, (0..10000).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn sample_sort_many_duplicates() {
+        let mut input: Vec<i64> = gen_input_with_seed(10000, 1)
+            .iter()
+            .map(|x| x % 3)
+            .collect();
+        let mut expected = input.clone();
+        expected.sort();
+        let mut scratch: Vec<i64> = vec![0i64; 10000];
+        parallel_sample_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
+        assert_eq!(input, expected);
+    }
+
+    #[test]
+    fn sample_sort_small_input() {
+        let mut input: Vec<i64> = vec![3, 1, 2];
+        let mut scratch: Vec<i64> = vec![0i64; 3];
+        parallel_sample_sort(&mut input, &mut scratch, NUM_PROCESSORS_TEST);
+        assert_eq!(input, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,288 @@
+use crate::merge_sort::fully_parallel_merge_sort;
+use benchmark::trace::span_with_size;
+use benchmark::tuning::profile;
+use turborand::prelude::*;
+
+// below this many candidates the selection finishes sequentially
+const SEQUENTIAL_SELECTION: usize = 4096;
+
+// the number of elements of each of the blocks of input the threads work on
+fn block_size(n: usize, num_processors: usize) -> usize {
+    n.div_ceil(num_processors.max(1)).max(1)
+}
+
+// runs f on every block of input on its own thread and returns the results in order of the blocks
+fn map_blocks<R: Send>(
+    input: &[i64],
+    num_processors: usize,
+    f: impl Fn(&[i64]) -> R + Sync,
+) -> Vec<R> {
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = input
+            .chunks(block_size(input.len(), num_processors))
+            .map(|block| {
+                let f = &f;
+                scope.spawn(move || f(block))
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    })
+}
+
+/**
+    Two values of a random sample of about sqrt(len) elements, chosen so that the value at
+    position n most likely lies between them while only few elements do.
+*/
+fn choose_pivots(input: &[i64], n: usize) -> (i64, i64) {
+    let rand = Rng::new();
+    let size = (input.len() as f64).sqrt() as usize;
+    let mut sample: Vec<i64> = (0..size)
+        .map(|_| input[rand.usize(0..input.len())])
+        .collect();
+    sample.sort_unstable();
+
+    let rank = n * size / input.len();
+    let gap = (size as f64).sqrt() as usize + 1;
+    (
+        sample[rank.saturating_sub(gap)],
+        sample[(rank + gap).min(size - 1)],
+    )
+}
+
+/**
+    The value at position n of the sorted input. The threads count the elements below and between
+    two sampled pivots, and only the elements of the part that holds position n are gathered for
+    the next round, until few enough are left to select sequentially.
+*/
+fn nth_value(input: &[i64], n: usize, num_processors: usize) -> i64 {
+    if num_processors < 2 || input.len() <= SEQUENTIAL_SELECTION {
+        let mut candidates = input.to_vec();
+        return *candidates.select_nth_unstable(n).1;
+    }
+    let _span = span_with_size("select", input.len());
+
+    let (low, high) = choose_pivots(input, n);
+    let counts = map_blocks(input, num_processors, |block| {
+        let below = block.iter().filter(|x| **x < low).count();
+        let between = block.iter().filter(|x| low <= **x && **x <= high).count();
+        (below, between)
+    });
+    let below: usize = counts.iter().map(|(below, _)| below).sum();
+    let between: usize = counts.iter().map(|(_, between)| between).sum();
+
+    let (keep, n): (&(dyn Fn(i64) -> bool + Sync), usize) = if n < below {
+        (&|x| x < low, n)
+    } else if n < below + between {
+        if low == high {
+            return low;
+        }
+        (&|x| low <= x && x <= high, n - below)
+    } else {
+        (&|x| x > high, n - below - between)
+    };
+    let candidates = map_blocks(input, num_processors, |block| {
+        block
+            .iter()
+            .copied()
+            .filter(|x| keep(*x))
+            .collect::<Vec<i64>>()
+    })
+    .concat();
+
+    if candidates.len() == input.len() {
+        // the pivots did not exclude anything, e.g. if there are only a few distinct values
+        return nth_value(input, n, 1);
+    }
+    nth_value(&candidates, n, num_processors)
+}
+
+/**
+    Reorders input into the elements smaller than, equal to and larger than value. Every thread
+    counts the smaller and equal elements of its block and copies its block to the offsets of the
+    groups after those of the blocks before it.
+*/
+fn partition_around(input: &mut [i64], value: i64, num_processors: usize) {
+    let counts = map_blocks(input, num_processors, |block| {
+        let smaller = block.iter().filter(|x| **x < value).count();
+        let equal = block.iter().filter(|x| **x == value).count();
+        (smaller, block.len() - smaller - equal)
+    });
+    let smaller: usize = counts.iter().map(|(smaller, _)| smaller).sum();
+    let larger: usize = counts.iter().map(|(_, larger)| larger).sum();
+
+    let block_size = block_size(input.len(), num_processors);
+    let mut scratch = vec![value; input.len()];
+    let larger_start = input.len() - larger;
+    let (mut smaller_rest, rest) = scratch.split_at_mut(smaller);
+    let (_, mut larger_rest) = rest.split_at_mut(larger_start - smaller);
+    std::thread::scope(|scope| {
+        let (mut smaller_part, mut larger_part);
+        for (block, (smaller, larger)) in input.chunks(block_size).zip(counts) {
+            (smaller_part, smaller_rest) = smaller_rest.split_at_mut(smaller);
+            (larger_part, larger_rest) = larger_rest.split_at_mut(larger);
+            scope.spawn(move || {
+                let (mut i, mut j) = (0, 0);
+                for &x in block {
+                    if x < value {
+                        smaller_part[i] = x;
+                        i += 1;
+                    } else if x > value {
+                        larger_part[j] = x;
+                        j += 1;
+                    }
+                }
+            });
+        }
+    });
+
+    std::thread::scope(|scope| {
+        for (block, part) in input.chunks_mut(block_size).zip(scratch.chunks(block_size)) {
+            scope.spawn(move || block.copy_from_slice(part));
+        }
+    });
+}
+
+// sorts input with the fully parallel merge sort
+fn sort(input: &mut [i64], num_processors: usize) {
+    if input.len() > 1 {
+        let mut scratch = input.to_vec();
+        fully_parallel_merge_sort(input, &mut scratch, num_processors);
+    }
+}
+
+/**
+    Reorders input so that the element at position n is the one that would be there if input was
+    sorted, with all smaller elements before it and all larger ones after it, and returns it.
+    threads may be `AUTO_THREADS`.
+*/
+pub fn select_nth(input: &mut [i64], n: usize, threads: usize) -> i64 {
+    assert!(
+        n < input.len(),
+        "position {} is out of bounds for {} elements",
+        n,
+        input.len()
+    );
+    let (threads, _) = profile().resolve("select_nth", input.len(), threads, 1);
+    if threads < 2 {
+        // the standard library selects in place without the copies of the parallel version
+        return *input.select_nth_unstable(n).1;
+    }
+    let value = nth_value(input, n, threads);
+    partition_around(input, value, threads);
+    value
+}
+
+/**
+    The k smallest elements of input in ascending order (all of them if there are fewer than k).
+    Only the elements below the k-th smallest value are sorted, the copies of that value are
+    appended as often as they fit.
+*/
+pub fn top_k(input: &[i64], k: usize, threads: usize) -> Vec<i64> {
+    let k = k.min(input.len());
+    if k == 0 {
+        return vec![];
+    }
+    let (threads, _) = profile().resolve("top_k", input.len(), threads, 1);
+    let value = nth_value(input, k - 1, threads);
+    let mut smallest = map_blocks(input, threads, |block| {
+        block
+            .iter()
+            .copied()
+            .filter(|x| *x < value)
+            .collect::<Vec<i64>>()
+    })
+    .concat();
+    sort(&mut smallest, threads);
+    smallest.resize(k, value);
+    smallest
+}
+
+// sorts the first k positions of input and leaves the other elements in any order after them
+pub fn partial_sort(input: &mut [i64], k: usize, threads: usize) {
+    let k = k.min(input.len());
+    if k == 0 {
+        return;
+    }
+    let (threads, _) = profile().resolve("partial_sort", input.len(), threads, 1);
+    if threads < 2 {
+        input.select_nth_unstable(k - 1);
+        input[..k].sort_unstable();
+        return;
+    }
+    let value = nth_value(input, k - 1, threads);
+    partition_around(input, value, threads);
+    sort(&mut input[..k], threads);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input_generation::{gen_input_with_seed, reverse_sorted, shuffled_with_seed};
+    use crate::selection::{partial_sort, select_nth, top_k};
+    use crate::NUM_PROCESSORS_TEST;
+    use benchmark::tuning::AUTO_THREADS;
+
+    fn inputs() -> Vec<Vec<i64>> {
+        vec![
+            vec![5],
+            vec![2, 1, 2],
+            shuffled_with_seed(3000, 1),
+            shuffled_with_seed(20000, 2),
+            reverse_sorted(20000),
+            gen_input_with_seed(20000, 3),
+            // few distinct values, so that the pivots often cannot exclude anything
+            gen_input_with_seed(20000, 4)
+                .iter()
+                .map(|x| x % 3)
+                .collect(),
+            vec![7; 20000],
+        ]
+    }
+
+    #[test]
+    fn selects_like_sorting() {
+        for input in inputs() {
+            let mut sorted = input.clone();
+            sorted.sort();
+            for threads in [1, 2, 3, NUM_PROCESSORS_TEST, AUTO_THREADS] {
+                for n in [0, input.len() / 3, input.len() / 2, input.len() - 1] {
+                    let mut output = input.clone();
+                    let value = select_nth(&mut output, n, threads);
+                    let message = format!("n={} of {} with {} threads", n, input.len(), threads);
+                    assert_eq!(value, sorted[n], "{}", message);
+                    assert_eq!(output[n], value, "{}", message);
+                    assert!(output[..n].iter().all(|x| *x <= value), "{}", message);
+                    assert!(output[n..].iter().all(|x| *x >= value), "{}", message);
+                    output.sort();
+                    assert_eq!(output, sorted, "{}", message);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn top_k_and_partial_sort_like_sorting() {
+        for input in inputs() {
+            let mut sorted = input.clone();
+            sorted.sort();
+            for threads in [1, 3, NUM_PROCESSORS_TEST] {
+                for k in [0, 1, 100, input.len() / 2, input.len(), input.len() + 5] {
+                    let k_sorted = &sorted[..k.min(input.len())];
+                    let message = format!("k={} of {} with {} threads", k, input.len(), threads);
+                    assert_eq!(top_k(&input, k, threads), k_sorted, "{}", message);
+
+                    let mut output = input.clone();
+                    partial_sort(&mut output, k, threads);
+                    assert_eq!(&output[..k.min(input.len())], k_sorted, "{}", message);
+                    output.sort();
+                    assert_eq!(output, sorted, "{}", message);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn select_out_of_bounds() {
+        select_nth(&mut [1, 2, 3], 3, 2);
+    }
+}
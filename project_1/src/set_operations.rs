@@ -0,0 +1,322 @@
+use crate::merge::{chunks, ranks};
+use crate::utils::Bound;
+use benchmark::counters::{count, Counter};
+use benchmark::trace::span_with_size;
+use benchmark::tuning::profile;
+use std::ops::Range;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SetOperation {
+    Union,
+    Intersection,
+    Difference,
+    SymmetricDifference,
+}
+
+// whether values that appear several times in an input are kept as often or only once
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Semantics {
+    Set,
+    Multiset,
+}
+
+impl SetOperation {
+    pub const ALL: [SetOperation; 4] = [
+        SetOperation::Union,
+        SetOperation::Intersection,
+        SetOperation::Difference,
+        SetOperation::SymmetricDifference,
+    ];
+
+    // how often a value that is l times in left and r times in right is in the result
+    fn multiplicity(&self, semantics: Semantics, l: usize, r: usize) -> usize {
+        let (l, r) = match semantics {
+            Semantics::Set => (l.min(1), r.min(1)),
+            Semantics::Multiset => (l, r),
+        };
+        match self {
+            SetOperation::Union => l.max(r),
+            SetOperation::Intersection => l.min(r),
+            SetOperation::Difference => l.saturating_sub(r),
+            SetOperation::SymmetricDifference => l.abs_diff(r),
+        }
+    }
+}
+
+/**
+    Applies the operation to the sorted slices left and right. Every value is handled once with
+    the number of times it appears on both sides, the result is written to output if given and
+    its length is returned either way.
+*/
+fn sequential_set_operation(
+    left: &[i64],
+    right: &[i64],
+    mut output: Option<&mut [i64]>,
+    operation: SetOperation,
+    semantics: Semantics,
+) -> usize {
+    let (mut i, mut j, mut k) = (0, 0, 0);
+    let run = |input: &[i64], value: i64| input.iter().take_while(|x| **x == value).count();
+
+    while i < left.len() || j < right.len() {
+        // the smallest value not handled yet
+        let value = match (left.get(i), right.get(j)) {
+            (Some(&l), Some(&r)) => l.min(r),
+            (Some(&l), None) => l,
+            (None, Some(&r)) => r,
+            (None, None) => unreachable!(),
+        };
+        let (l, r) = (run(&left[i..], value), run(&right[j..], value));
+        i += l;
+        j += r;
+
+        let times = operation.multiplicity(semantics, l, r);
+        if let Some(output) = output.as_deref_mut() {
+            output[k..k + times].fill(value);
+        }
+        k += times;
+    }
+    count(Counter::Comparisons, (left.len() + right.len()) as u64);
+    if output.is_some() {
+        count(Counter::Writes, k as u64);
+    }
+    k
+}
+
+/**
+    The parts of left and right that are handled by one thread each. Like in `parallel_merge`,
+    the longer slice is cut into chunks and the ranks of the first element of each chunk are
+    searched in parallel, but in both slices, so that all copies of a value end up in the same
+    part.
+*/
+fn parts(left: &[i64], right: &[i64], num_processors: usize) -> Vec<(Range<usize>, Range<usize>)> {
+    let longer = if left.len() >= right.len() {
+        left
+    } else {
+        right
+    };
+    if longer.is_empty() {
+        return vec![(0..0, 0..0)];
+    }
+    let (threads, chunk_size) = chunks(longer.len(), num_processors);
+    let left_ranks = ranks(left, longer, threads, chunk_size, Bound::Lower);
+    let right_ranks = ranks(right, longer, threads, chunk_size, Bound::Lower);
+
+    (0..threads)
+        .map(|i| {
+            // the last part also gets the copies of the last element of the longer slice
+            let (left_end, right_end) = if i == threads - 1 {
+                (left.len(), right.len())
+            } else {
+                (left_ranks[i + 1], right_ranks[i + 1])
+            };
+            (left_ranks[i]..left_end, right_ranks[i]..right_end)
+        })
+        .collect()
+}
+
+/**
+    Writes the result of the operation on the sorted slices left and right to the start of output
+    and returns its length. The threads first count the result of their part, then write it at
+    the offset given by the parts before. Panics if output is shorter than the result.
+    num_processors may be `AUTO_THREADS`.
+*/
+pub fn set_operation_into(
+    left: &[i64],
+    right: &[i64],
+    output: &mut [i64],
+    operation: SetOperation,
+    semantics: Semantics,
+    num_processors: usize,
+) -> usize {
+    let _span = span_with_size("set operation", left.len() + right.len());
+    let (num_processors, _) =
+        profile().resolve("set_operation", left.len() + right.len(), num_processors, 1);
+    let parts = parts(left, right, num_processors);
+
+    let sizes: Vec<usize> = std::thread::scope(|scope| {
+        let handles: Vec<_> = parts
+            .iter()
+            .map(|(l, r)| {
+                let (left, right) = (&left[l.clone()], &right[r.clone()]);
+                scope.spawn(move || {
+                    sequential_set_operation(left, right, None, operation, semantics)
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    let total: usize = sizes.iter().sum();
+    assert!(
+        output.len() >= total,
+        "output of length {} is too short for the {} elements of the result",
+        output.len(),
+        total
+    );
+
+    std::thread::scope(|scope| {
+        let mut current_part;
+        let mut rest = &mut output[..total];
+        for ((l, r), size) in parts.iter().zip(sizes) {
+            (current_part, rest) = rest.split_at_mut(size);
+            let (left, right) = (&left[l.clone()], &right[r.clone()]);
+            scope.spawn(move || {
+                sequential_set_operation(left, right, Some(current_part), operation, semantics)
+            });
+        }
+    });
+    total
+}
+
+// like set_operation_into, but returns the result as a new vector
+pub fn set_operation(
+    left: &[i64],
+    right: &[i64],
+    operation: SetOperation,
+    semantics: Semantics,
+    num_processors: usize,
+) -> Vec<i64> {
+    // no result is longer than both inputs together
+    let mut output = vec![0i64; left.len() + right.len()];
+    let len = set_operation_into(
+        left,
+        right,
+        &mut output,
+        operation,
+        semantics,
+        num_processors,
+    );
+    output.truncate(len);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input_generation::gen_input_with_seed;
+    use crate::set_operations::{set_operation, set_operation_into, Semantics, SetOperation};
+    use crate::NUM_PROCESSORS_TEST;
+    use benchmark::tuning::AUTO_THREADS;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    // a sorted input with many duplicates, every value is below a third of the size
+    fn sorted_input(size: usize, seed: u64) -> Vec<i64> {
+        let mut input: Vec<i64> = gen_input_with_seed(size, seed)
+            .iter()
+            .map(|x| x.rem_euclid(size as i64 / 3 + 1))
+            .collect();
+        input.sort();
+        input
+    }
+
+    fn expected_set(left: &[i64], right: &[i64], operation: SetOperation) -> Vec<i64> {
+        let (left, right): (BTreeSet<i64>, BTreeSet<i64>) = (
+            left.iter().copied().collect(),
+            right.iter().copied().collect(),
+        );
+        match operation {
+            SetOperation::Union => left.union(&right).copied().collect(),
+            SetOperation::Intersection => left.intersection(&right).copied().collect(),
+            SetOperation::Difference => left.difference(&right).copied().collect(),
+            SetOperation::SymmetricDifference => {
+                left.symmetric_difference(&right).copied().collect()
+            }
+        }
+    }
+
+    fn expected_multiset(left: &[i64], right: &[i64], operation: SetOperation) -> Vec<i64> {
+        let counts = |input: &[i64]| {
+            let mut counts = BTreeMap::new();
+            for x in input {
+                *counts.entry(*x).or_insert(0) += 1;
+            }
+            counts
+        };
+        let (left, right) = (counts(left), counts(right));
+        let values: BTreeSet<i64> = left.keys().chain(right.keys()).copied().collect();
+        values
+            .into_iter()
+            .flat_map(|value| {
+                let l: usize = left.get(&value).copied().unwrap_or(0);
+                let r: usize = right.get(&value).copied().unwrap_or(0);
+                let times = match operation {
+                    SetOperation::Union => l.max(r),
+                    SetOperation::Intersection => l.min(r),
+                    SetOperation::Difference => l.saturating_sub(r),
+                    SetOperation::SymmetricDifference => l.abs_diff(r),
+                };
+                vec![value; times]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn set_operations_match_btree_set() {
+        for (seed, (left_size, right_size)) in [(0, 0), (0, 5), (7, 0), (300, 200), (50, 900)]
+            .into_iter()
+            .enumerate()
+        {
+            let left = sorted_input(left_size, seed as u64);
+            let right = sorted_input(right_size, seed as u64 + 100);
+            for operation in SetOperation::ALL {
+                let set = expected_set(&left, &right, operation);
+                let multiset = expected_multiset(&left, &right, operation);
+                for threads in [1, 2, 3, NUM_PROCESSORS_TEST, 100, AUTO_THREADS] {
+                    let message = format!(
+                        "{:?} of {} and {} elements with {} threads",
+                        operation,
+                        left.len(),
+                        right.len(),
+                        threads
+                    );
+                    let result = set_operation(&left, &right, operation, Semantics::Set, threads);
+                    assert_eq!(result, set, "{}", message);
+                    let result =
+                        set_operation(&left, &right, operation, Semantics::Multiset, threads);
+                    assert_eq!(result, multiset, "multiset {}", message);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn writes_into_a_buffer() {
+        let left = vec![1, 1, 2, 4, 4, 4, 7];
+        let right = vec![1, 4, 4, 5, 7, 7];
+        let mut output = vec![-1i64; 10];
+        let len = set_operation_into(
+            &left,
+            &right,
+            &mut output,
+            SetOperation::Intersection,
+            Semantics::Multiset,
+            3,
+        );
+        assert_eq!(len, 4);
+        assert_eq!(output, vec![1, 4, 4, 7, -1, -1, -1, -1, -1, -1]);
+
+        let mut output = vec![0i64; 2];
+        let len = set_operation_into(
+            &left,
+            &right,
+            &mut output,
+            SetOperation::SymmetricDifference,
+            Semantics::Set,
+            2,
+        );
+        assert_eq!(&output[..len], &[2, 5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "too short")]
+    fn buffer_too_short() {
+        let mut output = vec![0i64; 2];
+        set_operation_into(
+            &[1, 2, 3],
+            &[4],
+            &mut output,
+            SetOperation::Union,
+            Semantics::Set,
+            2,
+        );
+    }
+}
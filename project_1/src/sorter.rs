@@ -0,0 +1,258 @@
+use crate::merge_sort::{
+    fully_parallel_merge_sort_with_cutoff, parallel_merge_sort_with_cutoff, sequential_merge_sort,
+    CutoffSortFunction, SortFunction,
+};
+use crate::natural_merge_sort::{natural_merge_sort, parallel_natural_merge_sort};
+use crate::radix_sort::parallel_radix_sort;
+use crate::sample_sort::parallel_sample_sort;
+use benchmark::tuning::profile;
+
+/**
+    A sorting strategy. `name` is the short identifier used on the command line and in file names,
+    `description` is used in plot captions and tables.
+*/
+pub trait Sorter<T>: Sync {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+
+    /**
+        Sorts input with the given number of threads using scratch as additional memory of the same
+        length. The sorted result is in input afterwards, scratch may hold anything. Parts of at
+        most cutoff elements are sorted sequentially, strategies that do not split the input
+        sort it with a single thread if it is no longer than the cutoff.
+    */
+    fn sort_with_cutoff(&self, input: &mut [T], scratch: &mut [T], threads: usize, cutoff: usize);
+
+    // whether the strategy splits the input at the cutoff, i.e. whether the cutoff is worth tuning
+    fn has_cutoff(&self) -> bool {
+        false
+    }
+
+    /**
+        Like `sort_with_cutoff`, threads may be `AUTO_THREADS` to take the thread count and cutoff
+        from the tuning profile.
+    */
+    fn sort_with_scratch(&self, input: &mut [T], scratch: &mut [T], threads: usize) {
+        let (threads, cutoff) = profile().resolve(self.name(), input.len(), threads, 1);
+        self.sort_with_cutoff(input, scratch, threads, cutoff);
+    }
+
+    fn sort(&self, input: &mut [T], threads: usize)
+    where
+        T: Clone,
+    {
+        let mut scratch = input.to_vec();
+        self.sort_with_scratch(input, &mut scratch, threads);
+    }
+}
+
+// a sorter backed by one of the sorting functions on i64
+pub struct FunctionSorter {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub function: SortFunction,
+}
+
+impl Sorter<i64> for FunctionSorter {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn sort_with_cutoff(
+        &self,
+        input: &mut [i64],
+        scratch: &mut [i64],
+        threads: usize,
+        cutoff: usize,
+    ) {
+        if input.len() < 2 {
+            return;
+        }
+        // the function does not split at a cutoff, so inputs up to the cutoff get a single thread
+        let threads = if input.len() <= cutoff { 1 } else { threads };
+        // the merge sorts expect scratch to start out as a copy of the input
+        scratch.copy_from_slice(input);
+        (self.function)(input, scratch, threads);
+    }
+}
+
+// a sorter backed by one of the sorting functions on i64 that switch to sequential sorting
+pub struct CutoffSorter {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub function: CutoffSortFunction,
+}
+
+impl Sorter<i64> for CutoffSorter {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn sort_with_cutoff(
+        &self,
+        input: &mut [i64],
+        scratch: &mut [i64],
+        threads: usize,
+        cutoff: usize,
+    ) {
+        if input.len() < 2 {
+            return;
+        }
+        scratch.copy_from_slice(input);
+        (self.function)(input, scratch, threads, cutoff);
+    }
+
+    fn has_cutoff(&self) -> bool {
+        true
+    }
+}
+
+/**
+    All sorting strategies on i64. The CLI, the benchmarks and the tests iterate over this list, so
+    a strategy added here is available everywhere.
+*/
+pub fn registry() -> Vec<Box<dyn Sorter<i64>>> {
+    vec![
+        Box::new(FunctionSorter {
+            name: "SSSM",
+            description: "Sequential Sorting using Sequential Merging",
+            function: |input, scratch, _| sequential_merge_sort(input, scratch),
+        }),
+        Box::new(CutoffSorter {
+            name: "PSSM",
+            description: "Parallel Sorting using Sequential Merging",
+            function: parallel_merge_sort_with_cutoff,
+        }),
+        Box::new(CutoffSorter {
+            name: "PSPM",
+            description: "Parallel Sorting using Parallel Merging",
+            function: fully_parallel_merge_sort_with_cutoff,
+        }),
+        Box::new(FunctionSorter {
+            name: "NSSM",
+            description: "Natural Sequential Sorting using Sequential Merging",
+            function: |input, scratch, _| natural_merge_sort(input, scratch),
+        }),
+        Box::new(FunctionSorter {
+            name: "NPPM",
+            description: "Natural Parallel Sorting using Parallel Merging",
+            function: parallel_natural_merge_sort,
+        }),
+        Box::new(FunctionSorter {
+            name: "PSAS",
+            description: "Parallel Sample Sort",
+            function: parallel_sample_sort,
+        }),
+        Box::new(FunctionSorter {
+            name: "PLSD",
+            description: "Parallel LSD Radix Sort",
+            function: parallel_radix_sort,
+        }),
+    ]
+}
+
+pub fn find_sorter(name: &str) -> Option<Box<dyn Sorter<i64>>> {
+    registry().into_iter().find(|sorter| sorter.name() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input_generation::{
+        all_left_smaller_than_right_with_seed, all_right_smaller_than_left_with_seed,
+        gen_input_with_seed, reverse_sorted, right_fits_between_two_elements_in_left, shuffled,
+        shuffled_with_seed,
+    };
+    use crate::sorter::{find_sorter, registry};
+    use crate::utils::is_sorted;
+    use crate::NUM_PROCESSORS_TEST;
+    use benchmark::trace::record_locally;
+    use benchmark::tuning::AUTO_THREADS;
+
+    // runs every registered sorter on the input with several thread counts
+    fn check_all_sorters(input: Vec<i64>) {
+        let mut expected = input.clone();
+        expected.sort();
+
+        for sorter in registry() {
+            for threads in [1, 2, 3, NUM_PROCESSORS_TEST, AUTO_THREADS] {
+                let mut output = input.clone();
+                sorter.sort(&mut output, threads);
+                assert_eq!(
+                    output,
+                    expected,
+                    "{} with {} threads on {} elements",
+                    sorter.name(),
+                    threads,
+                    input.len()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn registry_names_are_unique() {
+        let sorters = registry();
+        for (i, sorter) in sorters.iter().enumerate() {
+            assert!(sorters[i + 1..].iter().all(|s| s.name() != sorter.name()));
+            assert_eq!(find_sorter(sorter.name()).unwrap().name(), sorter.name());
+        }
+        assert!(find_sorter("unknown").is_none());
+    }
+
+    #[test]
+    fn function_sorters_use_one_thread_up_to_the_cutoff() {
+        let sorter = find_sorter("NPPM").unwrap();
+        for (cutoff, parallel) in [(1, true), (1000, false)] {
+            let mut input = shuffled_with_seed(1000, 3);
+            let mut scratch = input.clone();
+            let (_, spans) =
+                record_locally(|| sorter.sort_with_cutoff(&mut input, &mut scratch, 4, cutoff));
+            assert!(is_sorted(input));
+            // the calling thread runs the merge of the two halves
+            let merged = spans
+                .iter()
+                .any(|s| s.name == "parallel merge" && s.size == Some(1000));
+            assert_eq!(merged, parallel, "cutoff {}", cutoff);
+        }
+    }
+
+    #[test]
+    fn all_sorters_tiny_inputs() {
+        check_all_sorters(vec![]);
+        check_all_sorters(vec![1]);
+        check_all_sorters(vec![2, 1]);
+        check_all_sorters(vec![5, 7, 2, 9, 1, 3, 8, 4, 6]);
+    }
+
+    #[test]
+    fn all_sorters_shuffled() {
+        check_all_sorters(shuffled(3000));
+    }
+
+    #[test]
+    fn all_sorters_sorted_and_reversed() {
+        check_all_sorters((0..3000).collect());
+        check_all_sorters(reverse_sorted(3000));
+    }
+
+    #[test]
+    fn all_sorters_duplicates_and_negatives() {
+        check_all_sorters(gen_input_with_seed(3000, 1));
+        check_all_sorters(gen_input_with_seed(3000, 2).iter().map(|x| x % 5).collect());
+    }
+
+    #[test]
+    fn all_sorters_split_inputs() {
+        check_all_sorters(all_left_smaller_than_right_with_seed(3000, 3));
+        check_all_sorters(all_right_smaller_than_left_with_seed(3000, 4));
+        check_all_sorters(right_fits_between_two_elements_in_left(3000));
+    }
+}
@@ -1,3 +1,10 @@
+use benchmark::counters::{count, Counter};
+use benchmark::trace::span;
+use benchmark::tuning::profile;
+use std::cmp::{min, Ordering};
+use std::iter::zip;
+use std::panic::resume_unwind;
+
 pub fn is_sorted(vec: Vec<i64>) -> bool {
     for i in 0..vec.len() - 1 {
         if vec[i] > vec[i + 1] {
@@ -8,62 +15,296 @@ pub fn is_sorted(vec: Vec<i64>) -> bool {
 }
 
 pub fn binary_search(input: &[i64], key: i64) -> usize {
+    binary_partition_point(input, |x| *x < key)
+}
+
+// upper bound of key in input, the number of elements smaller or equal to key
+pub fn binary_search_upper(input: &[i64], key: i64) -> usize {
+    binary_partition_point(input, |x| *x <= key)
+}
+
+// first index in input for which pred is false
+fn binary_partition_point<T>(input: &[T], pred: impl Fn(&T) -> bool) -> usize {
     let (mut low, mut high) = (0, input.len());
 
+    let mut comparisons = 0;
     while low < high {
         let mid = (low + high) / 2;
-        if key <= input[mid] {
-            high = mid
-        } else {
+        comparisons += 1;
+        if pred(&input[mid]) {
             low = mid + 1
+        } else {
+            high = mid
         }
     }
+    count(Counter::Comparisons, comparisons);
     high
 }
 
+// first index in input for which pred is false, probing positions 0, 1, 3, 7, ... first
+fn exponential_partition_point<T>(input: &[T], pred: impl Fn(&T) -> bool) -> usize {
+    let mut comparisons = 0;
+    let mut pred = |x: &T| {
+        comparisons += 1;
+        pred(x)
+    };
+    let mut bound = 1;
+    while bound <= input.len() && pred(&input[bound - 1]) {
+        bound *= 2;
+    }
+
+    // the partition point lies in input[bound / 2..bound] (clamped to the slice)
+    let low = bound / 2;
+    let high = min(bound, input.len());
+    let point = low + input[low..high].partition_point(&mut pred);
+    count(Counter::Comparisons, comparisons);
+    point
+}
+
+/**
+    Lower bound of `key` in `input` like `binary_search`, but probes positions 0, 1, 3, 7, ... first
+    so that the cost is logarithmic in the returned rank instead of in the slice length.
+*/
+pub fn exponential_search(input: &[i64], key: i64) -> usize {
+    exponential_partition_point(input, |x| *x < key)
+}
+
+/**
+    Upper bound of `key` in `input` (the number of elements smaller or equal to key) using the
+    same exponential probing as `exponential_search`.
+*/
+pub fn exponential_search_upper(input: &[i64], key: i64) -> usize {
+    exponential_partition_point(input, |x| *x <= key)
+}
+
+// whether the rank of a query counts the elements smaller than it or those smaller or equal
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Bound {
+    Lower,
+    Upper,
+}
+
+impl Bound {
+    // whether an element that compares to the query like this counts towards its rank
+    pub fn counts(&self, ordering: Ordering) -> bool {
+        match self {
+            Bound::Lower => ordering == Ordering::Less,
+            Bound::Upper => ordering != Ordering::Greater,
+        }
+    }
+}
+
+/**
+    The ranks of all queries in the sorted input, computed by the given number of threads for
+    consecutive chunks of the queries. If the queries are sorted as well, every thread sweeps
+    through input like a merge: each search starts at the rank of the query before and gallops
+    ahead, so close queries cost only a few comparisons. Otherwise every query is a binary search.
+    num_processors may be `AUTO_THREADS`.
+*/
+pub fn batch_ranks(
+    input: &[i64],
+    queries: &[i64],
+    bound: Bound,
+    num_processors: usize,
+) -> Vec<usize> {
+    batch_ranks_by(input, queries, bound, num_processors, &i64::cmp)
+}
+
+/**
+    Like `batch_ranks`, with input and queries ordered by the comparator. If the comparator
+    panics in one of the threads, the panic is passed on to the caller as it is.
+*/
+pub fn batch_ranks_by<T: Sync, F: Fn(&T, &T) -> Ordering + Sync>(
+    input: &[T],
+    queries: &[T],
+    bound: Bound,
+    num_processors: usize,
+    compare: &F,
+) -> Vec<usize> {
+    let mut ranks = vec![0usize; queries.len()];
+    if queries.is_empty() {
+        return ranks;
+    }
+    let (num_processors, _) = profile().resolve("batch_ranks", queries.len(), num_processors, 1);
+    let sorted = queries
+        .windows(2)
+        .all(|w| compare(&w[0], &w[1]) != Ordering::Greater);
+    let chunk_size = queries.len().div_ceil(num_processors.max(1));
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = zip(queries.chunks(chunk_size), ranks.chunks_mut(chunk_size))
+            .map(|(queries, ranks)| {
+                scope.spawn(move || {
+                    let _span = span("rank search");
+                    let mut rank = 0;
+                    for (query, result) in zip(queries, ranks) {
+                        let counts = |x: &T| bound.counts(compare(x, query));
+                        *result = if sorted {
+                            rank += exponential_partition_point(&input[rank..], counts);
+                            rank
+                        } else {
+                            binary_partition_point(input, counts)
+                        };
+                    }
+                })
+            })
+            .collect();
+        // joined here, as the scope would replace the panic of a thread with its own
+        for handle in handles {
+            if let Err(payload) = handle.join() {
+                resume_unwind(payload);
+            }
+        }
+    });
+    ranks
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::utils::binary_search;
+    use crate::input_generation::gen_input_with_seed;
+    use crate::utils::{
+        batch_ranks, batch_ranks_by, binary_search, binary_search_upper, exponential_search,
+        exponential_search_upper, Bound,
+    };
+    use crate::NUM_PROCESSORS_TEST;
+    use benchmark::tuning::AUTO_THREADS;
 
     #[test]
     fn binary_search_element_missing() {
         let input: Vec<i64> = vec![1, 2, 3, 5, 6, 7, 8, 9];
-        let index = binary_search(&*input, 4);
+        let index = binary_search(&input, 4);
         assert_eq!(Err(index), input.binary_search(&4));
     }
 
     #[test]
     fn binary_search_middle() {
         let input: Vec<i64> = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
-        let index = binary_search(&*input, 4);
+        let index = binary_search(&input, 4);
         assert_eq!(Ok(index), input.binary_search(&4));
     }
 
     #[test]
     fn binary_search_right_edge() {
         let input: Vec<i64> = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
-        let index = binary_search(&*input, 9);
+        let index = binary_search(&input, 9);
         assert_eq!(Ok(index), input.binary_search(&9));
     }
 
     #[test]
     fn binary_search_right_edge_missing() {
         let input: Vec<i64> = vec![1, 2, 3, 4, 5, 6, 7, 8];
-        let index = binary_search(&*input, 9);
+        let index = binary_search(&input, 9);
         assert_eq!(Err(index), input.binary_search(&9));
     }
 
     #[test]
     fn binary_search_left_edge() {
         let input: Vec<i64> = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
-        let index = binary_search(&*input, 1);
+        let index = binary_search(&input, 1);
         assert_eq!(Ok(index), input.binary_search(&1));
     }
 
     #[test]
     fn binary_search_left_edge_missing() {
         let input: Vec<i64> = vec![2, 3, 4, 5, 6, 7, 8, 9];
-        let index = binary_search(&*input, 1);
+        let index = binary_search(&input, 1);
         assert_eq!(Err(index), input.binary_search(&1));
     }
+
+    #[test]
+    fn binary_search_upper_matches_partition_point() {
+        let input: Vec<i64> = vec![1, 2, 2, 2, 5, 6, 8, 8, 9, 12, 13, 20];
+        for key in 0..22 {
+            assert_eq!(
+                binary_search_upper(&input, key),
+                input.partition_point(|x| *x <= key),
+                "key {}",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn exponential_search_matches_binary_search() {
+        let input: Vec<i64> = vec![1, 2, 2, 2, 5, 6, 8, 8, 9, 12, 13, 20];
+        for key in 0..22 {
+            assert_eq!(
+                exponential_search(&input, key),
+                binary_search(&input, key),
+                "key {}",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn exponential_search_empty() {
+        let input: Vec<i64> = vec![];
+        assert_eq!(exponential_search(&input, 3), 0);
+    }
+
+    #[test]
+    fn exponential_search_upper_matches_partition_point() {
+        let input: Vec<i64> = vec![1, 2, 2, 2, 5, 6, 8, 8, 9, 12, 13, 20];
+        for key in 0..22 {
+            assert_eq!(
+                exponential_search_upper(&input, key),
+                input.partition_point(|x| *x <= key),
+                "key {}",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn batch_ranks_match_partition_point() {
+        let mut input: Vec<i64> = gen_input_with_seed(5000, 1)
+            .iter()
+            .map(|x| x % 500)
+            .collect();
+        input.sort();
+        let unsorted: Vec<i64> = gen_input_with_seed(3000, 2)
+            .iter()
+            .map(|x| x % 600)
+            .collect();
+        let mut sorted = unsorted.clone();
+        sorted.sort();
+
+        for queries in [vec![], vec![-1], vec![1000, 1000], unsorted, sorted] {
+            for threads in [1, 3, NUM_PROCESSORS_TEST, 100, AUTO_THREADS] {
+                let lower = batch_ranks(&input, &queries, Bound::Lower, threads);
+                let upper = batch_ranks(&input, &queries, Bound::Upper, threads);
+                for (i, query) in queries.iter().enumerate() {
+                    assert_eq!(lower[i], input.partition_point(|x| x < query));
+                    assert_eq!(upper[i], input.partition_point(|x| x <= query));
+                }
+                assert_eq!(lower.len(), queries.len());
+            }
+        }
+        assert_eq!(batch_ranks(&[], &[3, 1], Bound::Upper, 2), vec![0, 0]);
+    }
+
+    #[test]
+    fn batch_ranks_by_comparator() {
+        // sorted by length, "pear" and "plum" compare equal
+        let input = ["fig", "kiwi", "pear", "apple", "banana"];
+        let queries = ["plum", "date", "cherry", "a", "nectarine"];
+        let by_length = |a: &&str, b: &&str| a.len().cmp(&b.len());
+        for threads in [1, 2, NUM_PROCESSORS_TEST] {
+            let lower = batch_ranks_by(&input, &queries, Bound::Lower, threads, &by_length);
+            let upper = batch_ranks_by(&input, &queries, Bound::Upper, threads, &by_length);
+            assert_eq!(lower, vec![1, 1, 4, 0, 5]);
+            assert_eq!(upper, vec![3, 3, 5, 0, 5]);
+        }
+    }
+
+    #[test]
+    fn histogram_from_ranks() {
+        // the number of elements in each bucket [b, b + 10) is the difference of two ranks
+        let input: Vec<i64> = vec![1, 3, 3, 12, 15, 19, 20, 31, 35, 39, 39];
+        let bounds: Vec<i64> = (0..5).map(|b| b * 10).collect();
+        let ranks = batch_ranks(&input, &bounds, Bound::Lower, 2);
+        let histogram: Vec<usize> = ranks.windows(2).map(|w| w[1] - w[0]).collect();
+        assert_eq!(histogram, vec![3, 3, 1, 4]);
+    }
 }
@@ -0,0 +1,251 @@
+use crate::input_generation::{
+    all_left_smaller_than_right_with_seed, all_right_smaller_than_left_with_seed, alternating,
+    gen_input_with_seed, left_fits_between_last_two_elements_in_right,
+    random_sorted_halves_with_seed, reverse_sorted, right_fits_between_two_elements_in_left,
+    right_fits_between_two_elements_in_left_halves, shuffled_with_seed, sorted, LeftRightSplit,
+};
+use crate::merge::{merge_threads, parallel_merge_with_kernel, MergeKernel};
+use crate::selection::{partial_sort, select_nth, top_k};
+use crate::sorter::{find_sorter, registry};
+use benchmark::runner::{Sample, Workload};
+use std::time::Instant;
+
+// the generators get the seed of the run, the deterministic ones ignore it
+type SortInputFunction = fn(i64, u64) -> Vec<i64>;
+type MergeInputFunction = fn(i64, u64) -> LeftRightSplit;
+
+const SORT_GENERATORS: [(&str, SortInputFunction); 7] = [
+    ("shuffled", shuffled_with_seed),
+    ("sorted", |n, _| (0..n).collect()),
+    ("reverse_sorted", |n, _| reverse_sorted(n)),
+    ("random", |n, seed| gen_input_with_seed(n as usize, seed)),
+    (
+        "all_left_smaller_than_right",
+        all_left_smaller_than_right_with_seed,
+    ),
+    (
+        "all_right_smaller_than_left",
+        all_right_smaller_than_left_with_seed,
+    ),
+    ("right_fits_between_two_elements_in_left", |n, _| {
+        right_fits_between_two_elements_in_left(n)
+    }),
+];
+
+const MERGE_GENERATORS: [(&str, MergeInputFunction); 5] = [
+    ("alternating", |n, _| alternating(n)),
+    ("sorted", |n, _| sorted(n)),
+    ("random_sorted_halves", random_sorted_halves_with_seed),
+    ("lopsided", |n, _| {
+        left_fits_between_last_two_elements_in_right(n)
+    }),
+    ("right_between_two_in_left", |n, _| {
+        right_fits_between_two_elements_in_left_halves(n)
+    }),
+];
+
+// the algorithms of the task "select", k is a hundredth of the input size for top_k and partial_sort
+const SELECTION_ALGORITHMS: [(&str, &str); 3] = [
+    ("select_nth", "Parallel Selection of the Median"),
+    ("top_k", "Parallel Top-k"),
+    ("partial_sort", "Parallel Partial Sort"),
+];
+
+/**
+    Benchmark workload of project_1. The default task "sort" runs the sorters of the registry on
+    the sort input generators, the task "merge" runs the merge kernels on two sorted halves (with
+    the parallel merge if more than one thread is used). The task "select" runs the selection
+    algorithms and, to compare them to sorting everything, the sorters on the sort inputs.
+*/
+pub struct SortWorkload();
+
+impl Workload for SortWorkload {
+    fn algorithms(&self, task: Option<&str>) -> Vec<String> {
+        match task.unwrap_or("sort") {
+            "sort" => registry()
+                .iter()
+                .map(|sorter| sorter.name().to_string())
+                .collect(),
+            "merge" => MergeKernel::ALL
+                .iter()
+                .map(|kernel| kernel.name().to_lowercase())
+                .collect(),
+            "select" => SELECTION_ALGORITHMS
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .chain(registry().iter().map(|sorter| sorter.name().to_string()))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn generators(&self, task: Option<&str>) -> Vec<String> {
+        match task.unwrap_or("sort") {
+            "sort" | "select" => SORT_GENERATORS
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            "merge" => MERGE_GENERATORS
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn label(&self, name: &str) -> String {
+        match find_sorter(name) {
+            Some(sorter) => sorter.description().to_string(),
+            None => SELECTION_ALGORITHMS
+                .iter()
+                .find(|(algorithm, _)| *algorithm == name)
+                .map_or(name.to_string(), |(_, label)| label.to_string()),
+        }
+    }
+
+    fn run(
+        &self,
+        task: Option<&str>,
+        algorithm: &str,
+        generator: &str,
+        size: usize,
+        threads: usize,
+        seed: u64,
+    ) -> Sample {
+        let seconds = if task == Some("merge") {
+            let kernel = *MergeKernel::ALL
+                .iter()
+                .find(|kernel| kernel.name().to_lowercase() == algorithm)
+                .unwrap();
+            let (_, function) = MERGE_GENERATORS
+                .iter()
+                .find(|(name, _)| *name == generator)
+                .unwrap();
+
+            let (left, right) = function(size as i64, seed);
+            let mut output = vec![0; left.len() + right.len()];
+            let now = Instant::now();
+            let threads = merge_threads(right.len(), threads);
+            if threads > 1 {
+                parallel_merge_with_kernel(&left, &right, &mut output, threads, kernel);
+            } else {
+                kernel.merge(&left, &right, &mut output);
+            }
+            now.elapsed().as_secs_f64()
+        } else if task == Some("select") && find_sorter(algorithm).is_none() {
+            let (_, function) = SORT_GENERATORS
+                .iter()
+                .find(|(name, _)| *name == generator)
+                .unwrap();
+
+            let mut input = function(size as i64, seed);
+            let k = (size / 100).max(1);
+            let now = Instant::now();
+            match algorithm {
+                "select_nth" => {
+                    select_nth(&mut input, size / 2, threads);
+                }
+                "top_k" => {
+                    top_k(&input, k, threads);
+                }
+                _ => partial_sort(&mut input, k, threads),
+            }
+            now.elapsed().as_secs_f64()
+        } else {
+            let sorter = find_sorter(algorithm).unwrap();
+            let (_, function) = SORT_GENERATORS
+                .iter()
+                .find(|(name, _)| *name == generator)
+                .unwrap();
+
+            let mut input = function(size as i64, seed);
+            let mut scratch = vec![0; input.len()];
+            let now = Instant::now();
+            sorter.sort_with_scratch(&mut input, &mut scratch, threads);
+            now.elapsed().as_secs_f64()
+        };
+
+        Sample {
+            seconds,
+            output_size: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::workload::SortWorkload;
+    use benchmark::runner::{run_experiment, Workload};
+    use benchmark::spec::BenchmarkSpec;
+
+    #[test]
+    fn runs_every_sorter_and_kernel() {
+        let spec = BenchmarkSpec::parse(
+            r#"
+            [[experiment]]
+            name = "sort"
+            algorithms = ["all"]
+            generators = ["all"]
+            sizes = [100]
+            threads = [1, 4]
+            repetitions = 1
+
+            [[experiment]]
+            name = "merge"
+            task = "merge"
+            algorithms = ["all"]
+            generators = ["all"]
+            sizes = [100]
+            threads = [1, 4]
+            repetitions = 1
+
+            [[experiment]]
+            name = "select"
+            task = "select"
+            algorithms = ["all"]
+            generators = ["all"]
+            sizes = [100]
+            threads = [1, 4]
+            repetitions = 1
+            "#,
+        )
+        .unwrap();
+
+        let workload = SortWorkload();
+        let sort = run_experiment(&workload, &spec.experiments[0]).unwrap();
+        assert_eq!(
+            sort.len(),
+            workload.algorithms(None).len() * workload.generators(None).len() * 2
+        );
+        let merge = run_experiment(&workload, &spec.experiments[1]).unwrap();
+        assert_eq!(merge.len(), 4 * 5 * 2);
+        let select = run_experiment(&workload, &spec.experiments[2]).unwrap();
+        assert_eq!(
+            select.len(),
+            workload.algorithms(Some("select")).len() * workload.generators(None).len() * 2
+        );
+    }
+
+    #[test]
+    fn spec_file_is_valid() {
+        let spec = BenchmarkSpec::parse(include_str!("../benchmarks.toml")).unwrap();
+        let workload = SortWorkload();
+        for experiment in spec.experiments {
+            let task = experiment.task.as_deref();
+            for algorithm in experiment.algorithms.iter() {
+                assert!(
+                    workload.algorithms(task).contains(algorithm),
+                    "{}",
+                    algorithm
+                );
+            }
+            for generator in experiment.generators.iter() {
+                assert!(
+                    workload.generators(task).contains(generator),
+                    "{}",
+                    generator
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use crate::plotting::{axis_range, coordinates};
+use crate::types::{HullEvent, Point, PointVector, TurnType};
+use benchmark::plotting::{color, font_family, render_animation, Drawing};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::{GREY, ORANGE};
+use std::error::Error;
+
+// what an animation frame shows after the events up to it
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct AnimationState {
+    // the hull built so far by Graham's scan or gift wrapping
+    pub hull: Vec<Point>,
+    // the current gift wrapping candidate
+    pub pivot: Option<Point>,
+    pub sub_hulls: Vec<PointVector>,
+    // the tangents of the current merge step of the parallel Graham's scan
+    pub candidates: Vec<(Point, Point)>,
+    pub bridges: Vec<(Point, Point)>,
+    // the point the last event was about
+    pub highlight: Option<Point>,
+    pub caption: String,
+}
+
+fn turn_name(turn: &TurnType) -> &'static str {
+    match turn {
+        TurnType::Left => "left turn",
+        TurnType::Right => "right turn",
+        TurnType::Straight => "straight",
+    }
+}
+
+fn describe(p: &Point) -> String {
+    format!("({}, {})", p.x, p.y)
+}
+
+impl AnimationState {
+    pub fn apply(&mut self, event: &HullEvent) {
+        match event {
+            HullEvent::Push { point, turn } => {
+                self.hull.push(*point);
+                self.highlight = Some(*point);
+                self.caption = match turn {
+                    Some(turn) => format!("push {}, {}", describe(point), turn_name(turn)),
+                    None => format!("push {}", describe(point)),
+                };
+            }
+            HullEvent::Pop { point, turn } => {
+                self.hull.pop();
+                self.highlight = Some(*point);
+                self.caption = format!("pop {}, {}", describe(point), turn_name(turn));
+            }
+            HullEvent::Pivot(point) => {
+                self.pivot = Some(*point);
+                self.highlight = Some(*point);
+                self.caption = format!("pivot {}", describe(point));
+            }
+            HullEvent::HullPoint(point) => {
+                self.hull.push(*point);
+                self.pivot = None;
+                self.highlight = Some(*point);
+                self.caption = format!("add {} to the hull", describe(point));
+            }
+            HullEvent::SubHull { index, hull } => {
+                self.sub_hulls.push(hull.clone());
+                self.highlight = None;
+                self.caption = format!("sub-hull {} with {} points", index, hull.points.len());
+            }
+            HullEvent::TangentCandidate { left, right } => {
+                self.candidates.push((*left, *right));
+                self.highlight = Some(*right);
+                self.caption = format!("tangent candidate {} {}", describe(left), describe(right));
+            }
+            HullEvent::Bridge { left, right } => {
+                self.candidates.clear();
+                self.bridges.push((*left, *right));
+                self.highlight = None;
+                self.caption = format!("bridge {} {}", describe(left), describe(right));
+            }
+        }
+    }
+}
+
+/**
+    The state after every event, followed by a last frame with the final hull, so that an
+    animation has one frame per event.
+*/
+pub fn frames(events: &[HullEvent], result: &PointVector) -> Vec<AnimationState> {
+    let mut state = AnimationState::default();
+    let mut frames: Vec<AnimationState> = events
+        .iter()
+        .map(|event| {
+            state.apply(event);
+            state.clone()
+        })
+        .collect();
+    frames.push(AnimationState {
+        hull: result.points.clone(),
+        caption: format!("upper hull with {} points", result.points.len()),
+        ..AnimationState::default()
+    });
+    frames
+}
+
+struct Frame<'a> {
+    title: &'a str,
+    input: &'a PointVector,
+    state: &'a AnimationState,
+    step: usize,
+    steps: usize,
+}
+
+impl Drawing for Frame<'_> {
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let font = font_family("sans-serif");
+        let state = self.state;
+        root.fill(&WHITE)?;
+        let root = root.margin(10, 10, 10, 10);
+
+        let caption = format!(
+            "{} {}/{}: {}",
+            self.title, self.step, self.steps, state.caption
+        );
+        let mut chart = ChartBuilder::on(&root)
+            .caption(caption, (font, 18).into_font())
+            .x_label_area_size(35)
+            .y_label_area_size(55)
+            .build_cartesian_2d(
+                axis_range(self.input.points.iter().map(|p| p.x), 0.05, 0.05),
+                axis_range(self.input.points.iter().map(|p| p.y), 0.05, 0.05),
+            )?;
+        chart
+            .configure_mesh()
+            .max_light_lines(0)
+            .label_style((font, 12))
+            .draw()?;
+
+        chart.draw_series(
+            self.input
+                .points
+                .iter()
+                .map(|p| Circle::new(coordinates(p), 2, GREY.filled())),
+        )?;
+
+        for (i, hull) in state.sub_hulls.iter().enumerate() {
+            chart.draw_series(
+                LineSeries::new(hull.points.iter().map(coordinates), color(i + 1).mix(0.6))
+                    .point_size(2),
+            )?;
+        }
+        for (left, right) in state.candidates.iter() {
+            chart.draw_series(DashedLineSeries::new(
+                [coordinates(left), coordinates(right)],
+                6,
+                4,
+                ORANGE.stroke_width(2),
+            ))?;
+        }
+        for (left, right) in state.bridges.iter() {
+            chart.draw_series(LineSeries::new(
+                [coordinates(left), coordinates(right)],
+                RED.stroke_width(3),
+            ))?;
+        }
+
+        chart.draw_series(
+            LineSeries::new(state.hull.iter().map(coordinates), BLUE.stroke_width(2)).point_size(3),
+        )?;
+        if let (Some(last), Some(pivot)) = (state.hull.last(), state.pivot) {
+            chart.draw_series(DashedLineSeries::new(
+                [coordinates(last), coordinates(&pivot)],
+                6,
+                4,
+                ORANGE.stroke_width(2),
+            ))?;
+        }
+        if let Some(point) = state.highlight {
+            chart.draw_series(std::iter::once(Circle::new(
+                coordinates(&point),
+                7,
+                RED.stroke_width(2),
+            )))?;
+        }
+        Ok(())
+    }
+}
+
+/**
+    Renders one frame per event and a last frame with the final hull. A path ending in .gif gives
+    an animated GIF with the given delay between frames, any other path is a directory that gets
+    the numbered frames frame_0001.png, frame_0002.png, ...
+*/
+pub fn animate(
+    path: &str,
+    size: (u32, u32),
+    title: &str,
+    input: &PointVector,
+    events: &[HullEvent],
+    result: &PointVector,
+    delay_ms: u32,
+) -> Result<(), Box<dyn Error>> {
+    let frames = frames(events, result);
+    let steps = frames.len();
+    let frames: Vec<Frame> = frames
+        .iter()
+        .enumerate()
+        .map(|(i, state)| Frame {
+            title,
+            input,
+            state,
+            step: i + 1,
+            steps,
+        })
+        .collect();
+    render_animation(path, size, &frames, delay_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::animation::{animate, frames};
+    use crate::grahams_scan::grahams_scan_observed;
+    use crate::input_generation::UniformSquare;
+    use crate::types::{HullEvent, Point, PointVector};
+    use std::env::temp_dir;
+    use std::fs;
+
+    #[test]
+    fn replaying_the_events_builds_the_hull() {
+        let input = UniformSquare::get_input_with_seed(50, 1);
+        let mut events = vec![];
+        let hull = grahams_scan_observed(input, Some(true), &mut |e| events.push(e));
+        let frames = frames(&events, &hull);
+        assert_eq!(frames.len(), events.len() + 1);
+        // the stack after the last event is the hull
+        assert_eq!(frames[frames.len() - 2].hull, hull.points);
+        assert!(frames[0].caption.starts_with("push"));
+    }
+
+    #[test]
+    fn bridges_replace_the_candidates() {
+        let point = |x, y| Point { x, y };
+        let events = [
+            HullEvent::TangentCandidate {
+                left: point(0, 0),
+                right: point(5, 5),
+            },
+            HullEvent::TangentCandidate {
+                left: point(0, 0),
+                right: point(9, 1),
+            },
+            HullEvent::Bridge {
+                left: point(0, 0),
+                right: point(5, 5),
+            },
+        ];
+        let frames = frames(&events, &PointVector { points: vec![] });
+        assert_eq!(frames[1].candidates.len(), 2);
+        assert!(frames[2].candidates.is_empty());
+        assert_eq!(frames[2].bridges, vec![(point(0, 0), point(5, 5))]);
+    }
+
+    #[test]
+    fn writes_frames_and_gif() {
+        let input = UniformSquare::get_input_with_seed(8, 2);
+        let mut events = vec![];
+        let hull = grahams_scan_observed(input.clone(), Some(true), &mut |e| events.push(e));
+
+        let directory = temp_dir().join("project_2_animation_frames");
+        let _ = fs::remove_dir_all(&directory);
+        let directory = directory.to_str().unwrap();
+        animate(directory, (200, 200), "Test", &input, &events, &hull, 100).unwrap();
+        assert_eq!(fs::read_dir(directory).unwrap().count(), events.len() + 1);
+
+        let gif = temp_dir().join("project_2_animation.gif");
+        let gif = gif.to_str().unwrap();
+        animate(gif, (200, 200), "Test", &input, &events, &hull, 100).unwrap();
+        assert!(fs::metadata(gif).unwrap().len() > 0);
+    }
+}
@@ -1,10 +1,18 @@
-use crate::types::{Point, PointVector, TurnType};
+use crate::types::{HullEvent, Point, PointVector, TurnType};
 use crate::utils::turn_type;
 
 pub fn gift_wrapping_upper_hull(
-    mut input: PointVector,
+    input: PointVector,
     _: Option<bool>,
     _: Option<usize>,
+) -> PointVector {
+    gift_wrapping_observed(input, &mut |_| {})
+}
+
+// gift wrapping that reports every change of the pivot and every new hull point to the observer
+pub fn gift_wrapping_observed(
+    mut input: PointVector,
+    observe: &mut impl FnMut(HullEvent),
 ) -> PointVector {
     // init - find leftmost point
     let (leftmost_idx, leftmost) = input
@@ -18,6 +26,7 @@ pub fn gift_wrapping_upper_hull(
     let mut upper_hull: PointVector = PointVector {
         points: vec![*leftmost],
     };
+    observe(HullEvent::HullPoint(*leftmost));
 
     input.points.swap_remove(leftmost_idx);
 
@@ -38,6 +47,7 @@ pub fn gift_wrapping_upper_hull(
         }
         let mut p = input.points[0];
         let mut idx_p = 0;
+        observe(HullEvent::Pivot(p));
         // look at other points and whether they make a left turn with line that ends at p.
         // If left turn, use this point as the new pivot.
         // If no left turn is found, add p to the upper hull
@@ -48,12 +58,14 @@ pub fn gift_wrapping_upper_hull(
                 // therefore, switch pivot to the point q, as this is left of p and all the other points we have checked so far.
                 p = q;
                 idx_p = i;
+                observe(HullEvent::Pivot(p));
                 continue;
             }
         }
         // if p has all points to the right of it, add to convex hull
         upper_hull.points.push(p);
         input.points.swap_remove(idx_p);
+        observe(HullEvent::HullPoint(p));
     }
 
     upper_hull
@@ -61,9 +73,32 @@ pub fn gift_wrapping_upper_hull(
 
 #[cfg(test)]
 mod test {
-    use crate::gift_wrapping::gift_wrapping_upper_hull;
-    use crate::input_generation::{Curve, Line};
-    use crate::types::{Point, PointVector};
+    use crate::gift_wrapping::{gift_wrapping_observed, gift_wrapping_upper_hull};
+    use crate::input_generation::{Curve, Line, UniformSquare};
+    use crate::types::{HullEvent, Point, PointVector};
+
+    #[test]
+    fn observed_hull_points() {
+        let input = UniformSquare::get_input_with_seed(100, 7);
+        let mut events = vec![];
+        let hull = gift_wrapping_observed(input.clone(), &mut |e| events.push(e));
+        assert_eq!(hull, gift_wrapping_upper_hull(input, None, None));
+
+        let hull_points: Vec<Point> = events
+            .iter()
+            .filter_map(|e| match e {
+                HullEvent::HullPoint(p) => Some(*p),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(hull_points, hull.points);
+        // every hull point after the first one was a pivot before it was added
+        for (i, event) in events.iter().enumerate().skip(1) {
+            if let HullEvent::HullPoint(p) = event {
+                assert_eq!(events[i - 1], HullEvent::Pivot(*p));
+            }
+        }
+    }
 
     #[test]
     fn line_hull() {
@@ -1,10 +1,18 @@
-use crate::types::{Point, PointVector, TurnType};
+use crate::types::{HullEvent, Point, PointVector, TurnType};
 use crate::utils::turn_type;
 
-pub fn grahams_scan(
+pub fn grahams_scan(input: PointVector, sort_input: Option<bool>, _: Option<usize>) -> PointVector {
+    grahams_scan_observed(input, sort_input, &mut |_| {})
+}
+
+/**
+    Graham's scan that reports every push and pop to the observer. The benchmarked version passes
+    an observer that does nothing, which the compiler removes.
+*/
+pub fn grahams_scan_observed(
     mut input: PointVector,
     sort_input: Option<bool>,
-    _: Option<usize>,
+    observe: &mut impl FnMut(HullEvent),
 ) -> PointVector {
     if sort_input.unwrap_or(true) {
         input.points.sort_by_key(|Point { x, y: _ }| *x);
@@ -14,36 +22,92 @@ pub fn grahams_scan(
     let mut upper_hull: PointVector = PointVector { points: Vec::new() };
 
     // add two first points from input to upper hull
-    upper_hull.points.push(input.points[0]);
-    upper_hull.points.push(input.points[1]);
+    for &point in input.points[..2].iter() {
+        upper_hull.points.push(point);
+        observe(HullEvent::Push { point, turn: None });
+    }
     // store the index of the last element in the upper hull
     let mut uh_last_idx = 1;
 
     // loopidy loop
     for i in 2..input.points.len() {
         // remove last upper hull point if it makes a left turn with second last and new point
-        while upper_hull.points.len() > 1
-            && turn_type(
+        let mut turn = None;
+        while upper_hull.points.len() > 1 {
+            let last_turn = turn_type(
                 upper_hull.points[uh_last_idx - 1],
                 upper_hull.points[uh_last_idx],
                 input.points[i],
-            ) != TurnType::Right
-        {
-            upper_hull.points.pop();
+            );
+            if last_turn == TurnType::Right {
+                turn = Some(last_turn);
+                break;
+            }
+            let point = upper_hull.points.pop().unwrap();
             uh_last_idx -= 1;
+            observe(HullEvent::Pop {
+                point,
+                turn: last_turn,
+            });
         }
         // no longer a left turn -> new point can be added
         upper_hull.points.push(input.points[i]);
         uh_last_idx += 1;
+        observe(HullEvent::Push {
+            point: input.points[i],
+            turn,
+        });
     }
     upper_hull
 }
 
 #[cfg(test)]
 mod test {
-    use crate::grahams_scan::grahams_scan;
+    use crate::grahams_scan::{grahams_scan, grahams_scan_observed};
     use crate::input_generation::{Curve, Line};
-    use crate::types::{Point, PointVector};
+    use crate::types::{HullEvent, Point, PointVector, TurnType};
+
+    #[test]
+    fn observed_pushes_and_pops() {
+        let input = PointVector {
+            points: vec![
+                Point { x: 0, y: 0 },
+                Point { x: 1, y: 1 },
+                Point { x: 2, y: 3 },
+                Point { x: 3, y: 0 },
+            ],
+        };
+        let mut events = vec![];
+        let hull = grahams_scan_observed(input.clone(), Some(true), &mut |e| events.push(e));
+        assert_eq!(hull, grahams_scan(input, Some(true), None));
+
+        let point = |x, y| Point { x, y };
+        assert_eq!(
+            events,
+            vec![
+                HullEvent::Push {
+                    point: point(0, 0),
+                    turn: None
+                },
+                HullEvent::Push {
+                    point: point(1, 1),
+                    turn: None
+                },
+                HullEvent::Pop {
+                    point: point(1, 1),
+                    turn: TurnType::Left
+                },
+                HullEvent::Push {
+                    point: point(2, 3),
+                    turn: None
+                },
+                HullEvent::Push {
+                    point: point(3, 0),
+                    turn: Some(TurnType::Right)
+                },
+            ]
+        );
+    }
 
     #[test]
     fn line_hull() {
@@ -1,12 +1,69 @@
 use crate::grahams_scan::grahams_scan;
-use crate::types::{Errors, Point, PointVector, Side, Tangent, TurnType};
+use crate::types::{Errors, HullEvent, Point, PointVector, Side, Tangent, TurnType};
 use crate::utils::{get_point_side, turn_type};
+use benchmark::counters::{count, Counter};
+use benchmark::trace::{span, span_with_size};
+use benchmark::tuning::profile;
+use benchmark::workspan::{measure, WorkSpan};
 use std::thread::ScopedJoinHandle;
 
+// the points of each processor, the last one also gets the rest of the division
+fn split(input: &PointVector, processors: usize) -> Vec<PointVector> {
+    let splits = input.points.len() / processors;
+    (0..processors)
+        .map(|i| PointVector {
+            points: if i == processors - 1 {
+                input.points[i * splits..].to_vec()
+            } else {
+                input.points[i * splits..(i + 1) * splits].to_vec()
+            },
+        })
+        .collect()
+}
+
+// at most one processor per cutoff points, and every split needs two points for its scan
+fn clamp_processors(points: usize, processors: usize, cutoff: usize) -> usize {
+    processors.min((points / cutoff.max(2)).max(1))
+}
+
 pub fn grahams_scan_parallel(
-    mut input: PointVector,
+    input: PointVector,
+    sort_input: Option<bool>,
+    processors: Option<usize>,
+) -> PointVector {
+    grahams_scan_parallel_observed(input, sort_input, processors, &mut |_| {})
+}
+
+/**
+    The parallel Graham's scan that reports the sub-hulls, the tangent candidates and the chosen
+    bridges to the observer. The observer is only called from the calling thread.
+*/
+pub fn grahams_scan_parallel_observed(
+    input: PointVector,
     sort_input: Option<bool>,
     processors: Option<usize>,
+    observe: &mut impl FnMut(HullEvent),
+) -> PointVector {
+    // "auto" takes the processors and the cutoff from the tuning profile
+    let (processors, cutoff) = profile().resolve(
+        "grahams_scan_parallel",
+        input.points.len(),
+        processors.unwrap_or(1),
+        1,
+    );
+    grahams_scan_parallel_with_cutoff(input, sort_input, processors, cutoff, observe)
+}
+
+/**
+    The parallel Graham's scan on at most the given number of processors, using fewer of them if
+    a processor would otherwise scan less than cutoff points.
+*/
+pub fn grahams_scan_parallel_with_cutoff(
+    mut input: PointVector,
+    sort_input: Option<bool>,
+    processors: usize,
+    cutoff: usize,
+    observe: &mut impl FnMut(HullEvent),
 ) -> PointVector {
     // most of the time the input is already sorted by x coordinate O(n log n),
     // but allow to also sort if needed
@@ -14,30 +71,31 @@ pub fn grahams_scan_parallel(
         input.points.sort_by_key(|Point { x, y: _ }| *x);
     }
 
-    let processors = processors.unwrap_or(1);
+    let processors = clamp_processors(input.points.len(), processors, cutoff);
+    let _span = span_with_size("parallel hull", input.points.len());
 
-    let splits = input.points.len() / processors;
+    let mut splits = split(&input, processors);
     let mut upper_hulls: Vec<PointVector> = Vec::with_capacity(processors);
 
     // calculate upper hulls in parallel for subsets of all points
     std::thread::scope(|scope| {
-        let last_split = PointVector {
-            points: input.points[(processors - 1) * splits..].to_vec(),
-        };
-
-        let mut handles: Vec<ScopedJoinHandle<PointVector>> = Vec::with_capacity(processors - 1);
+        let last_split = splits.pop().unwrap();
 
         // store handles of each thread for the first p-1 splits
-        for i in 1..processors {
-            let current_split = PointVector {
-                points: input.points[(i - 1) * splits..i * splits].to_vec(),
-            };
-
-            let handle = scope.spawn(|| grahams_scan(current_split, Option::from(false), None));
-            handles.push(handle);
-        }
+        let handles: Vec<ScopedJoinHandle<PointVector>> = splits
+            .into_iter()
+            .map(|current_split| {
+                scope.spawn(|| {
+                    let _span = span_with_size("sub-hull scan", current_split.points.len());
+                    grahams_scan(current_split, Option::from(false), None)
+                })
+            })
+            .collect();
         // get result from last split of Points
-        let last_result = grahams_scan(last_split, Option::from(false), None);
+        let last_result = {
+            let _span = span_with_size("sub-hull scan", last_split.points.len());
+            grahams_scan(last_split, Option::from(false), None)
+        };
 
         // collect all results
         for handle in handles {
@@ -47,16 +105,65 @@ pub fn grahams_scan_parallel(
         }
         upper_hulls.push(last_result);
     });
+    for (index, hull) in upper_hulls.iter().enumerate() {
+        observe(HullEvent::SubHull {
+            index,
+            hull: hull.clone(),
+        });
+    }
+
+    merge_upper_hulls(upper_hulls, observe)
+}
+
+/**
+    Runs `grahams_scan_parallel` on a virtual machine with the given number of processors: the
+    scans of all splits in parallel, followed by the merge of their upper hulls, every task on the
+    calling thread. Sorting the input is not counted.
+*/
+pub fn simulate_grahams_scan_parallel(
+    mut input: PointVector,
+    sort_input: Option<bool>,
+    processors: Option<usize>,
+) -> (PointVector, WorkSpan) {
+    if sort_input.unwrap_or(true) {
+        input.points.sort_by_key(|Point { x, y: _ }| *x);
+    }
+    let processors = clamp_processors(input.points.len(), processors.unwrap_or(1), 1);
+
+    let mut upper_hulls: Vec<PointVector> = Vec::with_capacity(processors);
+    let scans = WorkSpan::parallel(split(&input, processors).into_iter().map(|points| {
+        let (hull, scan) = measure(|| grahams_scan(points, Option::from(false), None));
+        upper_hulls.push(hull);
+        scan
+    }));
+    let (hull, merge) = measure(|| merge_upper_hulls(upper_hulls, &mut |_| {}));
+    (hull, scans.then(merge))
+}
+
+/**
+    Joins the upper hulls of consecutive splits, from left to right, by the bridges between them.
+    This part runs on a single thread.
+*/
+fn merge_upper_hulls(
+    mut upper_hulls: Vec<PointVector>,
+    observe: &mut impl FnMut(HullEvent),
+) -> PointVector {
+    let processors = upper_hulls.len();
 
     // merge upper hulls
     let mut i = 0;
     while i < processors - 1 {
+        let _span = span("bridge");
         let mut tangents: Vec<Tangent> = vec![]; // indices of the upper hulls and indices of the points that form the tangent
         let left_hull = upper_hulls[i].clone();
 
         for (j, right_hull) in upper_hulls.iter().enumerate().take(processors).skip(i + 1) {
             // find tangent between i and j
             let (l, r) = get_tangent(&left_hull, right_hull);
+            observe(HullEvent::TangentCandidate {
+                left: left_hull.points[l],
+                right: right_hull.points[r],
+            });
             tangents.push(Tangent {
                 left_hull_idx: i,
                 right_hull_idx: j,
@@ -114,6 +221,10 @@ pub fn grahams_scan_parallel(
         let tangent = &tangents[idx_in_tangents];
 
         let right_uh_idx = tangent.right_hull_idx;
+        observe(HullEvent::Bridge {
+            left: upper_hulls[i].points[tangent.left_point_idx],
+            right: upper_hulls[right_uh_idx].points[tangent.right_point_idx],
+        });
 
         // delete points in first upper hull after tangent origin point
         upper_hulls[i].points.truncate(tangent.left_point_idx + 1);
@@ -243,14 +354,14 @@ fn get_tangent_from_point(hull: &PointVector, point: Point) -> Result<usize, Err
         Ok(0)
     } else if hull.points.len() == 2 {
         let turn = turn_type(point, hull.points[0], hull.points[1]);
-        return match (point_side, turn) {
+        match (point_side, turn) {
             (Side::Left, TurnType::Left) => Ok(1),
             (Side::Left, TurnType::Right) => Ok(0),
             (Side::Left, TurnType::Straight) => Ok(1),
             (Side::Right, TurnType::Left) => Ok(0),
             (Side::Right, TurnType::Right) => Ok(1),
             (Side::Right, TurnType::Straight) => Ok(0),
-        };
+        }
     } else {
         // hull is at least 3 elements long
 
@@ -262,6 +373,7 @@ fn get_tangent_from_point(hull: &PointVector, point: Point) -> Result<usize, Err
         let mut candidate: usize;
 
         loop {
+            count(Counter::TangentSteps, 1);
             candidate = (high + low) / 2;
             // if moved to edge, this is the connection to the upper hull
             if candidate == 0 {
@@ -310,11 +422,110 @@ fn get_tangent_from_point(hull: &PointVector, point: Point) -> Result<usize, Err
 
 #[cfg(test)]
 mod test {
+    use crate::grahams_scan::grahams_scan;
     use crate::grahams_scan_parallel::{
-        get_tangent, get_tangent_from_point, grahams_scan_parallel, is_upper_hull_tangent,
+        get_tangent, get_tangent_from_point, grahams_scan_parallel, grahams_scan_parallel_observed,
+        grahams_scan_parallel_with_cutoff, is_upper_hull_tangent, simulate_grahams_scan_parallel,
     };
-    use crate::input_generation::{Curve, Line};
-    use crate::types::{Point, PointVector};
+    use crate::input_generation::{Curve, Line, UniformSquare};
+    use crate::types::{HullEvent, Point, PointVector};
+    use benchmark::trace::record_locally;
+    use benchmark::tuning::AUTO_THREADS;
+
+    #[test]
+    fn traced_sub_hull_scans_and_bridges() {
+        let input = UniformSquare::get_input_with_seed(1000, 3);
+        let (_, spans) = record_locally(|| grahams_scan_parallel(input, Some(true), Some(4)));
+
+        let sizes = |name: &str| {
+            spans
+                .iter()
+                .filter(|s| s.name == name)
+                .map(|s| s.size)
+                .collect::<Vec<Option<usize>>>()
+        };
+        // the calling thread scans the last split itself, the other three are spawned
+        assert_eq!(sizes("sub-hull scan"), vec![Some(250)]);
+        assert_eq!(sizes("parallel hull"), vec![Some(1000)]);
+        // a bridge can skip sub-hulls, so there are between one and three of them
+        assert!(spans.iter().any(|s| s.name == "bridge"));
+    }
+
+    #[test]
+    fn simulated_on_a_virtual_machine() {
+        let input = UniformSquare::get_input_with_seed(10000, 4);
+        let (sequential_hull, sequential) =
+            simulate_grahams_scan_parallel(input.clone(), Some(true), Some(1));
+        let (hull, parallel) = simulate_grahams_scan_parallel(input.clone(), Some(true), Some(8));
+        assert_eq!(hull, grahams_scan_parallel(input, Some(true), Some(8)));
+        assert_eq!(sequential_hull, hull);
+
+        // one processor has no parallelism and more work than the orientation tests of the points
+        assert_eq!(sequential.work, sequential.span);
+        assert!(sequential.work >= 10000);
+        // the scans of the splits are about as much work, but they run in parallel
+        assert!(parallel.span * 4 < sequential.span);
+        assert!(parallel.brent(8) < sequential.brent(1));
+        assert!(parallel.parallelism() > 4.0);
+    }
+
+    #[test]
+    fn more_processors_than_pairs_of_points() {
+        let input = UniformSquare::get_input_with_seed(100, 5);
+        let expected = grahams_scan(input.clone(), Some(true), None);
+        let (hull, simulated) = simulate_grahams_scan_parallel(input.clone(), Some(true), Some(64));
+        assert_eq!(hull, expected);
+        assert!(simulated.work >= 100);
+        assert_eq!(grahams_scan_parallel(input, Some(true), Some(64)), expected);
+    }
+
+    #[test]
+    fn observed_sub_hulls_and_bridges() {
+        let input = UniformSquare::get_input_with_seed(1000, 3);
+        let mut events = vec![];
+        let hull = grahams_scan_parallel_observed(input.clone(), Some(true), Some(4), &mut |e| {
+            events.push(e)
+        });
+        assert_eq!(hull, grahams_scan_parallel(input, Some(true), Some(4)));
+
+        let sub_hulls = events
+            .iter()
+            .filter(|e| matches!(e, HullEvent::SubHull { .. }))
+            .count();
+        assert_eq!(sub_hulls, 4);
+        // every bridge is one of the candidates before it and both its ends are on the hull
+        for (i, event) in events.iter().enumerate() {
+            if let HullEvent::Bridge { left, right } = event {
+                assert!(events[..i].contains(&HullEvent::TangentCandidate {
+                    left: *left,
+                    right: *right
+                }));
+                assert!(hull.points.contains(left) && hull.points.contains(right));
+            }
+        }
+    }
+
+    #[test]
+    fn cutoff_limits_the_sub_hulls() {
+        let input = UniformSquare::get_input_with_seed(1000, 4);
+        let expected = grahams_scan_parallel(input.clone(), Some(true), Some(1));
+        for (cutoff, expected_sub_hulls) in [(1, 4), (300, 3), (1000, 1), (5000, 1)] {
+            let mut sub_hulls = 0;
+            let hull =
+                grahams_scan_parallel_with_cutoff(input.clone(), Some(true), 4, cutoff, &mut |e| {
+                    if let HullEvent::SubHull { .. } = e {
+                        sub_hulls += 1;
+                    }
+                });
+            assert_eq!(hull, expected);
+            assert_eq!(sub_hulls, expected_sub_hulls, "cutoff {}", cutoff);
+        }
+        // "auto" falls back to all cpus without a profile
+        assert_eq!(
+            grahams_scan_parallel(input, Some(true), Some(AUTO_THREADS)),
+            expected
+        );
+    }
 
     #[test]
     fn line_hull_2p() {
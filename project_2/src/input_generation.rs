@@ -10,10 +10,10 @@ pub struct Line();
 
 // implementing the input generation
 impl UniformSquare {
-    pub fn get_input(amount: i64) -> PointVector {
+    pub fn get_input_with_seed(amount: i64, seed: u64) -> PointVector {
         let side_length = f64::sqrt(amount as f64) as i64 * 5;
-        // get x and y randomly
-        let rand = Rng::new();
+        // get x and y randomly, the same seed gives the same points
+        let rand = Rng::with_seed(seed);
         PointVector {
             points: (0..amount)
                 .map(|_| Point {
@@ -26,11 +26,11 @@ impl UniformSquare {
 }
 
 impl UniformCircle {
-    pub fn get_input(amount: i64) -> PointVector {
+    pub fn get_input_with_seed(amount: i64, seed: u64) -> PointVector {
         let radius = f64::sqrt(amount as f64) as i64 * 5;
         // use rejection sampling
 
-        let rand = Rng::new();
+        let rand = Rng::with_seed(seed);
         let mut vec: Vec<Point> = Vec::with_capacity(amount as usize);
 
         while vec.len() < amount as usize {
@@ -73,14 +73,26 @@ impl Line {
 
 #[cfg(test)]
 mod test {
-    use crate::input_generation::{Curve, InverseCurve, Point, UniformCircle};
+    use crate::input_generation::{Curve, InverseCurve, Point, UniformCircle, UniformSquare};
 
     #[test]
     fn uniform_circle_test() {
-        let uniform_circle_input = UniformCircle::get_input(10);
+        let uniform_circle_input = UniformCircle::get_input_with_seed(10, 1);
         assert_eq!(uniform_circle_input.points.len(), 10);
     }
 
+    #[test]
+    fn same_seed_same_points() {
+        assert_eq!(
+            UniformSquare::get_input_with_seed(100, 42),
+            UniformSquare::get_input_with_seed(100, 42)
+        );
+        assert_eq!(
+            UniformCircle::get_input_with_seed(100, 42),
+            UniformCircle::get_input_with_seed(100, 42)
+        );
+    }
+
     #[test]
     fn curve_test() {
         let curve_input = Curve::get_input(10);
@@ -1,11 +1,26 @@
-use crate::gift_wrapping::gift_wrapping_upper_hull;
-use crate::grahams_scan::grahams_scan;
-use crate::grahams_scan_parallel::grahams_scan_parallel;
-use crate::input_generation::{Curve, InverseCurve, Line, UniformCircle, UniformSquare};
-use crate::plotting::{plot, plot_log, plot_upper_hull, plot_upper_hull_points};
-use crate::types::{ConvexHullAlgorithm, Experiment, InputFunction, Plot, Point};
-use std::time::Instant;
-
+use crate::animation::animate;
+use crate::gift_wrapping::gift_wrapping_observed;
+use crate::grahams_scan::grahams_scan_observed;
+use crate::grahams_scan_parallel::{
+    grahams_scan_parallel, grahams_scan_parallel_observed, grahams_scan_parallel_with_cutoff,
+    simulate_grahams_scan_parallel,
+};
+use crate::plotting::{plot_hulls, HullOutput, HullPlot};
+use crate::workload::{find_algorithm, generate, HullWorkload};
+use benchmark::compare::compare_command;
+use benchmark::runner::{plot_results, run_spec, Workload};
+use benchmark::spec::BenchmarkSpec;
+use benchmark::trace::{record, write_chrome_trace};
+use benchmark::tuning::{
+    calibrate, candidate_threads, load_profile, parse_threads, seconds, TuningProfile, AUTO_THREADS,
+};
+use benchmark::workspan::{simulation_table, WorkSpan};
+use std::env;
+use std::error::Error;
+use std::process::exit;
+use std::thread::available_parallelism;
+
+mod animation;
 mod gift_wrapping;
 mod grahams_scan;
 mod grahams_scan_parallel;
@@ -13,363 +28,280 @@ mod input_generation;
 mod plotting;
 mod types;
 mod utils;
+mod workload;
 
-fn main() {
-    let input_sizes: Vec<i64> = (5..=8).map(|exp| 10i64.pow(exp)).collect();
-
-    different_inputs_runtime(input_sizes.clone());
-    //upper_hull_size(input_sizes.clone());
-    parallel_runtime(input_sizes);
-
-    //upper_hull()
-}
-
-fn different_inputs_runtime(input_sizes: Vec<i64>) {
-    // grahams scan, gift wrapping, parallel grahams scan (8 cores) on all inputs
-    let input_types: Vec<(InputFunction, String)> = vec![
-        (UniformSquare::get_input, "Uniform Square".to_string()),
-        (UniformCircle::get_input, "Uniform Circle".to_string()),
-        (Curve::get_input, "Downwards Curve".to_string()),
-        (InverseCurve::get_input, "Upwards Curve".to_string()),
-        (Line::get_input, "Line".to_string()),
-    ];
-    
-    let y_range = 5e-7..4e-5;
-
-    let grahams_all_inputs = Plot {
-        title: "Grahams Scan on different Inputs".to_string(),
-        path: "project_2/plots/grahams_inputs.png".to_string(),
-        experiments: vec![],
-        input_sizes: input_sizes.clone(),
-        algorithm: grahams_scan,
-        args: (Option::from(true), None),
-        y_range: y_range.clone(),
-    };
-
-    let gift_wrapping_all_inputs = Plot {
-        title: "Gift Wrapping on different Inputs".to_string(),
-        path: "project_2/plots/gift_wrapping_inputs.png".to_string(),
-        experiments: vec![],
-        input_sizes: input_sizes.clone(),
-        algorithm: gift_wrapping_upper_hull,
-        args: (None, None),
-        y_range: 1e-6..1e-1,
-    };
-
-    let grahams_parallel_all_inputs = Plot {
-        title: "8-threaded Parallel Grahams Scan on different Inputs".to_string(),
-        path: "project_2/plots/grahams_parallel_inputs.png".to_string(),
-        experiments: vec![],
-        input_sizes: input_sizes.clone(),
-        algorithm: grahams_scan_parallel,
-        args: (Option::from(true), Option::from(8)),
-        y_range: y_range.clone(),
-    };
-
-    let mut input_plots = vec![
-        grahams_all_inputs,
-        gift_wrapping_all_inputs,
-        grahams_parallel_all_inputs,
-    ];
-
-    for (function, name) in input_types {
-        for input_plot in input_plots.iter_mut() {
-            println!("{}, {}", name, input_plot.title);
+// the thread counts and cutoffs chosen by "tune", used wherever the thread count is "auto"
+const TUNING_PROFILE: &str = "project_2/tuning.toml";
 
-            let mut experiment = Experiment {
-                name: name.clone(),
-                run_times: vec![],
-            };
+fn main() {
+    // the experiments and plots are described in a benchmark spec, see benchmarks.toml
+    // "plot" redraws the plots from the results file of the spec without running it again
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // a broken profile must not stop `tune` from writing a new one, "auto" then uses all cpus
+    if let Err(error) = load_profile(TUNING_PROFILE) {
+        println!("ignoring {}: {}", TUNING_PROFILE, error);
+    }
 
-            for input_size in &input_sizes {
-                // skip gift wrapping for large sizes on Curve Inputs and
-                // slightly larger sizes on Uniform Circle Inputs
-                if input_plot.algorithm as usize == gift_wrapping_upper_hull as usize
-                    && ((function as usize == Curve::get_input as usize && input_size > &100000)
-                        || (function as usize == UniformCircle::get_input as usize
-                            && input_size > &10000000))
-                {
-                    break;
-                }
+    // "compare" compares two results files and fails if the candidate has regressions
+    if args.first().is_some_and(|arg| arg == "compare") {
+        match compare_command(&HullWorkload(), &args[1..]) {
+            Ok(false) => {}
+            Ok(true) => exit(1),
+            Err(error) => {
+                println!("{}", error);
+                exit(2);
+            }
+        }
+        return;
+    }
 
-                let upper_hull_algorithm = input_plot.algorithm;
+    // "hull" draws the hulls of one input instead of running benchmarks
+    if args.first().is_some_and(|arg| arg == "hull") {
+        if let Err(error) = draw_hulls(&args[1..]) {
+            println!("{}", error);
+            exit(2);
+        }
+        return;
+    }
 
-                // calculate the runtime 4 additional times to get the more consistent average
-                let total_runtime: f64 = (0..5)
-                    .map(|_| {
-                        let input = function(*input_size);
-                        let now = Instant::now();
-                        upper_hull_algorithm(input, input_plot.args.0, input_plot.args.1);
-                        now.elapsed().as_secs_f64()
-                    })
-                    .sum();
+    // "animate" renders every step of one algorithm on one input
+    if args.first().is_some_and(|arg| arg == "animate") {
+        if let Err(error) = animate_algorithm(&args[1..]) {
+            println!("{}", error);
+            exit(2);
+        }
+        return;
+    }
 
-                let avg_runtime = total_runtime / 5.0;
-                println!("{}: {}", input_size, avg_runtime);
+    // "trace" writes the spans of the threads of the parallel Graham's scan as a Chrome trace
+    if args.first().is_some_and(|arg| arg == "trace") {
+        if let Err(error) = trace_parallel_hull(&args[1..]) {
+            println!("{}", error);
+            exit(2);
+        }
+        return;
+    }
 
-                experiment.run_times.push(avg_runtime * 1000.0 / *input_size as f64);
+    // "simulate" runs the parallel Graham's scan on a virtual machine
+    if args.first().is_some_and(|arg| arg == "simulate") {
+        match simulate_parallel_hull(&args[1..]) {
+            Ok(table) => print!("{}", table),
+            Err(error) => {
+                println!("{}", error);
+                exit(2);
             }
+        }
+        return;
+    }
 
-            input_plot.experiments.push(experiment);
+    // "tune" calibrates the thread count and cutoff of the parallel Graham's scan per input size
+    if args.first().is_some_and(|arg| arg == "tune") {
+        if let Err(error) = tune(&args[1..]) {
+            println!("{}", error);
+            exit(2);
         }
+        return;
     }
-    // convert to plots by input generation
-    let uniform_square_plot = Plot {
-        title: "Performance on Uniformly Distributed Points in a Square".to_string(),
-        path: "project_2/plots/uniform_square_plot.png".to_string(),
-        experiments: vec![
-            Experiment {
-                name: "Grahams Scan".to_string(),
-                run_times: input_plots[0].experiments[0].run_times.clone(),
-            },
-            Experiment {
-                name: "Gift Wrapping".to_string(),
-                run_times: input_plots[1].experiments[0].run_times.clone(),
-            },
-            Experiment {
-                name: "Parallel Grahams Scan".to_string(),
-                run_times: input_plots[2].experiments[0].run_times.clone(),
-            },
-        ],
-        input_sizes: input_sizes.clone(),
-        algorithm: grahams_scan_parallel, // not needed
-        args: (None, None),
-        y_range: 1e-6..1e-1,
-    };
-    let uniform_circle_plot = Plot {
-        title: "Performance on Uniformly Distributed Points in a Circle".to_string(),
-        path: "project_2/plots/uniform_circle_plot.png".to_string(),
-        experiments: vec![
-            Experiment {
-                name: "Grahams Scan".to_string(),
-                run_times: input_plots[0].experiments[1].run_times.clone(),
-            },
-            Experiment {
-                name: "Gift Wrapping".to_string(),
-                run_times: input_plots[1].experiments[1].run_times.clone(),
-            },
-            Experiment {
-                name: "Parallel Grahams Scan".to_string(),
-                run_times: input_plots[2].experiments[1].run_times.clone(),
-            },
-        ],
-        input_sizes: input_sizes.clone(),
-        algorithm: grahams_scan_parallel, // not needed
-        args: (None, None),
-        y_range: 1e-6..1e-1,
-    };
-    let curve_plot = Plot {
-        title: "Performance on Points on a Downwards Curve".to_string(),
-        path: "project_2/plots/curve_plot.png".to_string(),
-        experiments: vec![
-            Experiment {
-                name: "Grahams Scan".to_string(),
-                run_times: input_plots[0].experiments[2].run_times.clone(),
-            },
-            Experiment {
-                name: "Gift Wrapping".to_string(),
-                run_times: input_plots[1].experiments[2].run_times.clone(),
-            },
-            Experiment {
-                name: "Parallel Grahams Scan".to_string(),
-                run_times: input_plots[2].experiments[2].run_times.clone(),
-            },
-        ],
-        input_sizes: input_sizes.clone(),
-        algorithm: grahams_scan_parallel, // not needed
-        args: (None, None),
-        y_range: 1e-6..1e-1,
-    };
-    let upwards_curve_plot = Plot {
-        title: "Performance on Points on an Upwards Curve".to_string(),
-        path: "project_2/plots/upwards_curve_plot.png".to_string(),
-        experiments: vec![
-            Experiment {
-                name: "Grahams Scan".to_string(),
-                run_times: input_plots[0].experiments[3].run_times.clone(),
-            },
-            Experiment {
-                name: "Gift Wrapping".to_string(),
-                run_times: input_plots[1].experiments[3].run_times.clone(),
-            },
-            Experiment {
-                name: "Parallel Grahams Scan".to_string(),
-                run_times: input_plots[2].experiments[3].run_times.clone(),
-            },
-        ],
-        input_sizes: input_sizes.clone(),
-        algorithm: grahams_scan_parallel, // not needed
-        args: (None, None),
-        y_range: 5e-7..2e-5,
-    };
-    let line_plot = Plot {
-        title: "Performance on Points on a Line".to_string(),
-        path: "project_2/plots/line_plot.png".to_string(),
-        experiments: vec![
-            Experiment {
-                name: "Grahams Scan".to_string(),
-                run_times: input_plots[0].experiments[4].run_times.clone(),
-            },
-            Experiment {
-                name: "Gift Wrapping".to_string(),
-                run_times: input_plots[1].experiments[4].run_times.clone(),
-            },
-            Experiment {
-                name: "Parallel Grahams Scan".to_string(),
-                run_times: input_plots[2].experiments[4].run_times.clone(),
-            },
-        ],
-        input_sizes: input_sizes.clone(),
-        algorithm: grahams_scan_parallel, // not needed
-        args: (None, None),
-        y_range: 5e-7..2e-5,
-    };
 
-    // input_plots are grouped by solver
-    //for solver_plot in input_plots.iter() {
-    //    plot(solver_plot.clone());
-    //}
-    plot(input_plots[0].clone());
-    plot_log(input_plots[1].clone());
-    plot(input_plots[2].clone());
-    
-    
-    // plot grouped by input generation
-    plot_log(uniform_square_plot);
-    plot_log(uniform_circle_plot);
-    plot_log(curve_plot);
-    plot(upwards_curve_plot);
-    plot(line_plot);
+    let only_plot = args.first().is_some_and(|arg| arg == "plot");
+    if only_plot {
+        args.remove(0);
+    }
+    let path = args
+        .first()
+        .map(String::as_str)
+        .unwrap_or("project_2/benchmarks.toml");
+
+    let result = BenchmarkSpec::load(path).and_then(|spec| {
+        if only_plot {
+            plot_results(&HullWorkload(), &spec)
+        } else {
+            run_spec(&HullWorkload(), &spec).map(|_| ())
+        }
+    });
+    if let Err(error) = result {
+        println!("{}", error);
+    }
 }
 
-fn parallel_runtime(input_sizes: Vec<i64>) {
-    // grahams scan vs parallel grahams scan on one input with different cores
-    let threads = vec![1, 2, 4, 8, 16];
-    let input_function = UniformSquare::get_input;
-
-    let mut grahams_parallel_different_threads = Plot {
-        title: "Multithreaded Parallel Grahams Scan".to_string(),
-        path: "project_2/plots/grahams_parallel_threads.png".to_string(),
-        experiments: vec![],
-        input_sizes: input_sizes.clone(),
-        algorithm: grahams_scan_parallel,
-        args: (Option::from(false), Option::from(1)),
-        y_range: 5e-7..2e-5,
+/**
+    `hull <generator> <n> <plot.png|plot.svg> [algorithm ...]` draws the input points with the
+    upper and lower hulls of the given algorithms (all if none are given). The input is generated
+    with a fixed seed, so the same command always draws the same points.
+*/
+fn draw_hulls(args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args.len() < 3 {
+        return Err("usage: hull <generator> <n> <plot.png|plot.svg> [algorithm ...]".into());
+    }
+    let size: usize = args[1]
+        .parse()
+        .map_err(|_| format!("invalid input size {}", args[1]))?;
+    let input = generate(&args[0], size, 0).ok_or(format!("unknown generator {}", args[0]))?;
+
+    let workload = HullWorkload();
+    let algorithms = if args.len() > 3 {
+        args[3..].to_vec()
+    } else {
+        workload.algorithms(None)
     };
+    let outputs = algorithms
+        .iter()
+        .map(|name| {
+            let algorithm = find_algorithm(name).ok_or(format!("unknown algorithm {}", name))?;
+            Ok(HullOutput::compute(
+                &workload.label(name),
+                algorithm,
+                &input,
+                Some(AUTO_THREADS),
+            ))
+        })
+        .collect::<Result<Vec<HullOutput>, String>>()?;
+
+    let title = format!("{}, n={}", workload.label(&args[0]), size);
+    let plot = HullPlot {
+        title: &title,
+        input: &input,
+        outputs: &outputs,
+        label_extremes: true,
+        highlight_degeneracies: true,
+        font: "sans-serif",
+    };
+    plot_hulls(&args[2], (800, 800), &plot)
+}
 
-    for thread in threads {
-        println!(
-            "{} threads, {}",
-            thread, grahams_parallel_different_threads.title
+/**
+    `animate <algorithm> <generator> <n> <animation.gif|frame directory> [threads|auto]` records
+    the steps of Graham's scan, gift wrapping or the parallel Graham's scan (with 4 threads by
+    default) on a generated input and renders one frame per step.
+*/
+fn animate_algorithm(args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args.len() < 4 {
+        return Err(
+            "usage: animate <algorithm> <generator> <n> <animation.gif|frame directory> \
+             [threads|auto]"
+                .into(),
         );
-
-        let mut experiment = Experiment {
-            name: format!("{} threads", thread).to_string(),
-            run_times: vec![],
-        };
-
-        for input_size in &input_sizes {
-            let upper_hull_algorithm = grahams_parallel_different_threads.algorithm;
-
-            // calculate the runtime 5 times to get the more consistent average
-            let total_runtime: f64 = (0..5)
-                .map(|_| {
-                    let mut input = input_function(*input_size);
-                    input.points.sort_by_key(|Point { x, y: _ }| *x);
-
-                    let now = Instant::now();
-                    upper_hull_algorithm(
-                        input,
-                        grahams_parallel_different_threads.args.0,
-                        Some(thread),
-                    );
-                    now.elapsed().as_secs_f64()
-                })
-                .sum();
-
-            let avg_runtime = total_runtime / 5.0;
-            println!("{}: {}", input_size, avg_runtime);
-
-            experiment.run_times.push(avg_runtime * 1000.0 / *input_size as f64);
-        }
-        grahams_parallel_different_threads
-            .experiments
-            .push(experiment);
     }
-
-    // plot parallel comparison
-    plot(grahams_parallel_different_threads);
-}
-
-fn upper_hull_size(input_sizes: Vec<i64>) {
-    // upper hull points given the input size
-    let mut upper_hull_points = Plot {
-        title: "Points on the Upper Hull".to_string(),
-        path: "project_2/plots/upper_hull_points.png".to_string(),
-        experiments: vec![],
-        input_sizes: input_sizes.clone(),
-        algorithm: grahams_scan,
-        args: (Option::from(true), None),
-        y_range: 3f64..4000f64,
+    let size: usize = args[2]
+        .parse()
+        .map_err(|_| format!("invalid input size {}", args[2]))?;
+    let threads = match args.get(4) {
+        Some(threads) => parse_threads(threads)?,
+        None => 4,
     };
-
-    let runs: Vec<(InputFunction, ConvexHullAlgorithm, String)> = vec![
-        (
-            UniformSquare::get_input,
-            gift_wrapping_upper_hull,
-            "Uniform Square, Gift Wrapping".to_string(),
-        ),
-        (
-            UniformCircle::get_input,
-            gift_wrapping_upper_hull,
-            "Uniform Circle, Gift Wrapping".to_string(),
-        ),
-        (
-            UniformSquare::get_input,
-            grahams_scan,
-            "Uniform Square, Grahams Scan".to_string(),
-        ),
-        (
-            UniformCircle::get_input,
-            grahams_scan,
-            "Uniform Circle, Grahams Scan".to_string(),
-        ),
-    ];
-
-    for (input_function, algorithm, name) in runs {
-        println!("{}, {}", name, upper_hull_points.title);
-
-        let mut experiment = Experiment {
-            name: name.clone(),
-            run_times: vec![],
-        };
-
-        for input_size in &input_sizes {
-            // calculate the runtime 4 additional times to get the more consistent average
-            let total_points: usize = (0..5)
-                .map(|_| {
-                    let input = input_function(*input_size);
-                    algorithm(input, upper_hull_points.args.0, upper_hull_points.args.1)
-                        .points
-                        .len()
-                })
-                .sum();
-
-            let avg_points = total_points / 5;
-            println!("{}: {}", input_size, avg_points);
-
-            experiment.run_times.push(avg_points as f64);
+    let input = generate(&args[1], size, 0).ok_or(format!("unknown generator {}", args[1]))?;
+
+    let mut events = vec![];
+    let mut observe = |event| events.push(event);
+    let hull = match args[0].as_str() {
+        "grahams_scan" => grahams_scan_observed(input.clone(), Some(true), &mut observe),
+        "gift_wrapping" => gift_wrapping_observed(input.clone(), &mut observe),
+        "grahams_scan_parallel" => {
+            grahams_scan_parallel_observed(input.clone(), Some(true), Some(threads), &mut observe)
         }
+        algorithm => return Err(format!("unknown algorithm {}", algorithm).into()),
+    };
+
+    let workload = HullWorkload();
+    let title = format!("{}, {}", workload.label(&args[0]), workload.label(&args[1]));
+    animate(&args[3], (640, 640), &title, &input, &events, &hull, 500)?;
+    println!("{} frames written to {}", events.len() + 1, args[3]);
+    Ok(())
+}
 
-        upper_hull_points.experiments.push(experiment);
+/**
+    `trace <generator> <n> <threads|auto> <trace.json>` runs the parallel Graham's scan once and writes
+    the sub-hull scans and bridge computations of all threads in the Chrome trace event format,
+    which can be opened in chrome://tracing or Perfetto.
+*/
+fn trace_parallel_hull(args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args.len() < 4 {
+        return Err("usage: trace <generator> <n> <threads|auto> <trace.json>".into());
     }
+    let size: usize = args[1]
+        .parse()
+        .map_err(|_| format!("invalid input size {}", args[1]))?;
+    let threads = parse_threads(&args[2])?;
+    let input = generate(&args[0], size, 0).ok_or(format!("unknown generator {}", args[0]))?;
+
+    let (_, spans) = record(|| grahams_scan_parallel(input, Some(true), Some(threads)));
+    write_chrome_trace(&args[3], "project_2", &spans)?;
+    println!("{} spans written to {}", spans.len(), args[3]);
+    Ok(())
+}
 
-    // plot upper hull points by input size
-    plot_upper_hull_points(upper_hull_points);
+/**
+    `simulate <generator> <n> [threads ...]` prints the work, span and Brent bound of the parallel
+    Graham's scan on a virtual machine with each thread count (1 to 64 by default). The input is
+    generated with a fixed seed and the cost is counted in orientation tests and tangent search
+    steps, so the table is the same on every machine.
+*/
+fn simulate_parallel_hull(args: &[String]) -> Result<String, Box<dyn Error>> {
+    if args.len() < 2 {
+        return Err("usage: simulate <generator> <n> [threads ...]".into());
+    }
+    let size: usize = args[1]
+        .parse()
+        .map_err(|_| format!("invalid input size {}", args[1]))?;
+    let threads = if args.len() > 2 {
+        args[2..]
+            .iter()
+            .map(|p| match parse_threads(p)? {
+                // the simulation does not depend on the machine, so it needs a thread count
+                AUTO_THREADS => Err(format!("invalid thread count {}", p)),
+                threads => Ok(threads),
+            })
+            .collect::<Result<Vec<usize>, String>>()?
+    } else {
+        vec![1, 2, 4, 8, 16, 32, 64]
+    };
+    let input = generate(&args[0], size, 0).ok_or(format!("unknown generator {}", args[0]))?;
+
+    let runs: Vec<(usize, WorkSpan)> = threads
+        .iter()
+        .map(|&p| {
+            (
+                p,
+                simulate_grahams_scan_parallel(input.clone(), Some(true), Some(p)).1,
+            )
+        })
+        .collect();
+    let title = format!("{}, n={}", HullWorkload().label(&args[0]), size);
+    Ok(simulation_table(&title, &runs))
 }
 
-fn upper_hull() {
-    let input = UniformSquare::get_input(10000);
-    let result = grahams_scan(input, Option::from(true), None);
-    plot_upper_hull(result);
+/**
+    `tune [generator]` measures the parallel Graham's scan on presorted inputs of 10^4 to 10^7
+    points of the generator (uniform_square by default) with every candidate thread count and
+    cutoff, and saves the fastest setting per size to the tuning profile.
+*/
+fn tune(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let generator = args.first().map_or("uniform_square", String::as_str);
+    generate(generator, 1, 0).ok_or(format!("unknown generator {}", generator))?;
+
+    let run = |size: usize, threads: usize, cutoff: usize| {
+        let mut input = generate(generator, size, 0).unwrap();
+        input.points.sort_by_key(|point| point.x);
+        seconds(|| {
+            grahams_scan_parallel_with_cutoff(input, Some(false), threads, cutoff, &mut |_| {});
+        })
+    };
+    let tuning = calibrate(
+        "grahams_scan_parallel",
+        &[10000, 100000, 1000000, 10000000],
+        &candidate_threads(),
+        &[1, 1024, 16384, 262144],
+        3,
+        &run,
+    );
+
+    let mut profile = TuningProfile::load(TUNING_PROFILE).unwrap_or_default();
+    profile
+        .tuning
+        .retain(|t| t.algorithm != "grahams_scan_parallel");
+    profile.tuning.extend(tuning);
+    profile.cpus = available_parallelism().map_or(1, |n| n.get());
+    profile.save(TUNING_PROFILE)?;
+    println!("profile written to {}", TUNING_PROFILE);
+    Ok(())
 }
@@ -1,207 +1,376 @@
-use crate::types::{Plot, Point, PointVector};
-use plotters::coord::types::{RangedCoordi32, RangedCoordi64};
+use crate::types::{ConvexHullAlgorithm, Point, PointVector, TurnType};
+use crate::utils::{is_inside_segment, lower_hull, turn_type};
+use benchmark::plotting::{color, font_family, render_file, Drawing};
+use plotters::coord::Shift;
 use plotters::prelude::*;
-use plotters::style::full_palette::ORANGE;
-
-pub fn plot_log(plot: Plot) {
-    let colors = [&RED, &GREEN, &BLUE, &ORANGE, &BLACK];
-
-    let x_first = (plot.input_sizes[0] as f64 * 0.8) as i64;
-    let x_last = (plot.input_sizes[plot.input_sizes.len() - 1] as f64 * 1.2) as i64;
-
-    let root = BitMapBackend::new(plot.path.as_str(), (640, 480)).into_drawing_area();
-    let _ = root.fill(&WHITE);
-    let root = root.margin(10, 10, 10, 10);
-
-    let mut chart = ChartBuilder::on(&root)
-        .caption(plot.title, ("sans-serif", 26).into_font())
-        .x_label_area_size(30)
-        .y_label_area_size(55)
-        .build_cartesian_2d((x_first..x_last).log_scale(), plot.y_range.log_scale())
-        .unwrap();
-
-    chart
-        .configure_mesh()
-        .max_light_lines(0)
-        .y_label_formatter(&|y| format!("{:.1e}", y))
-        .y_desc("Average Runtime in milliseconds / input size (logarithmic scale)")
-        .x_label_formatter(&|x| format!("{:.0e}", x))
-        .x_desc("Input Size (logarithmic scale)")
-        .draw()
-        .unwrap();
-
-    for (i, experiment) in plot.experiments.iter().enumerate() {
-        chart
-            .draw_series(
-                LineSeries::new(
-                    plot.input_sizes
-                        .iter()
-                        .zip(experiment.run_times.clone())
-                        .map(|(x, run_time)| (*x, run_time)),
-                    colors[i],
-                ).point_size(2),
-            )
-            .unwrap()
-            .label(experiment.name.clone())
-            .legend({
-                let color = colors[i];
-                move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color)
-            });
+use plotters::style::full_palette::{GREY, PURPLE};
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+use std::error::Error;
+
+// the upper and the lower hull an algorithm computed for the input, both from left to right
+#[derive(Debug, PartialEq, Clone)]
+pub struct HullOutput {
+    pub name: String,
+    pub upper: PointVector,
+    pub lower: PointVector,
+}
+
+impl HullOutput {
+    pub fn compute(
+        name: &str,
+        algorithm: ConvexHullAlgorithm,
+        input: &PointVector,
+        threads: Option<usize>,
+    ) -> HullOutput {
+        HullOutput {
+            name: name.to_string(),
+            upper: algorithm(input.clone(), Some(true), threads),
+            lower: lower_hull(algorithm, input, threads),
+        }
     }
 
-    chart
-        .configure_series_labels()
-        .position(SeriesLabelPosition::UpperLeft)
-        .border_style(BLACK)
-        .background_style(WHITE.mix(0.75))
-        .draw()
-        .unwrap();
+    fn vertices(&self) -> impl Iterator<Item = &Point> {
+        self.upper.points.iter().chain(self.lower.points.iter())
+    }
+}
 
-    root.present().unwrap();
+// the points that appear more than once in the input, each of them once
+pub fn duplicate_points(input: &PointVector) -> Vec<Point> {
+    let mut sorted = input.points.clone();
+    sorted.sort();
+    let mut duplicates: Vec<Point> = sorted
+        .windows(2)
+        .filter(|pair| pair[0] == pair[1])
+        .map(|pair| pair[0])
+        .collect();
+    duplicates.dedup();
+    duplicates
 }
 
-pub fn plot(plot: Plot) {
-    let colors = [&RED, &GREEN, &BLUE, &ORANGE, &BLACK];
-
-    let x_first = (plot.input_sizes[0] as f64 * 0.8) as i64;
-    let x_last = (plot.input_sizes[plot.input_sizes.len() - 1] as f64 * 1.2) as i64;
-
-    let root = BitMapBackend::new(plot.path.as_str(), (640, 480)).into_drawing_area();
-    let _ = root.fill(&WHITE);
-    let root = root.margin(10, 10, 10, 10);
-
-    let mut chart = ChartBuilder::on(&root)
-        .caption(plot.title, ("sans-serif", 26).into_font())
-        .x_label_area_size(30)
-        .y_label_area_size(55)
-        .build_cartesian_2d((x_first..x_last).log_scale(), plot.y_range)
-        .unwrap();
-
-    chart
-        .configure_mesh()
-        .max_light_lines(0)
-        .y_label_formatter(&|y| format!("{:.1e}", y))
-        .y_desc("Average Runtime in milliseconds / input size")
-        .x_label_formatter(&|x| format!("{:.0e}", x))
-        .x_desc("Input Size (logarithmic scale)")
-        .draw()
-        .unwrap();
-
-    for (i, experiment) in plot.experiments.iter().enumerate() {
-        chart
-            .draw_series(
-                LineSeries::new(
-                    plot.input_sizes
-                        .iter()
-                        .zip(experiment.run_times.clone())
-                        .map(|(x, run_time)| (*x, run_time)),
-                    colors[i],
-                ).point_size(2),
-            )
-            .unwrap()
-            .label(experiment.name.clone())
-            .legend({
-                let color = colors[i];
-                move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color)
-            });
+/**
+    The points on the boundary of a hull that are not needed to describe it: input points inside a
+    hull edge and hull vertices between two collinear edges.
+*/
+pub fn collinear_points(hull: &PointVector, input: &PointVector) -> Vec<Point> {
+    let mut collinear: Vec<Point> = hull
+        .points
+        .windows(3)
+        .filter(|triple| turn_type(triple[0], triple[1], triple[2]) == TurnType::Straight)
+        .map(|triple| triple[1])
+        .collect();
+    for edge in hull.points.windows(2) {
+        collinear.extend(
+            input
+                .points
+                .iter()
+                .filter(|p| is_inside_segment(edge[0], edge[1], **p)),
+        );
     }
+    collinear.sort();
+    collinear.dedup();
+    collinear
+}
 
-    chart
-        .configure_series_labels()
-        .position(SeriesLabelPosition::UpperLeft)
-        .border_style(BLACK)
-        .background_style(WHITE.mix(0.75))
-        .draw()
-        .unwrap();
+// the leftmost, rightmost, lowest and highest point with their labels, ties are broken by the
+// other coordinate and a point that is extreme in several directions gets all labels
+pub fn extreme_points(input: &PointVector) -> Vec<(String, Point)> {
+    let points = input.points.iter();
+    let extremes = [
+        ("min x", points.clone().min_by_key(|p| (p.x, p.y))),
+        ("max x", points.clone().max_by_key(|p| (p.x, p.y))),
+        ("min y", points.clone().min_by_key(|p| (p.y, p.x))),
+        ("max y", points.clone().max_by_key(|p| (p.y, p.x))),
+    ];
+    let mut labelled: Vec<(String, Point)> = vec![];
+    for (label, point) in extremes.into_iter() {
+        let Some(&point) = point else { continue };
+        match labelled.iter_mut().find(|(_, p)| *p == point) {
+            Some((labels, _)) => *labels = format!("{}, {}", labels, label),
+            None => labelled.push((label.to_string(), point)),
+        }
+    }
+    labelled
+}
+
+// the hull vertices of each output (by index) that are not a hull vertex in every other output
+pub fn disagreements(outputs: &[HullOutput]) -> Vec<(usize, Point)> {
+    let mut disagreements = vec![];
+    for (i, output) in outputs.iter().enumerate() {
+        for vertex in output.vertices() {
+            if outputs
+                .iter()
+                .any(|other| other.vertices().all(|v| v != vertex))
+                && !disagreements.contains(&(i, *vertex))
+            {
+                disagreements.push((i, *vertex));
+            }
+        }
+    }
+    disagreements
+}
+
+/**
+    All input points with the hulls of one or more algorithms on top, each algorithm in its own
+    colour with a solid upper and a dashed lower hull. Vertices that not all algorithms agree on
+    are marked by a cross. The axes are fitted to the input.
+*/
+pub struct HullPlot<'a> {
+    pub title: &'a str,
+    pub input: &'a PointVector,
+    pub outputs: &'a [HullOutput],
+    // labels the leftmost, rightmost, lowest and highest point with their coordinates
+    pub label_extremes: bool,
+    // marks duplicate input points and collinear points on the hulls
+    pub highlight_degeneracies: bool,
+    pub font: &'a str,
+}
 
-    root.present().unwrap();
+// range of the coordinates with the given space (relative to the range) below and above them
+pub(crate) fn axis_range(
+    values: impl Iterator<Item = i64>,
+    below: f64,
+    above: f64,
+) -> std::ops::Range<f64> {
+    let (min, max) = values.fold((f64::MAX, f64::MIN), |(min, max), v| {
+        (min.min(v as f64), max.max(v as f64))
+    });
+    if min > max {
+        return 0.0..1.0;
+    }
+    let length = (max - min).max(1.0);
+    min - length * below..max + length * above
+}
+
+pub(crate) fn coordinates(p: &Point) -> (f64, f64) {
+    (p.x as f64, p.y as f64)
 }
 
-pub fn plot_upper_hull_points(plot: Plot) {
-    let colors = [&RED, &GREEN, &BLUE, &ORANGE, &BLACK];
-
-    let x_first = (plot.input_sizes[0] as f64 * 0.8) as i64;
-    let x_last = (plot.input_sizes[plot.input_sizes.len() - 1] as f64 * 1.2) as i64;
-
-    let root = BitMapBackend::new(plot.path.as_str(), (640, 480)).into_drawing_area();
-    let _ = root.fill(&WHITE);
-    let root = root.margin(10, 10, 10, 10);
-
-    let mut chart = ChartBuilder::on(&root)
-        .caption(plot.title, ("sans-serif", 26).into_font())
-        .x_label_area_size(30)
-        .y_label_area_size(55)
-        .build_cartesian_2d((x_first..x_last).log_scale(), plot.y_range.log_scale())
-        .unwrap();
-
-    chart
-        .configure_mesh()
-        .max_light_lines(0)
-        .y_desc("Points on Upper Hull (logarithmic scale)")
-        .x_label_formatter(&|x| format!("{:.0e}", x))
-        .x_desc("Input Size (logarithmic scale)")
-        .draw()
-        .unwrap();
-
-    for (i, experiment) in plot.experiments.iter().enumerate() {
+impl Drawing for HullPlot<'_> {
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let font = font_family(self.font);
+        root.fill(&WHITE)?;
+        let root = root.margin(10, 10, 10, 10);
+
+        let mut chart = ChartBuilder::on(&root)
+            .caption(self.title, (font, 26).into_font())
+            .x_label_area_size(35)
+            .y_label_area_size(55)
+            .build_cartesian_2d(
+                axis_range(self.input.points.iter().map(|p| p.x), 0.05, 0.05),
+                // room for the legend above the points
+                axis_range(self.input.points.iter().map(|p| p.y), 0.05, 0.35),
+            )?;
+
+        chart
+            .configure_mesh()
+            .max_light_lines(0)
+            .label_style((font, 12))
+            .axis_desc_style((font, 15))
+            .y_desc("Y")
+            .x_desc("X")
+            .draw()?;
+
         chart
             .draw_series(
-                LineSeries::new(
-                    plot.input_sizes
+                self.input
+                    .points
+                    .iter()
+                    .map(|p| Circle::new(coordinates(p), 2, GREY.filled())),
+            )?
+            .label(format!("input ({} points)", self.input.points.len()))
+            .legend(|(x, y)| Circle::new((x + 10, y), 2, GREY.filled()));
+
+        for (i, output) in self.outputs.iter().enumerate() {
+            let color = color(i);
+            // later outputs are drawn thinner, so that the earlier ones stay visible below them
+            let width = 2 * (self.outputs.len() - i) as u32 - 1;
+            chart.draw_series(DashedLineSeries::new(
+                output.lower.points.iter().map(coordinates),
+                6,
+                4,
+                color.stroke_width(width),
+            ))?;
+            chart
+                .draw_series(
+                    LineSeries::new(
+                        output.upper.points.iter().map(coordinates),
+                        color.stroke_width(width),
+                    )
+                    .point_size(width + 1),
+                )?
+                .label(format!(
+                    "{} ({} upper, {} lower)",
+                    output.name,
+                    output.upper.points.len(),
+                    output.lower.points.len()
+                ))
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        }
+
+        let disagreements = disagreements(self.outputs);
+        if !disagreements.is_empty() {
+            chart
+                .draw_series(
+                    disagreements
                         .iter()
-                        .zip(experiment.run_times.clone())
-                        .map(|(x, run_time)| (*x, run_time)),
-                    colors[i],
-                ), //.point_size(2),
-            )
-            .unwrap()
-            .label(experiment.name.clone())
-            .legend({
-                let color = colors[i];
-                move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color)
-            });
-    }
+                        .map(|(i, p)| Cross::new(coordinates(p), 6, color(*i).stroke_width(2))),
+                )?
+                .label("not a vertex in every output")
+                .legend(|(x, y)| Cross::new((x + 10, y), 4, BLACK.stroke_width(2)));
+        }
+
+        if self.highlight_degeneracies {
+            let duplicates = duplicate_points(self.input);
+            if !duplicates.is_empty() {
+                chart
+                    .draw_series(
+                        duplicates.iter().map(|p| {
+                            TriangleMarker::new(coordinates(p), 6, MAGENTA.stroke_width(2))
+                        }),
+                    )?
+                    .label("duplicate")
+                    .legend(|(x, y)| TriangleMarker::new((x + 10, y), 4, MAGENTA));
+            }
+
+            let mut collinear: Vec<Point> = self
+                .outputs
+                .iter()
+                .flat_map(|o| {
+                    collinear_points(&o.upper, self.input)
+                        .into_iter()
+                        .chain(collinear_points(&o.lower, self.input))
+                })
+                .collect();
+            collinear.sort();
+            collinear.dedup();
+            if !collinear.is_empty() {
+                chart
+                    .draw_series(
+                        collinear
+                            .iter()
+                            .map(|p| Circle::new(coordinates(p), 6, PURPLE.stroke_width(2))),
+                    )?
+                    .label("collinear on a hull")
+                    .legend(|(x, y)| Circle::new((x + 10, y), 4, PURPLE.stroke_width(2)));
+            }
+        }
 
-    chart
-        .configure_series_labels()
-        .position(SeriesLabelPosition::UpperLeft)
-        .border_style(BLACK)
-        .background_style(WHITE.mix(0.75))
-        .draw()
-        .unwrap();
+        if self.label_extremes {
+            let x_range = chart.x_range();
+            let middle = (x_range.start + x_range.end) / 2.0;
+            chart.draw_series(extreme_points(self.input).into_iter().map(|(label, p)| {
+                // labels of points in the right half extend to the left, so they stay inside
+                let (offset, anchor) = if (p.x as f64) < middle {
+                    (6, HPos::Left)
+                } else {
+                    (-6, HPos::Right)
+                };
+                EmptyElement::at(coordinates(&p))
+                    + Circle::new((0, 0), 4, BLACK.filled())
+                    + Text::new(
+                        format!("{} ({}, {})", label, p.x, p.y),
+                        (offset, -14),
+                        TextStyle::from((font, 12).into_font()).pos(Pos::new(anchor, VPos::Top)),
+                    )
+            }))?;
+        }
 
-    root.present().unwrap();
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperRight)
+            .label_font((font, 12))
+            .border_style(BLACK)
+            .background_style(WHITE.mix(0.75))
+            .draw()?;
+        Ok(())
+    }
+}
+
+// draws the hull plot into a PNG or SVG file depending on the extension of the path
+pub fn plot_hulls(path: &str, size: (u32, u32), plot: &HullPlot) -> Result<(), Box<dyn Error>> {
+    render_file(path, size, plot)
 }
 
-pub fn plot_upper_hull(points: PointVector) {
-    let root =
-        BitMapBackend::new("project_2/plots/upper_hull_us_gs.png", (480, 480)).into_drawing_area();
-    let _ = root.fill(&WHITE);
-    let root = root.margin(10, 10, 10, 10);
-
-    let mut chart = ChartBuilder::on(&root)
-        .caption(
-            "Uniform Square - Grahams Scan",
-            ("sans-serif", 26).into_font(),
-        )
-        .x_label_area_size(35)
-        .y_label_area_size(35)
-        .build_cartesian_2d(-25i64..525i64, -25i64..525i64)
-        .unwrap();
-
-    chart
-        .configure_mesh()
-        .y_desc("Y")
-        .x_desc("X")
-        .draw()
-        .unwrap();
-
-    chart
-        .draw_series(
-            LineSeries::new(points.points.iter().map(|Point { x, y }| (*x, *y)), &BLACK)
-                .point_size(2),
-        )
-        .unwrap();
-
-    root.present().unwrap();
+#[cfg(test)]
+mod tests {
+    use crate::gift_wrapping::gift_wrapping_upper_hull;
+    use crate::grahams_scan::grahams_scan;
+    use crate::plotting::{
+        collinear_points, disagreements, duplicate_points, extreme_points, HullOutput, HullPlot,
+    };
+    use crate::types::{Point, PointVector};
+    use benchmark::plotting::render_svg;
+
+    fn points(coordinates: &[(i64, i64)]) -> PointVector {
+        PointVector {
+            points: coordinates.iter().map(|&(x, y)| Point { x, y }).collect(),
+        }
+    }
+
+    // a square with a duplicate corner, a point inside the top edge and one in the middle
+    fn square() -> PointVector {
+        points(&[(0, 0), (4, 4), (2, 4), (0, 4), (4, 0), (2, 2), (4, 4)])
+    }
+
+    #[test]
+    fn degeneracies() {
+        let input = square();
+        assert_eq!(duplicate_points(&input), vec![Point { x: 4, y: 4 }]);
+
+        let hull = points(&[(0, 0), (0, 4), (2, 4), (4, 4)]);
+        assert_eq!(collinear_points(&hull, &input), vec![Point { x: 2, y: 4 }]);
+        let hull = points(&[(0, 4), (4, 4)]);
+        assert_eq!(collinear_points(&hull, &input), vec![Point { x: 2, y: 4 }]);
+    }
+
+    #[test]
+    fn extremes() {
+        let extremes = extreme_points(&square());
+        assert_eq!(
+            extremes,
+            vec![
+                ("min x, min y".to_string(), Point { x: 0, y: 0 }),
+                ("max x, max y".to_string(), Point { x: 4, y: 4 }),
+            ]
+        );
+        assert!(extreme_points(&points(&[])).is_empty());
+    }
+
+    #[test]
+    fn disagreeing_outputs() {
+        let input = square();
+        let graham = HullOutput::compute("graham", grahams_scan, &input, None);
+        let mut wrong = graham.clone();
+        wrong.upper.points.insert(1, Point { x: 2, y: 2 });
+
+        assert!(disagreements(&[graham.clone(), graham.clone()]).is_empty());
+        assert_eq!(
+            disagreements(&[graham, wrong]),
+            vec![(1, Point { x: 2, y: 2 })]
+        );
+    }
+
+    #[test]
+    fn renders_overlay() {
+        let input = square();
+        let outputs = [
+            HullOutput::compute("Grahams Scan", grahams_scan, &input, None),
+            HullOutput::compute("Gift Wrapping", gift_wrapping_upper_hull, &input, None),
+        ];
+        let plot = HullPlot {
+            title: "Square",
+            input: &input,
+            outputs: &outputs,
+            label_extremes: true,
+            highlight_degeneracies: true,
+            font: "sans-serif",
+        };
+        let svg = render_svg((480, 480), &plot).unwrap();
+        assert!(svg.contains("Grahams Scan"));
+        assert!(svg.contains("min x, min y (0, 0)"));
+        assert!(svg.contains("duplicate"));
+    }
 }
@@ -1,5 +1,3 @@
-use std::ops::Range;
-
 // data types for input generation
 #[derive(Debug, PartialEq, Copy, Clone, Ord, Eq, PartialOrd)]
 pub struct Point {
@@ -34,28 +32,45 @@ pub enum Side {
     Right,
 }
 
+// a step of a hull algorithm, recorded to animate or debug it
+#[derive(Debug, PartialEq, Clone)]
+pub enum HullEvent {
+    // Graham's scan: a point is pushed on the hull, turn is the turn of the last two hull points
+    // and the new point (None if there are fewer than two)
+    Push {
+        point: Point,
+        turn: Option<TurnType>,
+    },
+    // Graham's scan: a point is popped since it does not make a right turn with the new point
+    Pop {
+        point: Point,
+        turn: TurnType,
+    },
+    // gift wrapping: the candidate for the next hull point changes
+    Pivot(Point),
+    // gift wrapping: the candidate has all other points to its right and is added to the hull
+    HullPoint(Point),
+    // parallel Graham's scan: the upper hull of one split of the input
+    SubHull {
+        index: usize,
+        hull: PointVector,
+    },
+    // parallel Graham's scan: a tangent between two sub-hulls that may become a bridge
+    TangentCandidate {
+        left: Point,
+        right: Point,
+    },
+    // parallel Graham's scan: the tangent that joins two sub-hulls in the final hull
+    Bridge {
+        left: Point,
+        right: Point,
+    },
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Errors {
     LowerHullError,
 }
 
 // result collection
-pub type InputFunction = fn(i64) -> PointVector;
 pub type ConvexHullAlgorithm = fn(PointVector, Option<bool>, Option<usize>) -> PointVector;
-
-#[derive(Debug, PartialEq, Clone)]
-pub struct Plot {
-    pub title: String,
-    pub path: String,
-    pub experiments: Vec<Experiment>,
-    pub input_sizes: Vec<i64>,
-    pub algorithm: ConvexHullAlgorithm,
-    pub args: (Option<bool>, Option<usize>),
-    pub y_range: Range<f64>,
-}
-
-#[derive(Debug, PartialEq, Clone)]
-pub struct Experiment {
-    pub name: String,
-    pub run_times: Vec<f64>,
-}
@@ -1,7 +1,9 @@
-use crate::types::{Point, PointVector, Side, TurnType};
+use crate::types::{ConvexHullAlgorithm, Point, PointVector, Side, TurnType};
+use benchmark::counters::{count, Counter};
 use std::cmp::Ordering;
 
 pub fn turn_type(p1: Point, p2: Point, p3: Point) -> TurnType {
+    count(Counter::OrientationTests, 1);
     // determinant
     let determinant = p1.x as i128 * (p2.y as i128 - p3.y as i128)
         + p2.x as i128 * (p3.y as i128 - p1.y as i128)
@@ -22,11 +24,95 @@ pub fn get_point_side(hull: &PointVector, point: Point) -> Side {
     }
 }
 
-#[cfg(test)]
+fn mirrored(input: &PointVector) -> PointVector {
+    PointVector {
+        points: input
+            .points
+            .iter()
+            .map(|Point { x, y }| Point { x: *x, y: -y })
+            .collect(),
+    }
+}
+
+/**
+    The lower hull from left to right computed with an upper hull algorithm: the upper hull of the
+    points mirrored at the x axis is the mirrored lower hull.
+*/
+pub fn lower_hull(
+    algorithm: ConvexHullAlgorithm,
+    input: &PointVector,
+    threads: Option<usize>,
+) -> PointVector {
+    mirrored(&algorithm(mirrored(input), Some(true), threads))
+}
 
+// whether the point lies on the segment from start to end, but is neither of its endpoints
+pub fn is_inside_segment(start: Point, end: Point, point: Point) -> bool {
+    point != start
+        && point != end
+        && turn_type(start, end, point) == TurnType::Straight
+        && start.x.min(end.x) <= point.x
+        && point.x <= start.x.max(end.x)
+        && start.y.min(end.y) <= point.y
+        && point.y <= start.y.max(end.y)
+}
+
+#[cfg(test)]
 mod tests {
+    use crate::grahams_scan::grahams_scan;
+    use crate::input_generation::InverseCurve;
     use crate::types::{Point, PointVector, Side, TurnType};
-    use crate::utils::{get_point_side, turn_type};
+    use crate::utils::{get_point_side, is_inside_segment, lower_hull, turn_type};
+    use benchmark::counters::{counted_locally, Operations};
+
+    #[test]
+    fn counted_orientation_tests() {
+        let input = InverseCurve::get_input(100);
+        let (hull, operations) = counted_locally(|| grahams_scan(input, Some(true), None));
+        // on the parabola every new point pops the one before after a single test
+        assert_eq!(
+            operations,
+            Operations {
+                orientation_tests: 98,
+                ..Operations::default()
+            }
+        );
+        assert_eq!(hull.points.len(), 2);
+    }
+
+    #[test]
+    fn lower_hull_of_mirrored_points() {
+        let input = PointVector {
+            points: vec![
+                Point { x: 0, y: 0 },
+                Point { x: 2, y: -2 },
+                Point { x: 1, y: 5 },
+                Point { x: 4, y: 0 },
+                Point { x: 3, y: 1 },
+            ],
+        };
+        assert_eq!(
+            lower_hull(grahams_scan, &input, None).points,
+            vec![
+                Point { x: 0, y: 0 },
+                Point { x: 2, y: -2 },
+                Point { x: 4, y: 0 }
+            ]
+        );
+        // every point of an upwards curve is on its lower hull
+        let curve = InverseCurve::get_input(10);
+        assert_eq!(lower_hull(grahams_scan, &curve, None), curve);
+    }
+
+    #[test]
+    fn inside_segment() {
+        let start = Point { x: 0, y: 0 };
+        let end = Point { x: 4, y: 2 };
+        assert!(is_inside_segment(start, end, Point { x: 2, y: 1 }));
+        assert!(!is_inside_segment(start, end, start));
+        assert!(!is_inside_segment(start, end, Point { x: 6, y: 3 }));
+        assert!(!is_inside_segment(start, end, Point { x: 2, y: 2 }));
+    }
 
     #[test]
     fn left_turn() {
@@ -0,0 +1,176 @@
+use crate::gift_wrapping::gift_wrapping_upper_hull;
+use crate::grahams_scan::grahams_scan;
+use crate::grahams_scan_parallel::grahams_scan_parallel;
+use crate::input_generation::{Curve, InverseCurve, Line, UniformCircle, UniformSquare};
+use crate::types::{ConvexHullAlgorithm, Point, PointVector};
+use benchmark::runner::{Sample, Workload};
+use std::time::Instant;
+
+const ALGORITHMS: [(&str, &str, ConvexHullAlgorithm); 3] = [
+    ("grahams_scan", "Grahams Scan", grahams_scan),
+    ("gift_wrapping", "Gift Wrapping", gift_wrapping_upper_hull),
+    (
+        "grahams_scan_parallel",
+        "Parallel Grahams Scan",
+        grahams_scan_parallel,
+    ),
+];
+
+// the generators get the seed of the run, the deterministic ones ignore it
+type SeededInputFunction = fn(i64, u64) -> PointVector;
+
+const GENERATORS: [(&str, &str, SeededInputFunction); 5] = [
+    (
+        "uniform_square",
+        "Uniform Square",
+        UniformSquare::get_input_with_seed,
+    ),
+    (
+        "uniform_circle",
+        "Uniform Circle",
+        UniformCircle::get_input_with_seed,
+    ),
+    ("curve", "Downwards Curve", |n, _| Curve::get_input(n)),
+    ("inverse_curve", "Upwards Curve", |n, _| {
+        InverseCurve::get_input(n)
+    }),
+    ("line", "Line", |n, _| Line::get_input(n)),
+];
+
+// the upper hull algorithm of the given name of a spec
+pub fn find_algorithm(name: &str) -> Option<ConvexHullAlgorithm> {
+    ALGORITHMS.iter().find(|a| a.0 == name).map(|a| a.2)
+}
+
+// the points of the given generator name of a spec, the same seed gives the same points
+pub fn generate(generator: &str, size: usize, seed: u64) -> Option<PointVector> {
+    let (_, _, generator) = GENERATORS.iter().find(|g| g.0 == generator)?;
+    Some(generator(size as i64, seed))
+}
+
+/**
+    Benchmark workload of project_2. The default task "hull" runs the upper hull algorithms on the
+    generated points, the task "presorted" sorts the points by x before the algorithm is timed so
+    that it can skip its own sorting step.
+*/
+pub struct HullWorkload();
+
+impl Workload for HullWorkload {
+    fn algorithms(&self, task: Option<&str>) -> Vec<String> {
+        match task.unwrap_or("hull") {
+            "hull" | "presorted" => ALGORITHMS.iter().map(|a| a.0.to_string()).collect(),
+            _ => vec![],
+        }
+    }
+
+    fn generators(&self, task: Option<&str>) -> Vec<String> {
+        match task.unwrap_or("hull") {
+            "hull" | "presorted" => GENERATORS.iter().map(|g| g.0.to_string()).collect(),
+            _ => vec![],
+        }
+    }
+
+    fn label(&self, name: &str) -> String {
+        ALGORITHMS
+            .iter()
+            .map(|(n, label, _)| (n, label))
+            .chain(GENERATORS.iter().map(|(n, label, _)| (n, label)))
+            .find(|(n, _)| **n == name)
+            .map_or(name.to_string(), |(_, label)| label.to_string())
+    }
+
+    fn run(
+        &self,
+        task: Option<&str>,
+        algorithm: &str,
+        generator: &str,
+        size: usize,
+        threads: usize,
+        seed: u64,
+    ) -> Sample {
+        let algorithm = find_algorithm(algorithm).unwrap();
+        let mut input = generate(generator, size, seed).unwrap();
+        let presorted = task == Some("presorted");
+        if presorted {
+            input.points.sort_by_key(|Point { x, y: _ }| *x);
+        }
+
+        let now = Instant::now();
+        let hull = algorithm(input, Some(!presorted), Some(threads));
+        let seconds = now.elapsed().as_secs_f64();
+
+        Sample {
+            seconds,
+            output_size: Some(hull.points.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::workload::HullWorkload;
+    use benchmark::runner::{run_experiment, Workload};
+    use benchmark::spec::BenchmarkSpec;
+
+    #[test]
+    fn runs_every_algorithm_and_generator() {
+        let spec = BenchmarkSpec::parse(
+            r#"
+            [[experiment]]
+            name = "hull"
+            algorithms = ["all"]
+            generators = ["all"]
+            sizes = [1000]
+            threads = [1, 4]
+            repetitions = 1
+
+            [[experiment]]
+            name = "presorted"
+            task = "presorted"
+            algorithms = ["grahams_scan_parallel"]
+            generators = ["uniform_square"]
+            sizes = [1000]
+            threads = [1, 4]
+            repetitions = 1
+            "#,
+        )
+        .unwrap();
+
+        let workload = HullWorkload();
+        let hull = run_experiment(&workload, &spec.experiments[0]).unwrap();
+        assert_eq!(hull.len(), 3 * 5 * 2);
+        assert!(hull.iter().all(|m| m.output_size.unwrap() >= 2));
+
+        let presorted = run_experiment(&workload, &spec.experiments[1]).unwrap();
+        assert_eq!(presorted.len(), 2);
+        assert_eq!(workload.label("inverse_curve"), "Upwards Curve");
+    }
+
+    #[test]
+    fn spec_files_are_valid() {
+        let benchmarks = BenchmarkSpec::parse(include_str!("../benchmarks.toml")).unwrap();
+        let hull_size = BenchmarkSpec::parse(include_str!("../hull_size.toml")).unwrap();
+        let workload = HullWorkload();
+        for experiment in benchmarks
+            .experiments
+            .into_iter()
+            .chain(hull_size.experiments)
+        {
+            let task = experiment.task.as_deref();
+            for algorithm in experiment.algorithms.iter() {
+                assert!(
+                    workload.algorithms(task).contains(algorithm),
+                    "{}",
+                    algorithm
+                );
+            }
+            for generator in experiment.generators.iter() {
+                assert!(
+                    workload.generators(task).contains(generator),
+                    "{}",
+                    generator
+                );
+            }
+        }
+    }
+}